@@ -0,0 +1,37 @@
+use ckb_std::{dynamic_loading, error::SysError};
+
+/// Errors of finding and loading a library, shared by the `ckb-lib-*` crates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadError {
+    /// no cell dep with the library's code hash
+    LibraryNotFound,
+    /// the library doesn't export a symbol we need
+    SymbolMissing,
+    /// the `CKBDLContext` is too small for the library
+    InsufficientMemory,
+    /// the library isn't a loadable ELF
+    InvalidLibrary,
+    /// a syscall failed while looking for the library
+    Syscall,
+}
+
+impl From<dynamic_loading::Error> for LoadError {
+    fn from(err: dynamic_loading::Error) -> Self {
+        use dynamic_loading::Error::*;
+        match err {
+            CellNotFound => LoadError::LibraryNotFound,
+            MemoryNotEnough => LoadError::InsufficientMemory,
+            ContextFailure | InvalidElf | InvalidAlign => LoadError::InvalidLibrary,
+            Sys(_) => LoadError::Syscall,
+        }
+    }
+}
+
+impl From<SysError> for LoadError {
+    fn from(err: SysError) -> Self {
+        match err {
+            SysError::IndexOutOfBound => LoadError::LibraryNotFound,
+            _ => LoadError::Syscall,
+        }
+    }
+}
@@ -0,0 +1,19 @@
+#![no_std]
+
+mod error;
+mod locator;
+
+pub use error::LoadError;
+pub use locator::LibLocator;
+
+/// A signature scheme implemented by a dynamically loaded library.
+///
+/// Every verifier reduces the signer to a 20-byte identity, the blake160 of
+/// its pubkey or its Ethereum address, so a lock can pick a verifier by auth
+/// kind at runtime and match the signer against the same records.
+pub trait DynamicVerifier {
+    type Error;
+
+    /// verify `signature` of `message` and return the signer's identity
+    fn verify_signer(&self, signature: &[u8], message: &[u8; 32]) -> Result<[u8; 20], Self::Error>;
+}
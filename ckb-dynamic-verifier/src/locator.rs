@@ -0,0 +1,40 @@
+use ckb_std::{
+    ckb_constants::Source,
+    high_level::{load_cell_data_hash, load_cell_type_hash},
+};
+
+use crate::error::LoadError;
+
+/// Where to find a library among the cell deps.
+pub enum LibLocator {
+    /// the dep with this data hash, e.g. the `CODE_HASH_*` of a bundled build
+    DataHash([u8; 32]),
+    /// the dep with this type script hash, e.g. a type id, so the library
+    /// can be upgraded without rebuilding its users
+    TypeHash([u8; 32]),
+    /// the dep at this index, the caller has to make sure it is the library
+    CellDep(usize),
+}
+
+impl LibLocator {
+    /// The data hash of the dep the locator points at, `CKBDLContext` finds
+    /// code by data hash only.
+    pub fn data_hash(&self) -> Result<[u8; 32], LoadError> {
+        match self {
+            LibLocator::DataHash(hash) => Ok(*hash),
+            LibLocator::TypeHash(hash) => Ok(load_cell_data_hash(find_by_type_hash(hash)?, Source::CellDep)?),
+            LibLocator::CellDep(index) => Ok(load_cell_data_hash(*index, Source::CellDep)?),
+        }
+    }
+}
+
+fn find_by_type_hash(type_hash: &[u8; 32]) -> Result<usize, LoadError> {
+    for i in 0.. {
+        match load_cell_type_hash(i, Source::CellDep) {
+            Ok(Some(hash)) if hash == *type_hash => return Ok(i),
+            Ok(_) => {},
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Err(LoadError::LibraryNotFound)
+}
@@ -0,0 +1,182 @@
+//! Build script support shared by the `ckb-lib-*` crates.
+//!
+//! Each crate lists its dual-mode library binaries in `libraries.manifest`,
+//! and its `build.rs` calls `generate_code_hashes`, which checks every binary
+//! against its expected data hash and generates `src/code_hashes.rs` with one
+//! `CODE_HASH_<NAME>` constant per library.
+use blake2b_rs::{Blake2b, Blake2bBuilder};
+
+use std::{
+    env,
+    fs::{self, File},
+    io::Read,
+    path::Path,
+};
+
+const BUF_SIZE: usize = 8 * 1024;
+const CKB_HASH_PERSONALIZATION: &[u8] = b"ckb-default-hash";
+
+const MANIFEST: &str = "libraries.manifest";
+const OUT_PATH: &str = "src/code_hashes.rs";
+/// set to skip checking the binaries, the expected hashes are used as is
+pub const SKIP_CHECK_ENV: &str = "CKB_LIB_SKIP_BINARY_CHECK";
+
+/// A line of `libraries.manifest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Library {
+    /// upper case, the constant is `CODE_HASH_<name>`
+    pub name: String,
+    /// relative to the manifest's directory
+    pub path: String,
+    pub hash: [u8; 32],
+}
+
+/// What checking the binaries of a manifest gives: one code hash per
+/// library, in manifest order, and the warnings to show.
+#[derive(Debug, Default)]
+pub struct CodeHashes {
+    pub hashes: Vec<(String, [u8; 32])>,
+    pub warnings: Vec<String>,
+}
+
+impl CodeHashes {
+    /// the generated `code_hashes.rs`
+    pub fn to_code(&self) -> String {
+        let mut code = String::new();
+        for (name, hash) in &self.hashes {
+            code.push_str(&format!("pub const CODE_HASH_{}: [u8; 32] = {:?};\n", name, hash));
+        }
+        code
+    }
+}
+
+/// Check the binaries listed in `libraries.manifest` and generate
+/// `src/code_hashes.rs`, panics on a missing binary or a hash mismatch.
+pub fn generate_code_hashes() {
+    println!("cargo:rerun-if-changed={}", MANIFEST);
+    println!("cargo:rerun-if-env-changed={}", SKIP_CHECK_ENV);
+    let skip_check = env::var_os(SKIP_CHECK_ENV).is_some();
+
+    let manifest = fs::read_to_string(MANIFEST).expect("read libraries.manifest");
+    let libraries = parse_manifest(&manifest).unwrap_or_else(|err| panic!("{}", err));
+    for library in &libraries {
+        println!("cargo:rerun-if-changed={}", library.path);
+    }
+    let code_hashes = check_libraries(Path::new("."), &libraries, skip_check).unwrap_or_else(|err| panic!("{}", err));
+    for warning in &code_hashes.warnings {
+        println!("cargo:warning={}", warning);
+    }
+
+    let code = code_hashes.to_code();
+    // only touch the file when it changes, so it doesn't trigger a rebuild
+    if fs::read_to_string(OUT_PATH).ok().as_deref() != Some(code.as_str()) {
+        fs::write(OUT_PATH, code).expect("write code_hashes.rs");
+    }
+}
+
+/// The libraries of a manifest, one `name path hash` line each, blank lines
+/// and `#` comments skipped.
+pub fn parse_manifest(manifest: &str) -> Result<Vec<Library>, String> {
+    manifest
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(number, line)| parse_line(number + 1, line))
+        .collect()
+}
+
+/// Hash the binaries of `libraries`, relative to `dir`, against their
+/// expected hashes. A missing binary or a mismatch is an error listing every
+/// one of them, or with `skip_check` a warning, the expected hash is used.
+pub fn check_libraries(dir: &Path, libraries: &[Library], skip_check: bool) -> Result<CodeHashes, String> {
+    let mut errors = Vec::new();
+    let mut code_hashes = CodeHashes::default();
+    for library in libraries {
+        let path = dir.join(&library.path);
+        let actual = if path.exists() { Some(hash_file(&path)) } else { None };
+        let error = match actual {
+            Some(actual) if actual == library.hash => None,
+            Some(actual) => Some(format!(
+                "{}: binary {} hashes to 0x{}, but {} expects 0x{}",
+                library.name,
+                library.path,
+                to_hex(&actual),
+                MANIFEST,
+                to_hex(&library.hash)
+            )),
+            None => Some(format!("{}: binary {} is missing", library.name, library.path)),
+        };
+        code_hashes.hashes.push((library.name.clone(), library.hash));
+        match error {
+            Some(error) if skip_check => code_hashes.warnings.push(error),
+            Some(error) => errors.push(error),
+            None => {},
+        }
+    }
+    if !errors.is_empty() {
+        return Err(format!(
+            "\n{}\nbuild the libraries (`make` in ckb-miscellaneous-scripts) or update {}, \
+             set {}=1 to build with the expected hashes anyway\n",
+            errors.join("\n"),
+            MANIFEST,
+            SKIP_CHECK_ENV
+        ));
+    }
+    Ok(code_hashes)
+}
+
+fn parse_line(number: usize, line: &str) -> Result<Library, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() != 3 {
+        return Err(format!("{}:{}: expected `name path hash`", MANIFEST, number));
+    }
+    Ok(Library {
+        name: fields[0].to_uppercase(),
+        path: fields[1].to_string(),
+        hash: parse_hash(number, fields[2])?,
+    })
+}
+
+fn parse_hash(number: usize, field: &str) -> Result<[u8; 32], String> {
+    let hex = field.trim_start_matches("0x");
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err(format!("{}:{}: the hash must be 32 bytes of hex", MANIFEST, number));
+    }
+    let mut hash = [0u8; 32];
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| format!("{}:{}: invalid hex in the hash", MANIFEST, number))?;
+    }
+    Ok(hash)
+}
+
+/// blake2b-256 of the file, with the ckb personalization, its data hash
+pub fn hash_file(path: &Path) -> [u8; 32] {
+    let mut buf = [0u8; BUF_SIZE];
+
+    // build hash
+    let mut blake2b = new_blake2b();
+    let mut fd = File::open(path).expect("open file");
+    loop {
+        let read_bytes = fd.read(&mut buf).expect("read file");
+        if read_bytes > 0 {
+            blake2b.update(&buf[..read_bytes]);
+        } else {
+            break;
+        }
+    }
+
+    let mut hash = [0u8; 32];
+    blake2b.finalize(&mut hash);
+    hash
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn new_blake2b() -> Blake2b {
+    Blake2bBuilder::new(32)
+        .personal(CKB_HASH_PERSONALIZATION)
+        .build()
+}
@@ -0,0 +1,44 @@
+use ckb_dynamic_verifier::LoadError;
+use ckb_std::dynamic_loading;
+
+/// Errors of the ownership library and of loading it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibError {
+    /// no cell dep with the library's code hash
+    LibraryNotFound,
+    /// the library doesn't export a symbol we need
+    SymbolMissing,
+    /// the `CKBDLContext` is too small for the library
+    InsufficientMemory,
+    /// the library isn't a loadable ELF
+    InvalidLibrary,
+    /// a syscall failed while looking for the library
+    Syscall,
+    /// the owner doesn't authorize the transaction, with the ownership
+    /// lock's error code, see its `error.rs`
+    Denied(i32),
+}
+
+impl From<i32> for LibError {
+    fn from(code: i32) -> Self {
+        LibError::Denied(code)
+    }
+}
+
+impl From<LoadError> for LibError {
+    fn from(err: LoadError) -> Self {
+        match err {
+            LoadError::LibraryNotFound => LibError::LibraryNotFound,
+            LoadError::SymbolMissing => LibError::SymbolMissing,
+            LoadError::InsufficientMemory => LibError::InsufficientMemory,
+            LoadError::InvalidLibrary => LibError::InvalidLibrary,
+            LoadError::Syscall => LibError::Syscall,
+        }
+    }
+}
+
+impl From<dynamic_loading::Error> for LibError {
+    fn from(err: dynamic_loading::Error) -> Self {
+        LoadError::from(err).into()
+    }
+}
@@ -0,0 +1,12 @@
+#![no_std]
+
+#[cfg(feature = "code-hash")]
+mod code_hashes;
+mod error;
+mod libownership;
+
+pub use ckb_dynamic_verifier::LibLocator;
+#[cfg(feature = "code-hash")]
+pub use code_hashes::CODE_HASH_DAO_OWNERSHIP;
+pub use error::LibError;
+pub use libownership::LibDaoOwnership;
@@ -0,0 +1,49 @@
+#[cfg(feature = "code-hash")]
+use crate::code_hashes::CODE_HASH_DAO_OWNERSHIP;
+use crate::error::LibError;
+use ckb_dynamic_verifier::LibLocator;
+use ckb_std::dynamic_loading::{CKBDLContext, Symbol};
+
+/// function signature of verify_puppet_owner
+type VerifyPuppetOwner = unsafe extern "C" fn(puppet_type_hash: *const u8) -> i32;
+
+/// Symbol name
+const VERIFY_PUPPET_OWNER: &[u8; 19] = b"verify_puppet_owner";
+
+/// The extended ownership lock built as a library.
+///
+/// The library keeps the secp256k1 library's 1 MB precomputed table in its
+/// own image, so the context has to be larger than that, 1280 KB is enough.
+pub struct LibDaoOwnership {
+    verify_puppet_owner: Symbol<VerifyPuppetOwner>,
+}
+
+impl LibDaoOwnership {
+    #[cfg(feature = "code-hash")]
+    pub fn try_load<T>(context: &mut CKBDLContext<T>) -> Result<Self, LibError> {
+        Self::try_load_by(context, &LibLocator::DataHash(CODE_HASH_DAO_OWNERSHIP))
+    }
+
+    pub fn try_load_by<T>(context: &mut CKBDLContext<T>, locator: &LibLocator) -> Result<Self, LibError> {
+        let data_hash = locator.data_hash()?;
+
+        // load library
+        let lib = context.load(&data_hash)?;
+
+        // find symbols
+        let verify_puppet_owner: Symbol<VerifyPuppetOwner> =
+            unsafe { lib.get(VERIFY_PUPPET_OWNER).ok_or(LibError::SymbolMissing)? };
+        Ok(LibDaoOwnership { verify_puppet_owner })
+    }
+
+    /// Check the owner of the puppet with `puppet_type_hash` authorizes the
+    /// current transaction, i.e. signed for the calling script.
+    pub fn verify_puppet_owner(&self, puppet_type_hash: &[u8; 32]) -> Result<(), LibError> {
+        let f = &self.verify_puppet_owner;
+        let error_code = unsafe { f(puppet_type_hash.as_ptr()) };
+        if error_code != 0 {
+            return Err(error_code.into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,8 @@
+use std::env;
+
+fn main() {
+    // the native verifier of the tests doesn't need the pinned code hash
+    if env::var_os("CARGO_FEATURE_CODE_HASH").is_some() {
+        ckb_lib_build::generate_code_hashes();
+    }
+}
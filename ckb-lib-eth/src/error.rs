@@ -0,0 +1,78 @@
+use ckb_dynamic_verifier::LoadError;
+use ckb_std::dynamic_loading;
+
+/// Errors of the keccak256 dual library and of loading it.
+///
+/// The library shares its error codes with the blake2b one, except that the
+/// pubkey hash is an Ethereum address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibError {
+    /// no cell dep with the library's code hash
+    LibraryNotFound,
+    /// the library doesn't export a symbol we need
+    SymbolMissing,
+    /// the `CKBDLContext` is too small for the library
+    InsufficientMemory,
+    /// the library isn't a loadable ELF
+    InvalidLibrary,
+    // error codes of the C library
+    ArgumentsLen,
+    Encoding,
+    Syscall,
+    SecpRecoverPubkey,
+    SecpVerification,
+    SecpParsePubkey,
+    SecpParseSignature,
+    SecpSerializePubkey,
+    ScriptTooLong,
+    WitnessSize,
+    EthAddress,
+    InvalidPrefilledDataSize,
+    InvalidSignatureSize,
+    InvalidMessageSize,
+    InvalidOutputSize,
+    /// a code the C library isn't known to return
+    Unknown(i32),
+}
+
+impl From<i32> for LibError {
+    fn from(code: i32) -> Self {
+        use LibError::*;
+        match code {
+            -1 => ArgumentsLen,
+            -2 => Encoding,
+            -3 => Syscall,
+            -11 => SecpRecoverPubkey,
+            -12 => SecpVerification,
+            -13 => SecpParsePubkey,
+            -14 => SecpParseSignature,
+            -15 => SecpSerializePubkey,
+            -21 => ScriptTooLong,
+            -22 => WitnessSize,
+            -31 => EthAddress,
+            -41 => InvalidPrefilledDataSize,
+            -42 => InvalidSignatureSize,
+            -43 => InvalidMessageSize,
+            -44 => InvalidOutputSize,
+            code => Unknown(code),
+        }
+    }
+}
+
+impl From<LoadError> for LibError {
+    fn from(err: LoadError) -> Self {
+        match err {
+            LoadError::LibraryNotFound => LibError::LibraryNotFound,
+            LoadError::SymbolMissing => LibError::SymbolMissing,
+            LoadError::InsufficientMemory => LibError::InsufficientMemory,
+            LoadError::InvalidLibrary => LibError::InvalidLibrary,
+            LoadError::Syscall => LibError::Syscall,
+        }
+    }
+}
+
+impl From<dynamic_loading::Error> for LibError {
+    fn from(err: dynamic_loading::Error) -> Self {
+        LoadError::from(err).into()
+    }
+}
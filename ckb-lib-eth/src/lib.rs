@@ -0,0 +1,16 @@
+#![no_std]
+
+#[cfg(feature = "code-hash")]
+mod code_hashes;
+mod error;
+mod libeth;
+#[cfg(feature = "native")]
+mod native;
+
+pub use ckb_dynamic_verifier::{DynamicVerifier, LibLocator};
+#[cfg(feature = "code-hash")]
+pub use code_hashes::CODE_HASH_ETH;
+pub use error::LibError;
+pub use libeth::{EthVerifier, LibEth, PrefilledData, ETH_ADDRESS_SIZE, SECP256K1_DATA_SIZE};
+#[cfg(feature = "native")]
+pub use native::NativeEthVerifier;
@@ -0,0 +1,146 @@
+#[cfg(feature = "code-hash")]
+use crate::code_hashes::CODE_HASH_ETH;
+use crate::error::LibError;
+use ckb_dynamic_verifier::{DynamicVerifier, LibLocator};
+use ckb_std::dynamic_loading::{CKBDLContext, Symbol};
+
+/// function signature of validate_secp256k1_keccak256_sighash_all
+type ValidateKeccak256SighashAll = unsafe extern "C" fn(eth_address: *const u8) -> i32;
+/// function signature of validate_signature
+type ValidateSignature = unsafe extern "C" fn(
+    prefilled_data: *const u8,
+    signature_buffer: *const u8,
+    signature_size: u64,
+    message_buffer: *const u8,
+    message_size: u64,
+    output: *mut u8,
+    output_len: *mut u64,
+) -> i32;
+
+/// function signature of load_prefilled_data
+type LoadPrefilledData = unsafe extern "C" fn(data: *mut u8, len: *mut u64) -> i32;
+
+/// Symbol name
+const VALIDATE_KECCAK256_SIGHASH_ALL: &[u8; 40] = b"validate_secp256k1_keccak256_sighash_all";
+const VALIDATE_SIGNATURE: &[u8; 18] = b"validate_signature";
+const LOAD_PREFILLED_DATA: &[u8; 19] = b"load_prefilled_data";
+
+/// size of the precomputed table `load_prefilled_data` fills
+pub const SECP256K1_DATA_SIZE: usize = 1048576;
+/// last 20 bytes of the keccak256 hash of the uncompressed pubkey
+pub const ETH_ADDRESS_SIZE: usize = 20;
+
+/// The library's precomputed table, in a caller's buffer.
+pub struct PrefilledData(&'static mut [u8; SECP256K1_DATA_SIZE]);
+
+/// The `secp256k1_keccak256_sighash_all_dual` library, which checks
+/// Ethereum style signatures.
+pub struct LibEth {
+    validate_keccak256_sighash_all: Symbol<ValidateKeccak256SighashAll>,
+    validate_signature: Symbol<ValidateSignature>,
+    load_prefilled_data: Symbol<LoadPrefilledData>,
+}
+
+impl LibEth {
+    #[cfg(feature = "code-hash")]
+    pub fn try_load<T>(context: &mut CKBDLContext<T>) -> Result<Self, LibError> {
+        Self::try_load_by(context, &LibLocator::DataHash(CODE_HASH_ETH))
+    }
+
+    pub fn try_load_by<T>(context: &mut CKBDLContext<T>, locator: &LibLocator) -> Result<Self, LibError> {
+        let data_hash = locator.data_hash()?;
+
+        // load library
+        let lib = context.load(&data_hash)?;
+
+        // find symbols
+        let validate_keccak256_sighash_all: Symbol<ValidateKeccak256SighashAll> = unsafe {
+            lib.get(VALIDATE_KECCAK256_SIGHASH_ALL)
+                .ok_or(LibError::SymbolMissing)?
+        };
+        let validate_signature: Symbol<ValidateSignature> =
+            unsafe { lib.get(VALIDATE_SIGNATURE).ok_or(LibError::SymbolMissing)? };
+        let load_prefilled_data: Symbol<LoadPrefilledData> =
+            unsafe { lib.get(LOAD_PREFILLED_DATA).ok_or(LibError::SymbolMissing)? };
+        Ok(LibEth {
+            validate_keccak256_sighash_all,
+            validate_signature,
+            load_prefilled_data,
+        })
+    }
+
+    pub fn validate_keccak256_sighash_all(&self, eth_address: &[u8; ETH_ADDRESS_SIZE]) -> Result<(), LibError> {
+        let f = &self.validate_keccak256_sighash_all;
+        let error_code = unsafe { f(eth_address.as_ptr()) };
+        if error_code != 0 {
+            return Err(error_code.into());
+        }
+        Ok(())
+    }
+
+    /// Load the precomputed table into `buffer`, usually a `static mut`.
+    pub fn load_prefilled_data_into(
+        &self,
+        buffer: &'static mut [u8; SECP256K1_DATA_SIZE],
+    ) -> Result<PrefilledData, LibError> {
+        let mut len: u64 = SECP256K1_DATA_SIZE as u64;
+
+        let f = &self.load_prefilled_data;
+        let error_code = unsafe { f(buffer.as_mut_ptr(), &mut len as *mut u64) };
+        if error_code != 0 {
+            return Err(error_code.into());
+        }
+        Ok(PrefilledData(buffer))
+    }
+
+    /// Recover the address which made the 65-byte recoverable `signature`
+    /// of `message`, the recovery id may be 0/1 or 27/28.
+    pub fn recover_address(
+        &self,
+        prefilled_data: &PrefilledData,
+        signature: &[u8],
+        message: &[u8],
+    ) -> Result<[u8; ETH_ADDRESS_SIZE], LibError> {
+        let mut address = [0u8; ETH_ADDRESS_SIZE];
+        let mut len: u64 = address.len() as u64;
+
+        let f = &self.validate_signature;
+        let error_code = unsafe {
+            f(
+                prefilled_data.0.as_ptr(),
+                signature.as_ptr(),
+                signature.len() as u64,
+                message.as_ptr(),
+                message.len() as u64,
+                address.as_mut_ptr(),
+                &mut len as *mut u64,
+            )
+        };
+        if error_code != 0 {
+            return Err(error_code.into());
+        }
+        debug_assert_eq!(address.len() as u64, len);
+        Ok(address)
+    }
+}
+
+/// the library with its precomputed table loaded
+pub struct EthVerifier {
+    lib: LibEth,
+    prefilled_data: PrefilledData,
+}
+
+impl EthVerifier {
+    pub fn with_buffer(lib: LibEth, buffer: &'static mut [u8; SECP256K1_DATA_SIZE]) -> Result<Self, LibError> {
+        let prefilled_data = lib.load_prefilled_data_into(buffer)?;
+        Ok(EthVerifier { lib, prefilled_data })
+    }
+}
+
+impl DynamicVerifier for EthVerifier {
+    type Error = LibError;
+
+    fn verify_signer(&self, signature: &[u8], message: &[u8; 32]) -> Result<[u8; 20], LibError> {
+        self.lib.recover_address(&self.prefilled_data, signature, message)
+    }
+}
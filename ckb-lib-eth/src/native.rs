@@ -0,0 +1,50 @@
+use ckb_dynamic_verifier::DynamicVerifier;
+use secp256k1::{
+    recovery::{RecoverableSignature, RecoveryId},
+    Message, Secp256k1,
+};
+use tiny_keccak::{Hasher, Keccak};
+
+use crate::error::LibError;
+use crate::libeth::ETH_ADDRESS_SIZE;
+
+/// Pure Rust verifier for native unit tests, recovers the address like the
+/// dual library does.
+#[derive(Default)]
+pub struct NativeEthVerifier;
+
+impl NativeEthVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl DynamicVerifier for NativeEthVerifier {
+    type Error = LibError;
+
+    fn verify_signer(&self, signature: &[u8], message: &[u8; 32]) -> Result<[u8; 20], LibError> {
+        if signature.len() != 65 {
+            return Err(LibError::InvalidSignatureSize);
+        }
+        // Ethereum adds 27 to the recovery id
+        let v = match signature[64] {
+            v @ 27..=28 => v - 27,
+            v => v,
+        };
+        let recovery_id = RecoveryId::from_i32(v as i32).map_err(|_| LibError::SecpParseSignature)?;
+        let signature = RecoverableSignature::from_compact(&signature[..64], recovery_id)
+            .map_err(|_| LibError::SecpParseSignature)?;
+        let message = Message::from_slice(message).map_err(|_| LibError::InvalidMessageSize)?;
+        let pubkey = Secp256k1::verification_only()
+            .recover(&message, &signature)
+            .map_err(|_| LibError::SecpRecoverPubkey)?;
+
+        let mut hash = [0u8; 32];
+        let mut keccak = Keccak::v256();
+        keccak.update(&pubkey.serialize_uncompressed()[1..]);
+        keccak.finalize(&mut hash);
+        let mut address = [0u8; ETH_ADDRESS_SIZE];
+        address.copy_from_slice(&hash[32 - ETH_ADDRESS_SIZE..]);
+        Ok(address)
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    ckb_lib_build::generate_code_hashes();
+}
@@ -0,0 +1,72 @@
+use ckb_dynamic_verifier::LoadError;
+use ckb_std::dynamic_loading;
+
+/// Errors of the RSA dual library and of loading it.
+///
+/// The RSA variants mirror the error codes of `rsa_sighash_all` in
+/// ckb-miscellaneous-scripts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibError {
+    /// no cell dep with the library's code hash
+    LibraryNotFound,
+    /// the library doesn't export a symbol we need
+    SymbolMissing,
+    /// the `CKBDLContext` is too small for the library
+    InsufficientMemory,
+    /// the library isn't a loadable ELF
+    InvalidLibrary,
+    // error codes of the C library
+    ArgumentsLen,
+    Encoding,
+    Syscall,
+    ScriptTooLong,
+    WitnessSize,
+    PubkeyBlake160Hash,
+    RsaInvalidParam1,
+    RsaInvalidParam2,
+    RsaMdStringFailed,
+    RsaVerifyFailed,
+    RsaOnlyInit,
+    RsaInvalidKeySize,
+    /// a code the C library isn't known to return
+    Unknown(i32),
+}
+
+impl From<i32> for LibError {
+    fn from(code: i32) -> Self {
+        use LibError::*;
+        match code {
+            -1 => ArgumentsLen,
+            -2 => Encoding,
+            -3 => Syscall,
+            -21 => ScriptTooLong,
+            -22 => WitnessSize,
+            -31 => PubkeyBlake160Hash,
+            -41 => RsaInvalidParam1,
+            -42 => RsaInvalidParam2,
+            -43 => RsaMdStringFailed,
+            -44 => RsaVerifyFailed,
+            -45 => RsaOnlyInit,
+            -46 => RsaInvalidKeySize,
+            code => Unknown(code),
+        }
+    }
+}
+
+impl From<LoadError> for LibError {
+    fn from(err: LoadError) -> Self {
+        match err {
+            LoadError::LibraryNotFound => LibError::LibraryNotFound,
+            LoadError::SymbolMissing => LibError::SymbolMissing,
+            LoadError::InsufficientMemory => LibError::InsufficientMemory,
+            LoadError::InvalidLibrary => LibError::InvalidLibrary,
+            LoadError::Syscall => LibError::Syscall,
+        }
+    }
+}
+
+impl From<dynamic_loading::Error> for LibError {
+    fn from(err: dynamic_loading::Error) -> Self {
+        LoadError::from(err).into()
+    }
+}
@@ -0,0 +1,10 @@
+#![no_std]
+
+mod code_hashes;
+mod error;
+mod librsa;
+
+pub use ckb_dynamic_verifier::{DynamicVerifier, LibLocator};
+pub use code_hashes::CODE_HASH_RSA;
+pub use error::LibError;
+pub use librsa::LibRsa;
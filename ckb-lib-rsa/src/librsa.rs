@@ -0,0 +1,99 @@
+use core::ptr;
+
+use crate::code_hashes::CODE_HASH_RSA;
+use crate::error::LibError;
+use ckb_dynamic_verifier::{DynamicVerifier, LibLocator};
+use ckb_std::dynamic_loading::{CKBDLContext, Symbol};
+
+/// function signature of validate_rsa_sighash_all
+type ValidateRsaSighashAll = unsafe extern "C" fn(pubkey_hash: *const u8) -> i32;
+/// function signature of validate_signature, the RSA library takes no
+/// prefilled data
+type ValidateSignature = unsafe extern "C" fn(
+    prefilled_data: *const u8,
+    signature_buffer: *const u8,
+    signature_size: u64,
+    message_buffer: *const u8,
+    message_size: u64,
+    output: *mut u8,
+    output_len: *mut u64,
+) -> i32;
+
+/// Symbol name
+const VALIDATE_RSA_SIGHASH_ALL: &[u8; 24] = b"validate_rsa_sighash_all";
+const VALIDATE_SIGNATURE: &[u8; 18] = b"validate_signature";
+
+/// The `rsa_sighash_all` library.
+///
+/// Its signatures carry the public key, `algorithm id (u32 LE) | key size
+/// (u32 LE) | E (u32 LE) | N | signature`, and it identifies the signer by the
+/// blake160 of `algorithm id | key size | E | N`.
+pub struct LibRsa {
+    validate_rsa_sighash_all: Symbol<ValidateRsaSighashAll>,
+    validate_signature: Symbol<ValidateSignature>,
+}
+
+impl LibRsa {
+    pub fn try_load<T>(context: &mut CKBDLContext<T>) -> Result<Self, LibError> {
+        Self::try_load_by(context, &LibLocator::DataHash(CODE_HASH_RSA))
+    }
+
+    pub fn try_load_by<T>(context: &mut CKBDLContext<T>, locator: &LibLocator) -> Result<Self, LibError> {
+        let data_hash = locator.data_hash()?;
+
+        // load library
+        let lib = context.load(&data_hash)?;
+
+        // find symbols
+        let validate_rsa_sighash_all: Symbol<ValidateRsaSighashAll> =
+            unsafe { lib.get(VALIDATE_RSA_SIGHASH_ALL).ok_or(LibError::SymbolMissing)? };
+        let validate_signature: Symbol<ValidateSignature> =
+            unsafe { lib.get(VALIDATE_SIGNATURE).ok_or(LibError::SymbolMissing)? };
+        Ok(LibRsa {
+            validate_rsa_sighash_all,
+            validate_signature,
+        })
+    }
+
+    pub fn validate_rsa_sighash_all(&self, pubkey_hash: &[u8; 20]) -> Result<(), LibError> {
+        let f = &self.validate_rsa_sighash_all;
+        let error_code = unsafe { f(pubkey_hash.as_ptr()) };
+        if error_code != 0 {
+            return Err(error_code.into());
+        }
+        Ok(())
+    }
+
+    /// Verify `signature` of `message`, returning the blake160 of the public
+    /// key it carries.
+    pub fn validate_signature(&self, signature: &[u8], message: &[u8]) -> Result<[u8; 20], LibError> {
+        let mut pubkey_hash = [0u8; 20];
+        let mut len: u64 = pubkey_hash.len() as u64;
+
+        let f = &self.validate_signature;
+        let error_code = unsafe {
+            f(
+                ptr::null(),
+                signature.as_ptr(),
+                signature.len() as u64,
+                message.as_ptr(),
+                message.len() as u64,
+                pubkey_hash.as_mut_ptr(),
+                &mut len as *mut u64,
+            )
+        };
+        if error_code != 0 {
+            return Err(error_code.into());
+        }
+        debug_assert_eq!(pubkey_hash.len() as u64, len);
+        Ok(pubkey_hash)
+    }
+}
+
+impl DynamicVerifier for LibRsa {
+    type Error = LibError;
+
+    fn verify_signer(&self, signature: &[u8], message: &[u8; 32]) -> Result<[u8; 20], LibError> {
+        self.validate_signature(signature, message)
+    }
+}
@@ -3,7 +3,16 @@
 extern crate alloc;
 
 mod code_hashes;
+mod error;
 mod libsecp256k1;
+mod verifier;
 
 pub use code_hashes::CODE_HASH_SECP256K1;
-pub use libsecp256k1::LibSecp256k1;
\ No newline at end of file
+pub use error::LibError;
+pub use ckb_dynamic_verifier::{DynamicVerifier, LibLocator};
+pub use libsecp256k1::{
+    compress_pubkey, LibSecp256k1, LoadPrefilledData, PrefilledData, Pubkey, StaticPrefilledData, SECP256K1_DATA_SIZE,
+};
+pub use verifier::{LibVerifier, Verifier};
+#[cfg(feature = "native")]
+pub use verifier::NativeVerifier;
\ No newline at end of file
@@ -1,5 +1,11 @@
 use crate::code_hashes::CODE_HASH_SECP256K1;
+use crate::error::LibError;
 use crate::alloc::{alloc::{alloc, Layout}, boxed::Box};
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use ckb_dynamic_verifier::LibLocator;
 use ckb_std::dynamic_loading::{CKBDLContext, Symbol};
 
 /// function signature of validate_secp256k1_blake2b_sighash_all
@@ -16,15 +22,108 @@ type ValidateSignature = unsafe extern "C" fn(
 ) -> i32;
 
 /// function signature of load_prefilled_data
-type LoadPrefilledData = unsafe extern "C" fn(data: *mut u8, len: *mut u64) -> i32;
+pub type LoadPrefilledData = unsafe extern "C" fn(data: *mut u8, len: *mut u64) -> i32;
 
 /// Symbol name
 const VALIDATE_BLAKE2B_SIGHASH_ALL: &[u8; 38] = b"validate_secp256k1_blake2b_sighash_all";
 const VALIDATE_SIGNATURE: &[u8; 18] = b"validate_signature";
 const LOAD_PREFILLED_DATA: &[u8; 19] = b"load_prefilled_data";
 
-const SECP256K1_DATA_SIZE: usize = 1048576;
-pub struct PrefilledData(Box<[u8; SECP256K1_DATA_SIZE]>);
+/// size of the precomputed table `load_prefilled_data` fills
+pub const SECP256K1_DATA_SIZE: usize = 1048576;
+/// a compact signature, `r | s` without the recovery id
+const COMPACT_SIGNATURE_SIZE: usize = 64;
+/// The library's precomputed table, on the heap or in a caller's buffer.
+pub struct PrefilledData(Buffer);
+
+enum Buffer {
+    Heap(Box<[u8; SECP256K1_DATA_SIZE]>),
+    Static(&'static mut [u8; SECP256K1_DATA_SIZE]),
+}
+
+impl PrefilledData {
+    /// an empty table in a 1 MB heap allocation
+    pub fn on_heap() -> Result<Self, LibError> {
+        Self::on_heap_with(alloc)
+    }
+
+    /// like `on_heap`, with the allocator `alloc`
+    pub fn on_heap_with(alloc: unsafe fn(Layout) -> *mut u8) -> Result<Self, LibError> {
+        let data = unsafe {
+            let layout = Layout::new::<[u8; SECP256K1_DATA_SIZE]>();
+            let raw_allocation = alloc(layout) as *mut [u8; SECP256K1_DATA_SIZE];
+            if raw_allocation.is_null() {
+                return Err(LibError::AllocationFailed);
+            }
+            Box::from_raw(raw_allocation)
+        };
+        Ok(PrefilledData(Buffer::Heap(data)))
+    }
+
+    /// an empty table in `buffer`, see `StaticPrefilledData`
+    pub fn in_buffer(buffer: &'static mut [u8; SECP256K1_DATA_SIZE]) -> Self {
+        PrefilledData(Buffer::Static(buffer))
+    }
+
+    /// Fill the table with the library's `load_prefilled_data`.
+    pub fn fill_with(mut self, load: LoadPrefilledData) -> Result<Self, LibError> {
+        let mut len: u64 = SECP256K1_DATA_SIZE as u64;
+        let error_code = unsafe { load(self.as_mut_ptr(), &mut len as *mut u64) };
+        if error_code != 0 {
+            return Err(error_code.into());
+        }
+        Ok(self)
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        match self.0 {
+            Buffer::Heap(ref data) => &data[..],
+            Buffer::Static(ref data) => &data[..],
+        }
+    }
+
+    fn as_ptr(&self) -> *const u8 {
+        match self.0 {
+            Buffer::Heap(ref data) => data.as_ptr(),
+            Buffer::Static(ref data) => data.as_ptr(),
+        }
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        match self.0 {
+            Buffer::Heap(ref mut data) => data.as_mut_ptr(),
+            Buffer::Static(ref mut data) => data.as_mut_ptr(),
+        }
+    }
+}
+
+/// A `static` home for the precomputed table, so a contract doesn't need a
+/// 1 MB heap for it. It's handed out once: a second `take` fails with
+/// `AllocationFailed` rather than give two verifiers the same buffer.
+pub struct StaticPrefilledData {
+    taken: AtomicBool,
+    data: UnsafeCell<[u8; SECP256K1_DATA_SIZE]>,
+}
+
+// the buffer is only reachable through `take`, which hands it out once
+unsafe impl Sync for StaticPrefilledData {}
+
+impl StaticPrefilledData {
+    pub const fn new() -> Self {
+        StaticPrefilledData {
+            taken: AtomicBool::new(false),
+            data: UnsafeCell::new([0u8; SECP256K1_DATA_SIZE]),
+        }
+    }
+
+    pub fn take(&'static self) -> Result<&'static mut [u8; SECP256K1_DATA_SIZE], LibError> {
+        if self.taken.swap(true, Ordering::SeqCst) {
+            return Err(LibError::AllocationFailed);
+        }
+        Ok(unsafe { &mut *self.data.get() })
+    }
+}
+
 pub struct Pubkey([u8; 33]);
 
 impl Pubkey {
@@ -40,6 +139,24 @@ impl Default for Pubkey {
     }
 }
 
+/// Compressed form of a 33-byte compressed or 65-byte uncompressed pubkey,
+/// the form CKB hashes into a blake160 pubkey hash.
+///
+/// Only the prefix is checked, the uncompressed form gives its x coordinate
+/// and the parity of y, whether the point is on the curve isn't checked.
+pub fn compress_pubkey(pubkey: &[u8]) -> Result<[u8; 33], LibError> {
+    let mut compressed = [0u8; 33];
+    match (pubkey.len(), pubkey.first()) {
+        (33, Some(0x02)) | (33, Some(0x03)) => compressed.copy_from_slice(pubkey),
+        (65, Some(0x04)) => {
+            compressed[0] = 0x02 | (pubkey[64] & 1);
+            compressed[1..].copy_from_slice(&pubkey[1..33]);
+        },
+        _ => return Err(LibError::SecpParsePubkey),
+    }
+    Ok(compressed)
+}
+
 impl Into<[u8; 33]> for Pubkey {
     fn into(self) -> [u8; 33] {
         self.0
@@ -52,49 +169,67 @@ pub struct LibSecp256k1 {
 }
 
 impl LibSecp256k1 {
-    pub fn load<T>(context: &mut CKBDLContext<T>) -> Self {
+    pub fn try_load<T>(context: &mut CKBDLContext<T>) -> Result<Self, LibError> {
+        Self::try_load_by(context, &LibLocator::DataHash(CODE_HASH_SECP256K1))
+    }
+
+    pub fn try_load_by<T>(context: &mut CKBDLContext<T>, locator: &LibLocator) -> Result<Self, LibError> {
+        let data_hash = locator.data_hash()?;
+
         // load library
-        let lib = context.load(&CODE_HASH_SECP256K1).expect("load secp256k1");
+        let lib = context.load(&data_hash)?;
 
         // find symbols
         let validate_blake2b_sighash_all: Symbol<ValidateBlake2bSighashAll> = unsafe {
             lib.get(VALIDATE_BLAKE2B_SIGHASH_ALL)
-                .expect("load function")
+                .ok_or(LibError::SymbolMissing)?
         };
         let validate_signature: Symbol<ValidateSignature> =
-            unsafe { lib.get(VALIDATE_SIGNATURE).expect("load function") };
+            unsafe { lib.get(VALIDATE_SIGNATURE).ok_or(LibError::SymbolMissing)? };
         let load_prefilled_data: Symbol<LoadPrefilledData> =
-            unsafe { lib.get(LOAD_PREFILLED_DATA).expect("load function") };
-        LibSecp256k1 {
+            unsafe { lib.get(LOAD_PREFILLED_DATA).ok_or(LibError::SymbolMissing)? };
+        Ok(LibSecp256k1 {
             validate_blake2b_sighash_all,
             load_prefilled_data,
             validate_signature,
-        }
+        })
     }
 
-    pub fn validate_blake2b_sighash_all(&self, pubkey_hash: &mut [u8; 20]) -> Result<(), i32> {
+    /// Verify the current script group's sighash-all signature, the way
+    /// secp256k1_blake2b_sighash_all does, and write its signer's pubkey hash
+    /// to `pubkey_hash`.
+    ///
+    /// The library verifies with its own precomputed table, so unlike
+    /// `recover_pubkey` this needs no `PrefilledData`.
+    pub fn validate_blake2b_sighash_all(&self, pubkey_hash: &mut [u8; 20]) -> Result<(), LibError> {
         let f = &self.validate_blake2b_sighash_all;
         let error_code = unsafe { f(pubkey_hash.as_mut_ptr()) };
         if error_code != 0 {
-            return Err(error_code);
+            return Err(error_code.into());
         }
         Ok(())
     }
 
-    pub fn load_prefilled_data(&self) -> Result<PrefilledData, i32> {
-        let mut data = unsafe {
-            let layout = Layout::new::<[u8; SECP256K1_DATA_SIZE]>();
-            let raw_allocation = alloc(layout) as *mut [u8; SECP256K1_DATA_SIZE];
-            Box::from_raw(raw_allocation)
-        };
-        let mut len: u64 = SECP256K1_DATA_SIZE as u64;
+    /// the pubkey hash which signed the current script group's sighash-all
+    pub fn sighash_all_signer(&self) -> Result<[u8; 20], LibError> {
+        let mut pubkey_hash = [0u8; 20];
+        self.validate_blake2b_sighash_all(&mut pubkey_hash)?;
+        Ok(pubkey_hash)
+    }
 
-        let f = &self.load_prefilled_data;
-        let error_code = unsafe { f(data.as_mut_ptr(), &mut len as *mut u64) };
-        if error_code != 0 {
-            return Err(error_code);
-        }
-        Ok(PrefilledData(data))
+    /// Load the precomputed table into a 1 MB heap allocation, the contract's
+    /// heap must be large enough, see `load_prefilled_data_into` otherwise.
+    pub fn load_prefilled_data(&self) -> Result<PrefilledData, LibError> {
+        PrefilledData::on_heap()?.fill_with(*self.load_prefilled_data)
+    }
+
+    /// Load the precomputed table into `buffer`, usually taken from a
+    /// `StaticPrefilledData`, so the contract doesn't need a heap for it.
+    pub fn load_prefilled_data_into(
+        &self,
+        buffer: &'static mut [u8; SECP256K1_DATA_SIZE],
+    ) -> Result<PrefilledData, LibError> {
+        PrefilledData::in_buffer(buffer).fill_with(*self.load_prefilled_data)
     }
 
     pub fn recover_pubkey(
@@ -102,14 +237,14 @@ impl LibSecp256k1 {
         prefilled_data: &PrefilledData,
         signature: &[u8],
         message: &[u8],
-    ) -> Result<Pubkey, i32> {
+    ) -> Result<Pubkey, LibError> {
         let mut pubkey = Pubkey::default();
         let mut len: u64 = pubkey.0.len() as u64;
 
         let f = &self.validate_signature;
         let error_code = unsafe {
             f(
-                prefilled_data.0.as_ptr(),
+                prefilled_data.as_ptr(),
                 signature.as_ptr(),
                 signature.len() as u64,
                 message.as_ptr(),
@@ -119,9 +254,40 @@ impl LibSecp256k1 {
             )
         };
         if error_code != 0 {
-            return Err(error_code);
+            return Err(error_code.into());
         }
         debug_assert_eq!(pubkey.0.len() as u64, len);
         Ok(pubkey)
     }
+
+    /// Verify a 64-byte compact signature of `message` by `pubkey`, given
+    /// compressed or uncompressed, and return the compressed pubkey.
+    ///
+    /// The library only exports recovery, so the signature is recovered with
+    /// each recovery id until one gives `pubkey`. That is the same as an
+    /// ECDSA verification, at the cost of up to four recoveries.
+    pub fn verify(
+        &self,
+        prefilled_data: &PrefilledData,
+        pubkey: &[u8],
+        signature: &[u8],
+        message: &[u8],
+    ) -> Result<Pubkey, LibError> {
+        if signature.len() != COMPACT_SIGNATURE_SIZE {
+            return Err(LibError::InvalidSignatureSize);
+        }
+        let expected = compress_pubkey(pubkey)?;
+
+        let mut recoverable = [0u8; COMPACT_SIGNATURE_SIZE + 1];
+        recoverable[..COMPACT_SIGNATURE_SIZE].copy_from_slice(signature);
+        for recovery_id in 0..4 {
+            recoverable[COMPACT_SIGNATURE_SIZE] = recovery_id;
+            match self.recover_pubkey(prefilled_data, &recoverable, message) {
+                Ok(recovered) if recovered.0 == expected => return Ok(recovered),
+                Ok(_) | Err(LibError::SecpRecoverPubkey) => {},
+                Err(err) => return Err(err),
+            }
+        }
+        Err(LibError::SecpVerification)
+    }
 }
@@ -0,0 +1,173 @@
+use blake2b_ref::Blake2bBuilder;
+use ckb_dynamic_verifier::DynamicVerifier;
+
+use crate::error::LibError;
+use crate::libsecp256k1::{LibSecp256k1, PrefilledData, SECP256K1_DATA_SIZE};
+
+/// The signature checks a lock needs from a secp256k1 library.
+///
+/// `LibVerifier` calls the dual library on chain, `NativeVerifier` (feature
+/// `native`) does the same in pure Rust, so that scripts built on this trait
+/// can be unit tested natively.
+pub trait Verifier {
+    /// recover the compressed pubkey of a 65-byte recoverable signature
+    fn recover_pubkey(&self, signature: &[u8], message: &[u8; 32]) -> Result<[u8; 33], LibError>;
+
+    /// verify a 64-byte compact signature by a compressed or uncompressed
+    /// pubkey, and return the compressed pubkey
+    fn verify(&self, pubkey: &[u8], signature: &[u8], message: &[u8; 32]) -> Result<[u8; 33], LibError>;
+
+    /// check the current script group's sighash-all signature was made by
+    /// `pubkey_hash`
+    fn validate_blake2b_sighash_all(&self, pubkey_hash: &[u8; 20]) -> Result<(), LibError>;
+}
+
+/// blake160 of a compressed pubkey, the identity of a secp256k1 signer on CKB
+fn blake160(pubkey: &[u8; 33]) -> [u8; 20] {
+    let mut hash = [0u8; 32];
+    let mut blake2b = Blake2bBuilder::new(32)
+        .personal(b"ckb-default-hash")
+        .build();
+    blake2b.update(pubkey);
+    blake2b.finalize(&mut hash);
+    let mut pubkey_hash = [0u8; 20];
+    pubkey_hash.copy_from_slice(&hash[..20]);
+    pubkey_hash
+}
+
+/// the dual library with its precomputed table loaded
+pub struct LibVerifier {
+    lib: LibSecp256k1,
+    prefilled_data: PrefilledData,
+}
+
+impl LibVerifier {
+    /// with the precomputed table on the heap
+    pub fn new(lib: LibSecp256k1) -> Result<Self, LibError> {
+        let prefilled_data = lib.load_prefilled_data()?;
+        Ok(LibVerifier { lib, prefilled_data })
+    }
+
+    /// with the precomputed table in `buffer`, no heap needed
+    pub fn with_buffer(
+        lib: LibSecp256k1,
+        buffer: &'static mut [u8; SECP256K1_DATA_SIZE],
+    ) -> Result<Self, LibError> {
+        let prefilled_data = lib.load_prefilled_data_into(buffer)?;
+        Ok(LibVerifier { lib, prefilled_data })
+    }
+}
+
+impl Verifier for LibVerifier {
+    fn recover_pubkey(&self, signature: &[u8], message: &[u8; 32]) -> Result<[u8; 33], LibError> {
+        let pubkey = self.lib.recover_pubkey(&self.prefilled_data, signature, message)?;
+        Ok(pubkey.into())
+    }
+
+    fn verify(&self, pubkey: &[u8], signature: &[u8], message: &[u8; 32]) -> Result<[u8; 33], LibError> {
+        let pubkey = self.lib.verify(&self.prefilled_data, pubkey, signature, message)?;
+        Ok(pubkey.into())
+    }
+
+    fn validate_blake2b_sighash_all(&self, pubkey_hash: &[u8; 20]) -> Result<(), LibError> {
+        // the library gives the signer, it doesn't compare
+        if self.lib.sighash_all_signer()? != *pubkey_hash {
+            return Err(LibError::PubkeyBlake160Hash);
+        }
+        Ok(())
+    }
+}
+
+impl DynamicVerifier for LibVerifier {
+    type Error = LibError;
+
+    fn verify_signer(&self, signature: &[u8], message: &[u8; 32]) -> Result<[u8; 20], LibError> {
+        Ok(blake160(&self.recover_pubkey(signature, message)?))
+    }
+}
+
+#[cfg(feature = "native")]
+pub use native::NativeVerifier;
+
+#[cfg(feature = "native")]
+mod native {
+    use ckb_dynamic_verifier::DynamicVerifier;
+    use secp256k1::{
+        recovery::{RecoverableSignature, RecoveryId},
+        Message, PublicKey, Secp256k1, Signature,
+    };
+
+    use super::{blake160, Verifier};
+    use crate::error::LibError;
+
+    /// Pure Rust verifier for native unit tests.
+    ///
+    /// There is no transaction to compute a sighash-all message from, so the
+    /// signature and message it validates are given up front.
+    #[derive(Default)]
+    pub struct NativeVerifier {
+        sighash_all: Option<([u8; 65], [u8; 32])>,
+    }
+
+    impl NativeVerifier {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// validate `signature` over `message` as the group's sighash-all
+        pub fn with_sighash_all(signature: [u8; 65], message: [u8; 32]) -> Self {
+            NativeVerifier {
+                sighash_all: Some((signature, message)),
+            }
+        }
+    }
+
+    impl Verifier for NativeVerifier {
+        fn recover_pubkey(&self, signature: &[u8], message: &[u8; 32]) -> Result<[u8; 33], LibError> {
+            if signature.len() != 65 {
+                return Err(LibError::InvalidSignatureSize);
+            }
+            let recovery_id = RecoveryId::from_i32(signature[64] as i32)
+                .map_err(|_| LibError::SecpParseSignature)?;
+            let signature = RecoverableSignature::from_compact(&signature[..64], recovery_id)
+                .map_err(|_| LibError::SecpParseSignature)?;
+            let message = Message::from_slice(message).map_err(|_| LibError::InvalidMessageSize)?;
+            let pubkey = Secp256k1::verification_only()
+                .recover(&message, &signature)
+                .map_err(|_| LibError::SecpRecoverPubkey)?;
+            Ok(pubkey.serialize())
+        }
+
+        fn verify(&self, pubkey: &[u8], signature: &[u8], message: &[u8; 32]) -> Result<[u8; 33], LibError> {
+            if signature.len() != 64 {
+                return Err(LibError::InvalidSignatureSize);
+            }
+            let pubkey = PublicKey::from_slice(pubkey).map_err(|_| LibError::SecpParsePubkey)?;
+            let mut signature = Signature::from_compact(signature).map_err(|_| LibError::SecpParseSignature)?;
+            // recovery accepts either s, like the dual library
+            signature.normalize_s();
+            let message = Message::from_slice(message).map_err(|_| LibError::InvalidMessageSize)?;
+            Secp256k1::verification_only()
+                .verify(&message, &signature, &pubkey)
+                .map_err(|_| LibError::SecpVerification)?;
+            Ok(pubkey.serialize())
+        }
+
+        fn validate_blake2b_sighash_all(&self, pubkey_hash: &[u8; 20]) -> Result<(), LibError> {
+            let (signature, message) = self.sighash_all.as_ref().ok_or(LibError::WitnessSize)?;
+            let pubkey = self.recover_pubkey(signature, message)?;
+            if blake160(&pubkey) != *pubkey_hash {
+                return Err(LibError::PubkeyBlake160Hash);
+            }
+            Ok(())
+        }
+    }
+
+    impl DynamicVerifier for NativeVerifier {
+        type Error = LibError;
+
+        fn verify_signer(&self, signature: &[u8], message: &[u8; 32]) -> Result<[u8; 20], LibError> {
+            Ok(blake160(&self.recover_pubkey(signature, message)?))
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! The extended ownership lock as a dual-mode library.
+//!
+//! Other scripts load it with `CKBDLContext` (see the `ckb-lib-dao-ownership`
+//! crate) to ask whether the owner of a puppet authorizes the transaction.
+//! See `entry::verify_puppet_owner` in the lock for the rules.
+
+#![no_std]
+#![feature(lang_items)]
+#![feature(alloc_error_handler)]
+
+use core::slice;
+
+use ckb_std::{default_alloc, syscalls};
+use nervos_dao_extended_ownership_script::entry;
+
+// Alloc 4K fast HEAP + 64K HEAP, the secp256k1 PrefilledData is static.
+// The caller's context has to hold the whole image, so keep it small.
+default_alloc!(4 * 1024, 64 * 1024, 64);
+
+/// Check the owner of the puppet with the 32-byte `puppet_type_hash`
+/// authorizes the current transaction.
+///
+/// Returns 0 when it does, otherwise the lock's error code.
+#[no_mangle]
+pub extern "C" fn verify_puppet_owner(puppet_type_hash: *const u8) -> i32 {
+    let puppet_type_hash = unsafe { slice::from_raw_parts(puppet_type_hash, 32) };
+    match entry::verify_puppet_owner(puppet_type_hash) {
+        Ok(_) => 0,
+        Err(err) => err as i32,
+    }
+}
+
+#[panic_handler]
+fn panic_handler(_info: &core::panic::PanicInfo) -> ! {
+    syscalls::exit(-1)
+}
+
+#[lang = "eh_personality"]
+extern "C" fn eh_personality() {}
@@ -0,0 +1,191 @@
+// Import heap related library from `alloc`
+use alloc::vec::Vec;
+
+// Import CKB syscalls and structures
+use ckb_std::{
+    ckb_constants::Source,
+    ckb_types::{packed::{Byte, Script}, prelude::*},
+    error::SysError,
+};
+
+use crate::chain::{Chain, PuppetCell};
+use crate::entry::new_blake2b;
+use crate::error::Error;
+use crate::puppet::Puppet;
+
+/// NervosDAO cell data is an 8-byte block number, zero for a fresh deposit
+const DAO_DATA_LEN: usize = 8;
+
+/// hash of the nervosDAO type script, code hash
+/// `0x82d76d1b75fe2fd9a27dfbaa65a039221a380d76c926f378d3f81cf3e7e13f2e`
+/// by type with empty args, the same on mainnet and testnet
+pub const DAO_TYPE_HASH: [u8; 32] = [
+    0xcc, 0x77, 0xc4, 0xde, 0xac, 0x05, 0xd6, 0x8a, 0xb5, 0xb2, 0x68, 0x28, 0xf0, 0xbf, 0x45, 0x65,
+    0xa8, 0xd7, 0x31, 0x13, 0xd7, 0xbb, 0x7e, 0x92, 0xb8, 0x36, 0x2b, 0x8a, 0x74, 0xe5, 0x8e, 0x58,
+];
+
+/// code hash of the genesis secp256k1 blake160 sighash-all lock, by type
+pub const SECP256K1_BLAKE160_CODE_HASH: [u8; 32] = [
+    0x9b, 0xd7, 0xe0, 0x6f, 0x3e, 0xcf, 0x4b, 0xe0, 0xf2, 0xfc, 0xd2, 0x18, 0x8b, 0x23, 0xf1, 0xb9,
+    0xfc, 0xc8, 0x8e, 0x5d, 0x4b, 0x65, 0xa8, 0x63, 0x7b, 0x17, 0x72, 0x3b, 0xbd, 0xa3, 0xcc, 0xe8,
+];
+const HASH_TYPE_TYPE: u8 = 1;
+
+/// What a transaction does with the nervosDAO cells guarded by this lock.
+#[derive(PartialEq, Clone, Copy)]
+pub enum Action {
+    /// deposit cells are turned into withdrawing cells at the same index
+    Phase1,
+    /// withdrawing cells are consumed and paid out
+    Phase2,
+    /// the puppet record is rewritten or destroyed
+    Transfer,
+    /// anything else, only the owner may do it
+    Other,
+}
+
+pub fn classify<C: Chain>(chain: &C, puppet_type_hash: &[u8], puppet_cell: PuppetCell) -> Result<Action, Error> {
+    // changing the puppet changes who owns the position, so it wins over
+    // whatever happens to the dao cells in the same transaction
+    if puppet_changed(chain, puppet_type_hash, puppet_cell)? {
+        return Ok(Action::Transfer);
+    }
+
+    let lock_hash = chain.script_hash()?;
+    let mut action = None;
+    for i in 0.. {
+        match chain.cell_lock_hash(i, Source::Input) {
+            Ok(hash) => {
+                // the guard lock of a pending transfer may be this lock
+                if hash != lock_hash || is_puppet(chain, i, puppet_type_hash)? {
+                    continue;
+                }
+                let current = classify_input(chain, i, &lock_hash)?;
+                match action {
+                    None => action = Some(current),
+                    Some(previous) if previous != current => return Ok(Action::Other),
+                    _ => {},
+                }
+            },
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(err.into()),
+        };
+    }
+
+    Ok(action.unwrap_or(Action::Other))
+}
+
+fn puppet_changed<C: Chain>(chain: &C, puppet_type_hash: &[u8], puppet_cell: PuppetCell) -> Result<bool, Error> {
+    // a puppet among the cell deps isn't spent, an output puppet is a new one
+    if puppet_cell.source != Source::Input {
+        return Ok(load_output_puppet(chain, puppet_type_hash)?.is_some());
+    }
+    let input_data = chain.cell_data(puppet_cell.index, puppet_cell.source)?;
+    match load_output_puppet(chain, puppet_type_hash)? {
+        Some(output_data) => Ok(output_data != input_data),
+        None => Ok(true),
+    }
+}
+
+/// whether input `index` is the puppet
+pub fn is_puppet<C: Chain>(chain: &C, index: usize, puppet_type_hash: &[u8]) -> Result<bool, Error> {
+    match chain.cell_type_hash(index, Source::Input)? {
+        Some(type_hash) => Ok(puppet_type_hash[..] == type_hash[..]),
+        None => Ok(false),
+    }
+}
+
+/// data of the puppet cell recreated in the outputs, if any
+pub fn load_output_puppet<C: Chain>(chain: &C, puppet_type_hash: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+    let mut output_data = None;
+    for i in 0.. {
+        match chain.cell_type_hash(i, Source::Output) {
+            Ok(Some(type_hash)) => {
+                if puppet_type_hash[..] == type_hash[..] {
+                    if output_data.is_some() {
+                        return Err(Error::RequiredOnlyOnePuppet);
+                    }
+                    output_data = Some(chain.cell_data(i, Source::Output)?);
+                }
+            },
+            Ok(None) => {},
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(err.into()),
+        };
+    }
+    Ok(output_data)
+}
+
+fn classify_input<C: Chain>(chain: &C, index: usize, lock_hash: &[u8; 32]) -> Result<Action, Error> {
+    // only nervosDAO cells can be withdrawn, whatever their data looks like
+    let type_hash = match chain.cell_type_hash(index, Source::Input)? {
+        Some(type_hash) if type_hash == DAO_TYPE_HASH => type_hash,
+        _ => return Ok(Action::Other),
+    };
+    let data = chain.cell_data(index, Source::Input)?;
+    if data.len() != DAO_DATA_LEN {
+        return Ok(Action::Other);
+    }
+
+    if data.iter().any(|b| *b != 0) {
+        return Ok(Action::Phase2);
+    }
+
+    // a deposit cell may only move to a withdrawing cell which keeps
+    // the same lock and type at the same index
+    let same_lock = match chain.cell_lock_hash(index, Source::Output) {
+        Ok(hash) => hash == *lock_hash,
+        Err(SysError::IndexOutOfBound) => false,
+        Err(err) => return Err(err.into()),
+    };
+    let same_type = match chain.cell_type_hash(index, Source::Output) {
+        Ok(hash) => hash == Some(type_hash),
+        Err(SysError::IndexOutOfBound) => false,
+        Err(err) => return Err(err.into()),
+    };
+    if !same_lock || !same_type {
+        return Ok(Action::Other);
+    }
+    let output_data = chain.cell_data(index, Source::Output)?;
+    if output_data.len() != DAO_DATA_LEN || output_data.iter().all(|b| *b == 0) {
+        return Ok(Action::Other);
+    }
+
+    Ok(Action::Phase1)
+}
+
+/// A phase 2 withdrawal by anyone but the owner must pay every output, but
+/// the puppet kept as it is, to the payout lock of the puppet, or without
+/// one to the owner's default secp256k1 lock.
+pub fn check_payout<C: Chain>(chain: &C, puppet_type_hash: &[u8], puppet: &Puppet) -> Result<(), Error> {
+    let payout_lock_hash = match puppet.payout_lock {
+        Some(hash) => hash,
+        None => owner_lock_hash(&puppet.owner),
+    };
+    for i in 0.. {
+        match chain.cell_type_hash(i, Source::Output) {
+            Ok(Some(type_hash)) if puppet_type_hash[..] == type_hash[..] => continue,
+            Ok(_) => {},
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(err.into()),
+        }
+        if chain.cell_lock_hash(i, Source::Output)? != payout_lock_hash {
+            return Err(Error::PayoutLockMismatch);
+        }
+    }
+    Ok(())
+}
+
+/// hash of the genesis secp256k1 blake160 lock with the owner hash as args
+pub fn owner_lock_hash(owner: &[u8]) -> [u8; 32] {
+    let script = Script::new_builder()
+        .code_hash(SECP256K1_BLAKE160_CODE_HASH.pack())
+        .hash_type(Byte::new(HASH_TYPE_TYPE))
+        .args(owner.pack())
+        .build();
+    let mut buf = [0u8; 32];
+    let mut hasher = new_blake2b();
+    hasher.update(script.as_slice());
+    hasher.finalize(&mut buf);
+    buf
+}
@@ -0,0 +1,105 @@
+// Import heap related library from `alloc`
+use alloc::vec::Vec;
+
+// Import CKB syscalls and structures
+use ckb_std::{
+    high_level::{
+        load_script_hash, load_cell_type_hash, load_cell_lock_hash, load_cell_data, load_cell_capacity,
+        load_input_since, load_header,
+    },
+    ckb_types::prelude::*,
+    ckb_constants::Source,
+    error::SysError,
+};
+
+use crate::since;
+
+/// The part of the transaction the ownership rules look at.
+///
+/// On chain it's read through syscalls, natively it can be any mock
+/// transaction, so the rules can be unit tested without a VM.
+pub trait Chain {
+    fn script_hash(&self) -> Result<[u8; 32], SysError>;
+    fn cell_type_hash(&self, index: usize, source: Source) -> Result<Option<[u8; 32]>, SysError>;
+    fn cell_lock_hash(&self, index: usize, source: Source) -> Result<[u8; 32], SysError>;
+    fn cell_data(&self, index: usize, source: Source) -> Result<Vec<u8>, SysError>;
+    fn cell_capacity(&self, index: usize, source: Source) -> Result<u64, SysError>;
+    fn input_since(&self, index: usize, source: Source) -> Result<u64, SysError>;
+    /// epoch number of the block which created the input, its header must
+    /// be a header dep
+    fn input_epoch(&self, index: usize) -> Result<u64, SysError>;
+    /// epoch number of the header dep at `index`
+    fn header_dep_epoch(&self, index: usize) -> Result<u64, SysError>;
+}
+
+/// Where the puppet cell is, an input or, when the lock args allow the
+/// cell-dep ownership proof, a cell dep.
+#[derive(Clone, Copy)]
+pub struct PuppetCell {
+    pub index: usize,
+    pub source: Source,
+}
+
+impl PuppetCell {
+    pub fn input(index: usize) -> Self {
+        PuppetCell { index, source: Source::Input }
+    }
+
+    /// `since` of the puppet input, a cell dep has none, so it proves no
+    /// epoch and every since check on it fails. The rules refuse the lessee
+    /// and pending cooldowns before asking, see `rules::authorize`.
+    pub fn since<C: Chain>(&self, chain: &C) -> Result<u64, SysError> {
+        match self.source {
+            Source::Input => chain.input_since(self.index, Source::Input),
+            _ => Ok(0),
+        }
+    }
+
+    /// epoch number in which the puppet input was created, a cell dep isn't
+    /// spent so it proves nothing
+    pub fn epoch<C: Chain>(&self, chain: &C) -> Result<u64, SysError> {
+        match self.source {
+            Source::Input => chain.input_epoch(self.index),
+            _ => Err(SysError::ItemMissing),
+        }
+    }
+}
+
+/// the transaction being verified
+pub struct Syscalls;
+
+impl Chain for Syscalls {
+    fn script_hash(&self) -> Result<[u8; 32], SysError> {
+        load_script_hash()
+    }
+
+    fn cell_type_hash(&self, index: usize, source: Source) -> Result<Option<[u8; 32]>, SysError> {
+        load_cell_type_hash(index, source)
+    }
+
+    fn cell_lock_hash(&self, index: usize, source: Source) -> Result<[u8; 32], SysError> {
+        load_cell_lock_hash(index, source)
+    }
+
+    fn cell_data(&self, index: usize, source: Source) -> Result<Vec<u8>, SysError> {
+        load_cell_data(index, source)
+    }
+
+    fn cell_capacity(&self, index: usize, source: Source) -> Result<u64, SysError> {
+        load_cell_capacity(index, source)
+    }
+
+    fn input_since(&self, index: usize, source: Source) -> Result<u64, SysError> {
+        load_input_since(index, source)
+    }
+
+    fn input_epoch(&self, index: usize) -> Result<u64, SysError> {
+        let epoch: u64 = load_header(index, Source::Input)?.raw().epoch().unpack();
+        Ok(since::epoch_number(epoch))
+    }
+
+    fn header_dep_epoch(&self, index: usize) -> Result<u64, SysError> {
+        let epoch: u64 = load_header(index, Source::HeaderDep)?.raw().epoch().unpack();
+        Ok(since::epoch_number(epoch))
+    }
+}
@@ -0,0 +1,164 @@
+// Import CKB syscalls and structures
+use ckb_std::{
+    ckb_constants::Source,
+    error::SysError,
+};
+
+use crate::chain::{Chain, PuppetCell};
+use crate::error::Error;
+use crate::action::{self, Action};
+use crate::puppet::{Cooldown, Puppet};
+use crate::since;
+
+/// A pending transfer can only be acted on once the puppet cell written by
+/// the transfer is old enough. Nobody but the previous owner may touch the
+/// puppet in the meantime, so its age is the time since the transfer.
+pub fn check_elapsed<C: Chain>(chain: &C, puppet_cell: PuppetCell, cooldown: &Cooldown) -> Result<(), Error> {
+    check_age(puppet_cell.since(chain)?, cooldown)
+}
+
+fn check_age(since: u64, cooldown: &Cooldown) -> Result<(), Error> {
+    match since::relative_epochs(since) {
+        Some(epochs) if epochs >= cooldown.epochs => Ok(()),
+        _ => Err(Error::CooldownActive),
+    }
+}
+
+/// The previous owner may only take the puppet back during a pending
+/// transfer, leaving the DAO cells as they are.
+pub fn check_cancel<C: Chain>(
+    chain: &C,
+    puppet_type_hash: &[u8],
+    cooldown: &Cooldown,
+    action: Action,
+) -> Result<(), Error> {
+    if action != Action::Transfer {
+        return Err(Error::CooldownCancelOnly);
+    }
+    let output = match action::load_output_puppet(chain, puppet_type_hash)? {
+        Some(data) => Puppet::from_slice(&data)?,
+        None => return Err(Error::CooldownCancelOnly),
+    };
+    if output.owner != cooldown.previous_owner {
+        return Err(Error::CooldownCancelOnly);
+    }
+    match output.cooldown {
+        Some(ref restored) if restored.epochs >= cooldown.epochs && !restored.is_pending() => {},
+        _ => return Err(Error::CooldownRecordMismatch),
+    }
+    check_carried_over(chain, puppet_type_hash)
+}
+
+/// every cell guarded by this lock is recreated as it is at the same index,
+/// but the puppet, which the guard lock of a pending transfer guards too
+fn check_carried_over<C: Chain>(chain: &C, puppet_type_hash: &[u8]) -> Result<(), Error> {
+    let lock_hash = chain.script_hash()?;
+    for i in 0.. {
+        match chain.cell_lock_hash(i, Source::Input) {
+            Ok(hash) if hash == lock_hash => {},
+            Ok(_) => continue,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(err.into()),
+        }
+        if action::is_puppet(chain, i, puppet_type_hash)? {
+            continue;
+        }
+        let same = match chain.cell_lock_hash(i, Source::Output) {
+            Ok(hash) => {
+                hash == lock_hash
+                    && chain.cell_type_hash(i, Source::Output)? == chain.cell_type_hash(i, Source::Input)?
+                    && chain.cell_capacity(i, Source::Output)? == chain.cell_capacity(i, Source::Input)?
+                    && chain.cell_data(i, Source::Output)? == chain.cell_data(i, Source::Input)?
+            },
+            Err(SysError::IndexOutOfBound) => false,
+            Err(err) => return Err(err.into()),
+        };
+        if !same {
+            return Err(Error::CooldownCancelOnly);
+        }
+    }
+    Ok(())
+}
+
+/// A cooldown can't be dropped or shortened, and handing the puppet to a new
+/// owner must open a pending transfer stamped with the transfer epoch.
+pub fn check_transfer<C: Chain>(
+    chain: &C,
+    puppet_type_hash: &[u8],
+    puppet_cell: PuppetCell,
+    puppet: &Puppet,
+) -> Result<(), Error> {
+    let output = match action::load_output_puppet(chain, puppet_type_hash)? {
+        Some(data) => Some(Puppet::from_slice(&data)?),
+        None => None,
+    };
+    check_update(puppet_cell.since(chain)?, puppet, output.as_ref(), || puppet_cell.epoch(chain))
+}
+
+/// The cooldown rules of rewriting the puppet `input`, spent with `since`,
+/// into `output`, or destroying it. Both the lock and the puppet's type
+/// script apply them, so they hold whether the DAO cells move or not.
+///
+/// During a pending transfer the puppet can only be handed back to the
+/// previous owner, or, once the cooldown has passed, be settled or
+/// destroyed.
+///
+/// `created_epoch` loads the epoch the puppet input was created in, from its
+/// header dep. It is only asked for when the puppet changes hands: the
+/// transfer epoch can't be before it, so the stamp lies between the last
+/// update of the puppet and the commit of the transfer.
+pub fn check_update<F>(since: u64, input: &Puppet, output: Option<&Puppet>, created_epoch: F) -> Result<(), Error>
+where
+    F: FnOnce() -> Result<u64, SysError>,
+{
+    let cooldown = match input.cooldown {
+        Some(ref cooldown) => cooldown,
+        None => return Ok(()),
+    };
+    let output = match output {
+        Some(output) => output,
+        // destroying the puppet gives up the position, but not while the
+        // previous owner may still take it back
+        None if cooldown.is_pending() => return check_age(since, cooldown),
+        None => return Ok(()),
+    };
+    let next = match output.cooldown {
+        Some(ref next) if next.epochs >= cooldown.epochs => next,
+        _ => return Err(Error::CooldownRecordMismatch),
+    };
+
+    if output.owner != input.owner {
+        // handed back to the previous owner, which cancels the transfer
+        if cooldown.is_pending() && output.owner == cooldown.previous_owner && !next.is_pending() {
+            return Ok(());
+        }
+        // the transfer epoch is an absolute since, which can't prove the
+        // cooldown has passed, so a pending transfer is settled first
+        if cooldown.is_pending() {
+            return Err(Error::CooldownActive);
+        }
+        let transfer_epoch = since::absolute_epoch(since).ok_or(Error::CooldownRecordMismatch)?;
+        if next.previous_owner != input.owner || next.last_transfer_epoch != transfer_epoch {
+            return Err(Error::CooldownRecordMismatch);
+        }
+        let created = match created_epoch() {
+            Ok(epoch) => epoch,
+            Err(SysError::ItemMissing) | Err(SysError::IndexOutOfBound) => return Err(Error::CooldownRecordMismatch),
+            Err(err) => return Err(err.into()),
+        };
+        if transfer_epoch < created {
+            return Err(Error::CooldownRecordMismatch);
+        }
+        return Ok(());
+    }
+
+    // same owner, either settling the pending transfer or leaving it as it is
+    if next.is_pending() {
+        if next.previous_owner != cooldown.previous_owner || next.last_transfer_epoch != cooldown.last_transfer_epoch {
+            return Err(Error::CooldownRecordMismatch);
+        }
+    } else if cooldown.is_pending() {
+        check_age(since, cooldown)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,215 @@
+// Import CKB syscalls and structures
+use ckb_std::{
+    high_level::{
+        load_header, load_script_hash, load_transaction, load_tx_hash,
+        load_input, load_cell, load_cell_data, load_cell_lock_hash,
+    },
+    ckb_types::prelude::*,
+    ckb_constants::Source,
+    error::SysError,
+};
+
+use blake2b_ref::Blake2b;
+
+use crate::entry::new_blake2b;
+use crate::error::Error;
+use crate::witness::{LockWitness, OtxRange, OtxRanges, SIGHASH_ALL, SIGHASH_SINGLE, SIGHASH_ANYONECANPAY};
+
+pub const DOMAIN_TAG: &[u8] = b"nervos-dao-extended-ownership";
+
+/// The message signed in the lock witness, bound to one chain and one
+/// deployment so that the signature can't be replayed anywhere else:
+///
+/// blake2b(DOMAIN_TAG | genesis hash | lock script hash | puppet type hash | tx hash)
+///
+/// The genesis hash is the header dep with block number 0, which the
+/// transaction has to carry. With sighash flags other than ALL the tx hash is
+/// replaced by the parts of the transaction the flags cover, see
+/// `hash_covered` and `hash_otx`.
+pub fn signing_message(puppet_type_hash: &[u8], puppet_id: usize, lock_witness: &LockWitness) -> Result<[u8; 32], Error> {
+    let genesis_hash = load_genesis_hash()?;
+    let lock_hash = load_script_hash()?;
+
+    let mut message = [0u8; 32];
+    let mut hasher = new_blake2b();
+    hasher.update(DOMAIN_TAG);
+    hasher.update(&genesis_hash);
+    hasher.update(&lock_hash);
+    hasher.update(puppet_type_hash);
+    match lock_witness.otx {
+        Some(ref ranges) => hash_otx(&mut hasher, &lock_hash, puppet_id, lock_witness.sighash, ranges)?,
+        None if lock_witness.sighash == SIGHASH_ALL => hasher.update(&load_tx_hash()?),
+        None => hash_covered(&mut hasher, &lock_hash, puppet_id, lock_witness.sighash)?,
+    }
+    hasher.finalize(&mut message);
+    Ok(message)
+}
+
+/// The domain of a bare signature, which signs the sighash-all of
+/// secp256k1_blake2b_sighash_all rather than the message above. That one
+/// covers the group's first witness, so the witness carries the domain in
+/// its `output_type` field:
+///
+/// blake2b(DOMAIN_TAG | genesis hash | lock script hash | puppet type hash)
+///
+/// A signature made for another domain is as good as one by another key.
+pub fn check_sighash_all_domain(puppet_type_hash: &[u8], domain: Option<&[u8]>) -> Result<(), Error> {
+    let genesis_hash = load_genesis_hash()?;
+    let lock_hash = load_script_hash()?;
+
+    let mut expected = [0u8; 32];
+    let mut hasher = new_blake2b();
+    hasher.update(DOMAIN_TAG);
+    hasher.update(&genesis_hash);
+    hasher.update(&lock_hash);
+    hasher.update(puppet_type_hash);
+    hasher.finalize(&mut expected);
+    match domain {
+        Some(domain) if domain == &expected[..] => Ok(()),
+        _ => Err(Error::WrongPublicKey),
+    }
+}
+
+/// sighash flags (1 byte) | covered inputs | covered outputs
+///
+/// The covered inputs are every input, or with ANYONECANPAY only the inputs
+/// of this lock group followed by the puppet input, each one as its
+/// molecule `CellInput`. The covered outputs are every output, or with
+/// SINGLE only the outputs at the indexes of the group inputs, each one as
+/// its molecule `CellOutput` followed by the data length (u64 LE) and data.
+fn hash_covered(hasher: &mut Blake2b, lock_hash: &[u8; 32], puppet_id: usize, sighash: u8) -> Result<(), Error> {
+    hasher.update(&[sighash]);
+
+    let anyone_can_pay = sighash & SIGHASH_ANYONECANPAY != 0;
+    for i in 0.. {
+        let input = match load_input(i, Source::Input) {
+            Ok(input) => input,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(err.into()),
+        };
+        if !anyone_can_pay || load_cell_lock_hash(i, Source::Input)? == *lock_hash {
+            hasher.update(input.as_slice());
+        }
+    }
+    if anyone_can_pay {
+        hasher.update(load_input(puppet_id, Source::Input)?.as_slice());
+    }
+
+    if sighash & SIGHASH_SINGLE != 0 {
+        for i in 0.. {
+            match load_cell_lock_hash(i, Source::Input) {
+                Ok(hash) if hash == *lock_hash => match hash_output(hasher, i) {
+                    Err(Error::IndexOutOfBound) => return Err(Error::SighashSingleMissingOutput),
+                    result => result?,
+                },
+                Ok(_) => {},
+                Err(SysError::IndexOutOfBound) => break,
+                Err(err) => return Err(err.into()),
+            };
+        }
+    } else {
+        for i in 0.. {
+            match hash_output(hasher, i) {
+                Err(Error::IndexOutOfBound) => break,
+                result => result?,
+            };
+        }
+    }
+    Ok(())
+}
+
+/// sighash flags (1 byte) | range counts (u32 LE each) | inputs | outputs
+/// | cell deps | header deps
+///
+/// Only the items in the declared ranges are hashed, inputs and outputs like
+/// `hash_covered`, cell deps as molecule `CellDep` and header deps as the
+/// 32-byte block hash. The starts aren't signed, but every input of this lock
+/// group and the puppet input must lie within the inputs range.
+fn hash_otx(
+    hasher: &mut Blake2b,
+    lock_hash: &[u8; 32],
+    puppet_id: usize,
+    sighash: u8,
+    ranges: &OtxRanges,
+) -> Result<(), Error> {
+    if !ranges.inputs.contains(puppet_id) {
+        return Err(Error::OtxGroupNotCovered);
+    }
+    for i in 0.. {
+        match load_cell_lock_hash(i, Source::Input) {
+            Ok(hash) if hash == *lock_hash && !ranges.inputs.contains(i) => {
+                return Err(Error::OtxGroupNotCovered)
+            },
+            Ok(_) => {},
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(err.into()),
+        };
+    }
+
+    hasher.update(&[sighash]);
+    for range in &[&ranges.inputs, &ranges.outputs, &ranges.cell_deps, &ranges.header_deps] {
+        hasher.update(&range.count.to_le_bytes());
+    }
+
+    for i in range_indexes(&ranges.inputs) {
+        hasher.update(load_input(i, Source::Input).map_err(otx_range_error)?.as_slice());
+    }
+    for i in range_indexes(&ranges.outputs) {
+        match hash_output(hasher, i) {
+            Err(Error::IndexOutOfBound) => return Err(Error::OtxRangeOutOfBound),
+            result => result?,
+        };
+    }
+
+    let raw = load_transaction()?.raw();
+    let cell_deps = raw.cell_deps();
+    for i in range_indexes(&ranges.cell_deps) {
+        hasher.update(cell_deps.get(i).ok_or(Error::OtxRangeOutOfBound)?.as_slice());
+    }
+    let header_deps = raw.header_deps();
+    for i in range_indexes(&ranges.header_deps) {
+        hasher.update(&header_deps.get(i).ok_or(Error::OtxRangeOutOfBound)?.raw_data());
+    }
+    Ok(())
+}
+
+fn range_indexes(range: &OtxRange) -> core::ops::Range<usize> {
+    range.start as usize..range.end()
+}
+
+fn otx_range_error(err: SysError) -> Error {
+    match err {
+        SysError::IndexOutOfBound => Error::OtxRangeOutOfBound,
+        err => err.into(),
+    }
+}
+
+fn hash_output(hasher: &mut Blake2b, index: usize) -> Result<(), Error> {
+    let output = load_cell(index, Source::Output)?;
+    let data = load_cell_data(index, Source::Output)?;
+    hasher.update(output.as_slice());
+    hasher.update(&(data.len() as u64).to_le_bytes());
+    hasher.update(&data);
+    Ok(())
+}
+
+/// The header dep with block number 0, hashed from the header itself so the
+/// whole transaction doesn't have to fit in the heap.
+fn load_genesis_hash() -> Result<[u8; 32], Error> {
+    for i in 0.. {
+        let header = match load_header(i, Source::HeaderDep) {
+            Ok(header) => header,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(err.into()),
+        };
+        let number: u64 = header.raw().number().unpack();
+        if number == 0 {
+            let mut hash = [0u8; 32];
+            let mut hasher = new_blake2b();
+            hasher.update(header.as_slice());
+            hasher.finalize(&mut hash);
+            return Ok(hash);
+        }
+    }
+    Err(Error::GenesisHeaderMissing)
+}
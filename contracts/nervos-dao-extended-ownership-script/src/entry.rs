@@ -8,44 +8,159 @@ use core::result::Result;
 // Import CKB syscalls and structures
 // https://nervosnetwork.github.io/ckb-std/riscv64imac-unknown-none-elf/doc/ckb_std/index.html
 use ckb_std::{
-    debug,
-    high_level::{load_script, load_cell_type_hash, load_cell_data, load_witness_args, load_tx_hash},
+    high_level::{load_script, load_witness_args},
     ckb_types::{bytes::Bytes, prelude::*},
     ckb_constants::Source,
     error::SysError,
-    dynamic_loading::CKBDLContext
 };
 
 use crate::error::Error;
+use crate::chain::{Chain, PuppetCell, Syscalls};
+use crate::puppet::{Puppet, PUBKEY_HASH_LEN};
+use crate::rules;
+use crate::witness::{is_sighash_all, Auth, LockWitness, AUTH_PUBKEY_HASH, SIGHASH_ALL};
+use crate::domain;
 use blake2b_ref::{Blake2b, Blake2bBuilder};
-use ckb_lib_secp256k1::LibSecp256k1;
+use crate::library;
+use crate::args::LockArgs;
+use crate::lock_period;
+#[cfg(feature = "owner-lock")]
+use crate::owner_lock::puppet_lock_signature;
+use crate::verifier;
+use ckb_lib_secp256k1::{LibLocator, CODE_HASH_SECP256K1};
 
-fn new_blake2b() -> Blake2b {
+/// The signature authorizing this group.
+pub(crate) enum Signature {
+    /// recovered from the lock witness, over the message
+    Recoverable(LockWitness, [u8; 32]),
+    /// a bare signature in the lock witness, over the group's sighash-all
+    SighashAll,
+    /// already checked by the puppet's own secp256k1 lock for this pubkey hash
+    #[cfg_attr(not(feature = "owner-lock"), allow(dead_code))]
+    Delegated([u8; PUBKEY_HASH_LEN]),
+}
+
+pub fn new_blake2b() -> Blake2b {
     Blake2bBuilder::new(32)
         .personal(b"ckb-default-hash")
         .build()
 }
 
-pub fn main() -> Result<(), Error> {
+/// The group's first witness, unless its lock field is empty.
+enum GroupWitness {
+    /// a bare signature, over the sighash-all of secp256k1_blake2b_sighash_all,
+    /// with the `output_type` field holding its domain
+    SighashAll(Option<Bytes>),
+    Lock(LockWitness),
+}
 
-    let mut total_puppet = 0;
-    let mut puppet_id = 0;
+impl GroupWitness {
+    fn puppet_index(&self) -> Option<u32> {
+        match self {
+            GroupWitness::SighashAll(_) => None,
+            GroupWitness::Lock(lock_witness) => lock_witness.puppet_index,
+        }
+    }
+}
 
-    let script = load_script()?;
-    let args: Bytes = script.args().unpack();
+fn load_group_witness() -> Result<Option<GroupWitness>, Error> {
+    let witness_args = match load_witness_args(0, Source::GroupInput) {
+        Ok(witness_args) => witness_args,
+        Err(SysError::IndexOutOfBound) | Err(SysError::Encoding) => return Ok(None),
+        Err(_err) => return Err(Error::LoadWitnessArgs),
+    };
+    let witness: Bytes = match witness_args.lock().to_opt() {
+        Some(lock) => lock.unpack(),
+        None => return Ok(None),
+    };
+    if witness.is_empty() {
+        return Ok(None);
+    }
+    if is_sighash_all(&witness) {
+        trace!("witness", kind = AUTH_PUBKEY_HASH, sighash = SIGHASH_ALL);
+        let domain = witness_args.output_type().to_opt().map(|domain| domain.unpack());
+        return Ok(Some(GroupWitness::SighashAll(domain)));
+    }
+    let lock_witness = LockWitness::from_slice(&witness)?;
+    trace!("witness", kind = lock_witness.auth.kind(), sighash = lock_witness.sighash);
+    Ok(Some(GroupWitness::Lock(lock_witness)))
+}
 
-    if args.is_empty() || args.len() != 32 {
-        return Err(Error::NoValidArgs);
+/// Find the signature shared with the puppet input.
+///
+/// When the group's own lock witness is empty, one signature authorizes both
+/// cells: the `input_type` field of the puppet's witness, or else the
+/// signature of the puppet's own secp256k1 lock. The first one signs the
+/// domain separated message, the second one the puppet lock's sighash-all.
+///
+/// The second one needs the `owner-lock` feature, see `owner_lock.rs`.
+fn load_shared_signature(
+    puppet_type_hash: &[u8],
+    puppet_id: usize,
+    locator: &LibLocator,
+) -> Result<Signature, Error> {
+    let puppet_witness = match load_witness_args(puppet_id, Source::Input) {
+        Ok(data) => data,
+        Err(SysError::IndexOutOfBound) | Err(SysError::Encoding) => return Err(Error::WitnessArgsEncoding),
+        Err(_err) => return Err(Error::LoadWitnessArgs),
     };
+    if let Some(input_type) = puppet_witness.input_type().to_opt() {
+        let witness: Bytes = input_type.unpack();
+        let lock_witness = LockWitness::from_slice(&witness)?;
+        let message = domain::signing_message(puppet_type_hash, puppet_id, &lock_witness)?;
+        return Ok(Signature::Recoverable(lock_witness, message));
+    }
+
+    puppet_lock_signature(puppet_id, locator)
+}
+
+/// without the `owner-lock` feature only `input_type` can share a signature
+#[cfg(not(feature = "owner-lock"))]
+fn puppet_lock_signature(_puppet_id: usize, _locator: &LibLocator) -> Result<Signature, Error> {
+    Err(Error::UnsupportedAuth)
+}
+
+/// Find the puppet cell.
+///
+/// With a hint from the witness only that input is checked, the puppet's type
+/// id script already guarantees there's no second puppet among the inputs.
+/// Without one every input is scanned, and when the args allow the cell-dep
+/// ownership proof, every cell dep after that.
+fn find_puppet<C: Chain>(
+    chain: &C,
+    puppet_type_hash: &[u8],
+    hint: Option<u32>,
+    cell_dep_proof: bool,
+) -> Result<PuppetCell, Error> {
+    if let Some(index) = hint {
+        let index = index as usize;
+        return match chain.cell_type_hash(index, Source::Input) {
+            Ok(Some(type_hash)) if puppet_type_hash[..] == type_hash[..] => Ok(PuppetCell::input(index)),
+            Ok(_) | Err(SysError::IndexOutOfBound) => Err(Error::PuppetIndexMismatch),
+            Err(err) => Err(err.into()),
+        };
+    }
+
+    match scan_puppets(chain, puppet_type_hash, Source::Input) {
+        Err(Error::NoPuppetCellFound) if cell_dep_proof => {
+            scan_puppets(chain, puppet_type_hash, Source::CellDep)
+        },
+        result => result,
+    }
+}
+
+/// the only cell in `source` with the puppet type
+fn scan_puppets<C: Chain>(chain: &C, puppet_type_hash: &[u8], source: Source) -> Result<PuppetCell, Error> {
+    let mut total_puppet = 0;
+    let mut puppet_id = 0;
 
-    // debug!("start looking for puppet cell...");
     // let's find the puppet cell attched to nervosDAO cell
     for i in 0.. {
-        match load_cell_type_hash(i, Source::Input){
+        match chain.cell_type_hash(i, source){
             Ok(result) => {
                 match result{
                     Some(type_hash) => {
-                        if args[..] == type_hash[..] {
+                        if puppet_type_hash[..] == type_hash[..] {
                             total_puppet = total_puppet + 1;
                             puppet_id = i;
                         };
@@ -58,77 +173,138 @@ pub fn main() -> Result<(), Error> {
         };
     }
 
-    // debug!("finished looking for puppet cell..., 
-    //    found puppet id: {:?}, total_puppet: {:?}", 
-    //    puppet_id, total_puppet
-    // );
+    trace!("scan", source = source, puppets = total_puppet);
 
     match total_puppet {
-        0 => return Err(Error::NoPuppetCellFound),
-        n if n > 1 => return Err(Error::RequiredOnlyOnePuppet),
-        _ => {},
+        0 => Err(Error::NoPuppetCellFound),
+        n if n > 1 => Err(Error::RequiredOnlyOnePuppet),
+        _ => Ok(PuppetCell { index: puppet_id, source }),
     }
+}
 
-    // debug!("start to find public key hash...");
-
-    let public_key_hash = match load_cell_data(puppet_id, Source::Input){
+/// data of the puppet cell, parsed
+fn load_puppet<C: Chain>(chain: &C, puppet_cell: PuppetCell) -> Result<Puppet, Error> {
+    let puppet_data = match chain.cell_data(puppet_cell.index, puppet_cell.source){
         Ok(data) => data,
         Err(SysError::IndexOutOfBound) => return Err(Error::NoPuppetCellFound),
         Err(err) => return Err(err.into()),
     };
-    // debug!("public key is {:?} {:?}", public_key_hash, public_key_hash.pack());
-    
-    if public_key_hash.len() != 20 {
-        return Err(Error::WrongPubkeyHashLength);
+    trace!("puppet", index = puppet_cell.index, source = puppet_cell.source);
+
+    Ok(Puppet::from_slice(&puppet_data)?)
+}
+
+/// The auth, the signer and, for secp256k1 signatures, its pubkey.
+fn resolve_signer(
+    signature: Signature,
+    locator: &LibLocator,
+) -> Result<(Auth, [u8; PUBKEY_HASH_LEN], Option<[u8; 33]>), Error> {
+    match signature {
+        // no need to load the secp256k1 library and its 1 MB table again
+        Signature::Delegated(pubkey_hash) => {
+            trace!("signer", kind = AUTH_PUBKEY_HASH, hash = pubkey_hash, delegated = true);
+            Ok((Auth::PubkeyHash, pubkey_hash, None))
+        },
+        Signature::Recoverable(lock_witness, message) => {
+            let (signer, sign_pubkey) = verifier::verify_signer(locator, &lock_witness, &message)?;
+            trace!("signer", kind = lock_witness.auth.kind(), hash = signer, delegated = false);
+            Ok((lock_witness.auth, signer, sign_pubkey))
+        },
+        // verified with the library's own table, nothing to copy either
+        Signature::SighashAll => {
+            let signer = verifier::sighash_all_signer(locator)?;
+            trace!("signer", kind = AUTH_PUBKEY_HASH, hash = signer, delegated = false);
+            Ok((Auth::PubkeyHash, signer, None))
+        },
     }
+}
 
-    let witness_args = match load_witness_args(0, Source::GroupInput) {
-        Ok(data) => data,
-        Err(SysError::IndexOutOfBound) => return Err(Error::NoPuppetCellFound),
-        Err(_err) => return Err(Error::LoadWitnessArgs),
+pub fn main() -> Result<(), Error> {
+    let result = check_lock();
+    trace!("decision", allow = result.is_ok(), code = result);
+    result
+}
+
+fn check_lock() -> Result<(), Error> {
+    let chain = Syscalls;
+
+    let script = load_script()?;
+    let args: Bytes = script.args().unpack();
+    let args = LockArgs::from_slice(&args)?;
+    let puppet_type_hash = &args.puppet_type_hash[..];
+    let locator = library::locator(&args.library)?;
+    if let Some(epochs) = args.lock_period {
+        lock_period::check(&chain, epochs)?;
+    }
+
+    let group_witness = load_group_witness()?;
+    let hint = group_witness.as_ref().and_then(GroupWitness::puppet_index);
+    let puppet_cell = find_puppet(&chain, puppet_type_hash, hint, args.cell_dep_proof)?;
+    let on_input = puppet_cell.source == Source::Input;
+
+    let puppet = load_puppet(&chain, puppet_cell)?;
+
+    let signature = match group_witness {
+        Some(GroupWitness::SighashAll(domain)) => {
+            domain::check_sighash_all_domain(puppet_type_hash, domain.as_deref())?;
+            Signature::SighashAll
+        },
+        Some(GroupWitness::Lock(lock_witness)) => {
+            // partial signatures cover the puppet as an input
+            if !on_input && lock_witness.sighash != SIGHASH_ALL {
+                return Err(Error::UnknownSighashMode);
+            }
+            let message = domain::signing_message(puppet_type_hash, puppet_cell.index, &lock_witness)?;
+            Signature::Recoverable(lock_witness, message)
+        },
+        // a cell dep has no witness to share a signature through
+        None if on_input => load_shared_signature(puppet_type_hash, puppet_cell.index, &locator)?,
+        None => return Err(Error::WitnessArgsEncoding),
     };
-    // debug!("witness_args: {:?}", witness_args);
-
-    let witness: Bytes = witness_args
-            .lock()
-            .to_opt()
-            .ok_or(Error::WitnessArgsEncoding)?
-            .unpack();
-    
-    // Validate the signatures of puppet cell
-    // debug!("...start check signature with secp256");
-    let mut context = unsafe{ CKBDLContext::<[u8; 128 * 1024]>::new()};
-    let lib = LibSecp256k1::load(&mut context);
- 
-    // recover pubkey_hash
-    let prefilled_data = lib.load_prefilled_data().map_err(|err| {
-        debug!("load prefilled data error: {}", err);
-        Error::LoadPrefilledData
-    })?;
-
-    let message:[u8; 32] = load_tx_hash().unwrap();
-    let mut signature = [0u8; 65];
-    let sig_len = signature.len();
-    signature.copy_from_slice(&witness[0..sig_len]);
-    
-    let sign_pubkey = lib
-            .recover_pubkey(&prefilled_data, &signature, &message)
-            .map_err(|err| {
-                debug!("recover pubkey error: {}", err);
-                Error::RecoverPubkey
-            })?;
-    let sign_pubkey_hash = {
-            let mut buf = [0u8; 32];
-            let mut hasher = new_blake2b();
-            hasher.update(sign_pubkey.as_slice());
-            hasher.finalize(&mut buf);
-            buf
+
+    if let Some(auth_kind) = args.auth_kind {
+        let signed_with = match signature {
+            Signature::Recoverable(ref lock_witness, _) => lock_witness.auth.kind(),
+            Signature::SighashAll | Signature::Delegated(_) => AUTH_PUBKEY_HASH,
         };
-        
-    if sign_pubkey_hash[..20] == public_key_hash[..] {
-        return Ok(());
-    }else{
-        return Err(Error::WrongPublicKey);
+        if signed_with != auth_kind {
+            return Err(Error::AuthKindNotAllowed);
+        }
     }
+
+    let (auth, signer, sign_pubkey) = resolve_signer(signature, &locator)?;
+    let signer = &signer[..];
+
+    let is_owner = rules::is_owner(&puppet, signer, &auth, sign_pubkey.as_ref())?;
+    rules::authorize(&chain, puppet_type_hash, puppet_cell, &puppet, signer, is_owner)
 }
 
+/// Whether the owner of the puppet with `puppet_type_hash` authorizes the
+/// current transaction, for other scripts through the library build.
+///
+/// The puppet has to be an input, signed for like the DAO cell with an empty
+/// lock witness, see `load_shared_signature`, except that the signing
+/// message binds the calling script's hash. The owner's cooldown and lease
+/// restrictions apply as they do to the lock.
+pub fn verify_puppet_owner(puppet_type_hash: &[u8]) -> Result<(), Error> {
+    let result = check_puppet_owner(puppet_type_hash);
+    trace!("decision", allow = result.is_ok(), code = result);
+    result
+}
+
+fn check_puppet_owner(puppet_type_hash: &[u8]) -> Result<(), Error> {
+    let chain = Syscalls;
+    let locator = LibLocator::DataHash(CODE_HASH_SECP256K1);
+
+    let puppet_cell = find_puppet(&chain, puppet_type_hash, None, false)?;
+    let puppet = load_puppet(&chain, puppet_cell)?;
+
+    let signature = load_shared_signature(puppet_type_hash, puppet_cell.index, &locator)?;
+    let (auth, signer, sign_pubkey) = resolve_signer(signature, &locator)?;
+    let signer = &signer[..];
+
+    if !rules::is_owner(&puppet, signer, &auth, sign_pubkey.as_ref())? {
+        return Err(Error::WrongPublicKey);
+    }
+    rules::authorize(&chain, puppet_type_hash, puppet_cell, &puppet, signer, true)
+}
@@ -1,22 +1,86 @@
 use ckb_std::error::SysError;
+use ckb_lib_secp256k1::LibError;
+#[cfg(feature = "eth")]
+use ckb_lib_eth::LibError as EthLibError;
+#[cfg(feature = "rsa")]
+use ckb_lib_rsa::LibError as RsaLibError;
+use dao_ownership_types::Error as TypesError;
 
-/// Error
+use crate::error_code::*;
+
+/// Error, the codes are listed in `error_code`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(i8)]
 pub enum Error {
-    IndexOutOfBound = 1,
-    ItemMissing,
-    LengthNotEnough,
-    Encoding,
-    // Add customized errors here...
-    NoValidArgs, 
-    NoPuppetCellFound,
-    RequiredOnlyOnePuppet,
-    LoadPrefilledData,
-    RecoverPubkey,
-    WrongPublicKey,
-    WrongPubkeyHashLength,
-    LoadWitnessArgs,
-    WitnessArgsEncoding,
+    IndexOutOfBound = ERROR_INDEX_OUT_OF_BOUND,
+    ItemMissing = ERROR_ITEM_MISSING,
+    LengthNotEnough = ERROR_LENGTH_NOT_ENOUGH,
+    Encoding = ERROR_ENCODING,
+    NoValidArgs = ERROR_NO_VALID_ARGS,
+    NoPuppetCellFound = ERROR_NO_PUPPET_CELL_FOUND,
+    RequiredOnlyOnePuppet = ERROR_REQUIRED_ONLY_ONE_PUPPET,
+    LoadPrefilledData = ERROR_LOAD_PREFILLED_DATA,
+    RecoverPubkey = ERROR_RECOVER_PUBKEY,
+    WrongPublicKey = ERROR_WRONG_PUBLIC_KEY,
+    WrongPubkeyHashLength = ERROR_WRONG_PUBKEY_HASH_LENGTH,
+    LoadWitnessArgs = ERROR_LOAD_WITNESS_ARGS,
+    WitnessArgsEncoding = ERROR_WITNESS_ARGS_ENCODING,
+    InvalidPuppetData = ERROR_INVALID_PUPPET_DATA,
+    OperatorPhase1Denied = ERROR_OPERATOR_PHASE1_DENIED,
+    OperatorPhase2Denied = ERROR_OPERATOR_PHASE2_DENIED,
+    OperatorTransferDenied = ERROR_OPERATOR_TRANSFER_DENIED,
+    OperatorActionDenied = ERROR_OPERATOR_ACTION_DENIED,
+    CooldownActive = ERROR_COOLDOWN_ACTIVE,
+    CooldownCancelOnly = ERROR_COOLDOWN_CANCEL_ONLY,
+    CooldownRecordMismatch = ERROR_COOLDOWN_RECORD_MISMATCH,
+    LeaseActive = ERROR_LEASE_ACTIVE,
+    LeaseExpired = ERROR_LEASE_EXPIRED,
+    UnknownAuthKind = ERROR_UNKNOWN_AUTH_KIND,
+    MerkleProofInvalid = ERROR_MERKLE_PROOF_INVALID,
+    CommitmentMismatch = ERROR_COMMITMENT_MISMATCH,
+    GenesisHeaderMissing = ERROR_GENESIS_HEADER_MISSING,
+    UnknownSighashMode = ERROR_UNKNOWN_SIGHASH_MODE,
+    SighashSingleMissingOutput = ERROR_SIGHASH_SINGLE_MISSING_OUTPUT,
+    OtxRangeOutOfBound = ERROR_OTX_RANGE_OUT_OF_BOUND,
+    OtxGroupNotCovered = ERROR_OTX_GROUP_NOT_COVERED,
+    PuppetIndexMismatch = ERROR_PUPPET_INDEX_MISMATCH,
+    // secp256k1 library
+    LibraryNotFound = ERROR_LIBRARY_NOT_FOUND,
+    LibrarySymbolMissing = ERROR_LIBRARY_SYMBOL_MISSING,
+    LibraryMemoryNotEnough = ERROR_LIBRARY_MEMORY_NOT_ENOUGH,
+    LibraryInvalid = ERROR_LIBRARY_INVALID,
+    SecpParseSignature = ERROR_SECP_PARSE_SIGNATURE,
+    SecpSerializePubkey = ERROR_SECP_SERIALIZE_PUBKEY,
+    SecpInvalidSignatureSize = ERROR_SECP_INVALID_SIGNATURE_SIZE,
+    SecpInvalidMessageSize = ERROR_SECP_INVALID_MESSAGE_SIZE,
+    SecpInvalidOutputSize = ERROR_SECP_INVALID_OUTPUT_SIZE,
+    SecpUnknown = ERROR_SECP_UNKNOWN,
+    SecpParsePubkey = ERROR_SECP_PARSE_PUBKEY,
+    SecpVerification = ERROR_SECP_VERIFICATION,
+    PrefilledDataAllocation = ERROR_PREFILLED_DATA_ALLOCATION,
+    // ethereum and rsa libraries
+    EthVerification = ERROR_ETH_VERIFICATION,
+    RsaVerification = ERROR_RSA_VERIFICATION,
+    // lock args extensions
+    AuthKindNotAllowed = ERROR_AUTH_KIND_NOT_ALLOWED,
+    LockPeriodActive = ERROR_LOCK_PERIOD_ACTIVE,
+    // lock witness layout
+    WitnessTooShort = ERROR_WITNESS_TOO_SHORT,
+    InvalidWitnessPubkey = ERROR_INVALID_WITNESS_PUBKEY,
+    InvalidAuthPayload = ERROR_INVALID_AUTH_PAYLOAD,
+    WitnessMoleculeEncoding = ERROR_WITNESS_MOLECULE_ENCODING,
+    // a syscall code ckb-std doesn't know
+    UnknownSysError = ERROR_UNKNOWN_SYS_ERROR,
+    // an auth kind this build doesn't verify
+    UnsupportedAuth = ERROR_UNSUPPORTED_AUTH,
+    // withdrawal payout
+    PayoutLockMismatch = ERROR_PAYOUT_LOCK_MISMATCH,
+    // puppet type script
+    PuppetTypeIdMismatch = ERROR_PUPPET_TYPE_ID_MISMATCH,
+    // cell-dep ownership proof
+    CellDepProofDenied = ERROR_CELL_DEP_PROOF_DENIED,
+    // guard lock of a pending transfer
+    CooldownGuardMismatch = ERROR_COOLDOWN_GUARD_MISMATCH,
 }
 
 impl From<SysError> for Error {
@@ -27,8 +91,74 @@ impl From<SysError> for Error {
             ItemMissing => Self::ItemMissing,
             LengthNotEnough(_) => Self::LengthNotEnough,
             Encoding => Self::Encoding,
-            Unknown(err_code) => panic!("unexpected sys error {}", err_code),
+            Unknown(_) => Self::UnknownSysError,
+        }
+    }
+}
+
+impl From<TypesError> for Error {
+    fn from(err: TypesError) -> Self {
+        match err {
+            TypesError::InvalidArgs => Self::NoValidArgs,
+            TypesError::WrongPubkeyHashLength => Self::WrongPubkeyHashLength,
+            TypesError::InvalidPuppetData => Self::InvalidPuppetData,
+            TypesError::WitnessEncoding => Self::WitnessArgsEncoding,
+            TypesError::UnknownSighashMode => Self::UnknownSighashMode,
+            TypesError::UnknownAuthKind => Self::UnknownAuthKind,
+            TypesError::WitnessTooShort => Self::WitnessTooShort,
+            TypesError::InvalidWitnessPubkey => Self::InvalidWitnessPubkey,
+            TypesError::InvalidAuthPayload => Self::InvalidAuthPayload,
+            TypesError::WitnessMolecule => Self::WitnessMoleculeEncoding,
+        }
+    }
+}
+
+impl From<LibError> for Error {
+    fn from(err: LibError) -> Self {
+        match err {
+            LibError::LibraryNotFound => Self::LibraryNotFound,
+            LibError::SymbolMissing => Self::LibrarySymbolMissing,
+            LibError::InsufficientMemory => Self::LibraryMemoryNotEnough,
+            LibError::AllocationFailed => Self::PrefilledDataAllocation,
+            LibError::InvalidLibrary => Self::LibraryInvalid,
+            // the only syscall the library makes for us loads its table
+            LibError::Syscall | LibError::InvalidPrefilledDataSize => Self::LoadPrefilledData,
+            LibError::SecpRecoverPubkey => Self::RecoverPubkey,
+            LibError::SecpParseSignature => Self::SecpParseSignature,
+            LibError::SecpSerializePubkey => Self::SecpSerializePubkey,
+            LibError::InvalidSignatureSize => Self::SecpInvalidSignatureSize,
+            LibError::InvalidMessageSize => Self::SecpInvalidMessageSize,
+            LibError::InvalidOutputSize => Self::SecpInvalidOutputSize,
+            LibError::SecpParsePubkey => Self::SecpParsePubkey,
+            LibError::SecpVerification => Self::SecpVerification,
+            _ => Self::SecpUnknown,
+        }
+    }
+}
+
+#[cfg(feature = "eth")]
+impl From<EthLibError> for Error {
+    fn from(err: EthLibError) -> Self {
+        match err {
+            EthLibError::LibraryNotFound => Self::LibraryNotFound,
+            EthLibError::SymbolMissing => Self::LibrarySymbolMissing,
+            EthLibError::InsufficientMemory => Self::LibraryMemoryNotEnough,
+            EthLibError::InvalidLibrary => Self::LibraryInvalid,
+            EthLibError::Syscall | EthLibError::InvalidPrefilledDataSize => Self::LoadPrefilledData,
+            _ => Self::EthVerification,
         }
     }
 }
 
+#[cfg(feature = "rsa")]
+impl From<RsaLibError> for Error {
+    fn from(err: RsaLibError) -> Self {
+        match err {
+            RsaLibError::LibraryNotFound => Self::LibraryNotFound,
+            RsaLibError::SymbolMissing => Self::LibrarySymbolMissing,
+            RsaLibError::InsufficientMemory => Self::LibraryMemoryNotEnough,
+            RsaLibError::InvalidLibrary => Self::LibraryInvalid,
+            _ => Self::RsaVerification,
+        }
+    }
+}
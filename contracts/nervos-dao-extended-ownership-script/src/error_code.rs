@@ -0,0 +1,80 @@
+//! The lock's exit codes.
+//!
+//! `Error` takes its discriminants from here, so a code never changes when
+//! a variant is added or moved. New codes are appended, a retired code is
+//! never reused. The tests and off-chain tooling match failures against
+//! these constants, see `docs/error_codes.md` for what each one means.
+
+// syscalls
+pub const ERROR_INDEX_OUT_OF_BOUND: i8 = 1;
+pub const ERROR_ITEM_MISSING: i8 = 2;
+pub const ERROR_LENGTH_NOT_ENOUGH: i8 = 3;
+pub const ERROR_ENCODING: i8 = 4;
+// args, puppet and signature
+pub const ERROR_NO_VALID_ARGS: i8 = 5;
+pub const ERROR_NO_PUPPET_CELL_FOUND: i8 = 6;
+pub const ERROR_REQUIRED_ONLY_ONE_PUPPET: i8 = 7;
+pub const ERROR_LOAD_PREFILLED_DATA: i8 = 8;
+pub const ERROR_RECOVER_PUBKEY: i8 = 9;
+pub const ERROR_WRONG_PUBLIC_KEY: i8 = 10;
+pub const ERROR_WRONG_PUBKEY_HASH_LENGTH: i8 = 11;
+pub const ERROR_LOAD_WITNESS_ARGS: i8 = 12;
+pub const ERROR_WITNESS_ARGS_ENCODING: i8 = 13;
+pub const ERROR_INVALID_PUPPET_DATA: i8 = 14;
+// ownership rules
+pub const ERROR_OPERATOR_PHASE1_DENIED: i8 = 15;
+pub const ERROR_OPERATOR_PHASE2_DENIED: i8 = 16;
+pub const ERROR_OPERATOR_TRANSFER_DENIED: i8 = 17;
+pub const ERROR_OPERATOR_ACTION_DENIED: i8 = 18;
+pub const ERROR_COOLDOWN_ACTIVE: i8 = 19;
+pub const ERROR_COOLDOWN_CANCEL_ONLY: i8 = 20;
+pub const ERROR_COOLDOWN_RECORD_MISMATCH: i8 = 21;
+pub const ERROR_LEASE_ACTIVE: i8 = 22;
+pub const ERROR_LEASE_EXPIRED: i8 = 23;
+pub const ERROR_UNKNOWN_AUTH_KIND: i8 = 24;
+pub const ERROR_MERKLE_PROOF_INVALID: i8 = 25;
+pub const ERROR_COMMITMENT_MISMATCH: i8 = 26;
+pub const ERROR_GENESIS_HEADER_MISSING: i8 = 27;
+// sighash modes and open transactions
+pub const ERROR_UNKNOWN_SIGHASH_MODE: i8 = 28;
+pub const ERROR_SIGHASH_SINGLE_MISSING_OUTPUT: i8 = 29;
+pub const ERROR_OTX_RANGE_OUT_OF_BOUND: i8 = 30;
+pub const ERROR_OTX_GROUP_NOT_COVERED: i8 = 31;
+pub const ERROR_PUPPET_INDEX_MISMATCH: i8 = 32;
+// secp256k1 library
+pub const ERROR_LIBRARY_NOT_FOUND: i8 = 33;
+pub const ERROR_LIBRARY_SYMBOL_MISSING: i8 = 34;
+pub const ERROR_LIBRARY_MEMORY_NOT_ENOUGH: i8 = 35;
+pub const ERROR_LIBRARY_INVALID: i8 = 36;
+pub const ERROR_SECP_PARSE_SIGNATURE: i8 = 37;
+pub const ERROR_SECP_SERIALIZE_PUBKEY: i8 = 38;
+pub const ERROR_SECP_INVALID_SIGNATURE_SIZE: i8 = 39;
+pub const ERROR_SECP_INVALID_MESSAGE_SIZE: i8 = 40;
+pub const ERROR_SECP_INVALID_OUTPUT_SIZE: i8 = 41;
+pub const ERROR_SECP_UNKNOWN: i8 = 42;
+pub const ERROR_SECP_PARSE_PUBKEY: i8 = 43;
+pub const ERROR_SECP_VERIFICATION: i8 = 44;
+pub const ERROR_PREFILLED_DATA_ALLOCATION: i8 = 45;
+// ethereum and rsa libraries
+pub const ERROR_ETH_VERIFICATION: i8 = 46;
+pub const ERROR_RSA_VERIFICATION: i8 = 47;
+// lock args extensions
+pub const ERROR_AUTH_KIND_NOT_ALLOWED: i8 = 48;
+pub const ERROR_LOCK_PERIOD_ACTIVE: i8 = 49;
+// lock witness layout
+pub const ERROR_WITNESS_TOO_SHORT: i8 = 50;
+pub const ERROR_INVALID_WITNESS_PUBKEY: i8 = 51;
+pub const ERROR_INVALID_AUTH_PAYLOAD: i8 = 52;
+pub const ERROR_WITNESS_MOLECULE_ENCODING: i8 = 53;
+// syscalls
+pub const ERROR_UNKNOWN_SYS_ERROR: i8 = 54;
+// contract variants
+pub const ERROR_UNSUPPORTED_AUTH: i8 = 55;
+// withdrawal payout
+pub const ERROR_PAYOUT_LOCK_MISMATCH: i8 = 56;
+// puppet type script
+pub const ERROR_PUPPET_TYPE_ID_MISMATCH: i8 = 57;
+// cell-dep ownership proof
+pub const ERROR_CELL_DEP_PROOF_DENIED: i8 = 58;
+// guard lock of a pending transfer
+pub const ERROR_COOLDOWN_GUARD_MISMATCH: i8 = 59;
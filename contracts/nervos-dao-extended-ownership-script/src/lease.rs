@@ -0,0 +1,53 @@
+// Import CKB syscalls and structures
+use ckb_std::error::SysError;
+
+use crate::chain::{Chain, PuppetCell};
+use crate::error::Error;
+use crate::puppet::Lease;
+use crate::since;
+
+/// The lessor gets the position back by proving the lease is over, the
+/// puppet input must not be committed before the expiry epoch.
+pub fn check_expired<C: Chain>(chain: &C, puppet_cell: PuppetCell, lease: &Lease) -> Result<(), Error> {
+    let since = puppet_cell.since(chain)?;
+    match since::absolute_epoch(since) {
+        Some(epoch) if epoch >= lease.expiry_epoch => Ok(()),
+        _ => Err(Error::LeaseActive),
+    }
+}
+
+/// The lessee has to show the lease is running: the puppet input must carry
+/// an absolute epoch `since` before the expiry epoch, and not before the
+/// epoch the puppet was created in, whose header must be a header dep. A
+/// header dep from the expiry epoch on, such as the header a DAO withdrawal
+/// needs, proves the lease is over.
+///
+/// A transaction can't bound its commit time from above: a lessee leaving
+/// the newer headers out can still act once after the expiry. Acting
+/// recreates the puppet past the expiry, which ends the lease for good, and
+/// any withdrawal is paid to the owner. The lessor is expected to take the
+/// position back once the lease expires.
+pub fn check_lessee<C: Chain>(chain: &C, puppet_cell: PuppetCell, lease: &Lease) -> Result<(), Error> {
+    let since = since::absolute_epoch(puppet_cell.since(chain)?).ok_or(Error::LeaseExpired)?;
+    let created = match puppet_cell.epoch(chain) {
+        Ok(epoch) => epoch,
+        Err(SysError::ItemMissing) | Err(SysError::IndexOutOfBound) => return Err(Error::LeaseExpired),
+        Err(err) => return Err(err.into()),
+    };
+    if since < created || since >= lease.expiry_epoch {
+        return Err(Error::LeaseExpired);
+    }
+    check_header_deps(chain, lease)
+}
+
+fn check_header_deps<C: Chain>(chain: &C, lease: &Lease) -> Result<(), Error> {
+    for i in 0.. {
+        match chain.header_dep_epoch(i) {
+            Ok(epoch) if epoch >= lease.expiry_epoch => return Err(Error::LeaseExpired),
+            Ok(_) => {},
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(())
+}
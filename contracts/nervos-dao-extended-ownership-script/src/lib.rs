@@ -0,0 +1,36 @@
+//! The extended ownership lock as a library.
+//!
+//! `main.rs` builds it into the contract binary. Natively, the ownership
+//! rules in `rules` run against any `chain::Chain`, so they can be unit
+//! tested without a VM, see `tests/src/native.rs`. The args, puppet data
+//! and witness layouts come from `dao-ownership-types`. The `trace`
+//! feature adds structured debug output, see `trace.rs`, the other features
+//! choose the verifiers compiled in, see `verifier.rs`.
+
+#![no_std]
+
+extern crate alloc;
+
+#[macro_use]
+mod trace;
+pub mod entry;
+pub mod error;
+pub mod error_code;
+pub mod chain;
+pub mod rules;
+pub mod action;
+pub mod since;
+pub mod cooldown;
+pub mod lease;
+pub mod lock_period;
+pub mod merkle;
+pub mod puppet_type;
+mod verifier;
+#[cfg(feature = "owner-lock")]
+mod owner_lock;
+#[cfg(feature = "owner-lock")]
+mod sighash;
+mod domain;
+mod library;
+
+pub use dao_ownership_types::{args, puppet, witness};
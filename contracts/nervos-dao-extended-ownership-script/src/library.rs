@@ -0,0 +1,58 @@
+// Import CKB syscalls and structures
+use ckb_std::{
+    high_level::load_cell_type_hash,
+    ckb_constants::Source,
+    error::SysError,
+};
+
+use ckb_lib_secp256k1::{LibLocator, CODE_HASH_SECP256K1};
+
+use crate::args::LibraryLocator;
+use crate::error::Error;
+
+/// `hash_type` of a script referring to its code by data hash or by type hash
+#[cfg(feature = "owner-lock")]
+const HASH_TYPE_DATA: u8 = 0;
+#[cfg(feature = "owner-lock")]
+const HASH_TYPE_TYPE: u8 = 1;
+
+/// the secp256k1 library the args point at
+pub fn locator(library: &LibraryLocator) -> Result<LibLocator, Error> {
+    match *library {
+        LibraryLocator::Default => Ok(LibLocator::DataHash(CODE_HASH_SECP256K1)),
+        LibraryLocator::TypeHash(type_hash) => Ok(LibLocator::TypeHash(type_hash)),
+        LibraryLocator::CellDep { type_hash, index } => {
+            let index = index as usize;
+            // anyone could put any code at that index, pin it by type hash
+            match load_cell_type_hash(index, Source::CellDep) {
+                Ok(Some(dep_type_hash)) if dep_type_hash == type_hash => Ok(LibLocator::CellDep(index)),
+                Ok(_) | Err(SysError::IndexOutOfBound) => Err(Error::LibraryNotFound),
+                Err(err) => Err(err.into()),
+            }
+        },
+    }
+}
+
+/// `locator` moved to the library bundled with `code_hash`, for the auth
+/// kinds the secp256k1 library doesn't verify. Without a locator in the args
+/// each library is the cell dep with its bundled code hash, a locator in the
+/// args names the library verifying the signature, whichever it is.
+#[cfg(any(feature = "eth", feature = "rsa"))]
+pub fn with_code_hash(locator: &LibLocator, code_hash: [u8; 32]) -> LibLocator {
+    match *locator {
+        LibLocator::DataHash(_) => LibLocator::DataHash(code_hash),
+        LibLocator::TypeHash(type_hash) => LibLocator::TypeHash(type_hash),
+        LibLocator::CellDep(index) => LibLocator::CellDep(index),
+    }
+}
+
+/// the script `code_hash` and `hash_type` which run the library `locator`
+/// points at, if they are known without loading it
+#[cfg(feature = "owner-lock")]
+pub fn script_code(locator: &LibLocator) -> Option<([u8; 32], u8)> {
+    match locator {
+        LibLocator::DataHash(hash) => Some((*hash, HASH_TYPE_DATA)),
+        LibLocator::TypeHash(hash) => Some((*hash, HASH_TYPE_TYPE)),
+        LibLocator::CellDep(_) => None,
+    }
+}
@@ -0,0 +1,33 @@
+// Import CKB syscalls and structures
+use ckb_std::{
+    ckb_constants::Source,
+    error::SysError,
+};
+
+use crate::chain::Chain;
+use crate::error::Error;
+use crate::since;
+
+/// Deposits guarded by this lock can't be spent, not even to start a
+/// withdrawal, until they are `epochs` old. Withdrawing cells are left to
+/// the nervosDAO's own since rules.
+///
+/// Only the cells of this lock's group are read, other inputs cost nothing.
+pub fn check<C: Chain>(chain: &C, epochs: u64) -> Result<(), Error> {
+    for i in 0.. {
+        // a deposit holds a zero block number
+        let data = match chain.cell_data(i, Source::GroupInput) {
+            Ok(data) => data,
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(err.into()),
+        };
+        if data.len() != 8 || data.iter().any(|b| *b != 0) {
+            continue;
+        }
+        match since::relative_epochs(chain.input_since(i, Source::GroupInput)?) {
+            Some(age) if age >= epochs => {},
+            _ => return Err(Error::LockPeriodActive),
+        }
+    }
+    Ok(())
+}
@@ -3,6 +3,7 @@
 //! `main.rs` is used to define rust lang items and modules.
 //! See `entry.rs` for the `main` function. 
 //! See `error.rs` for the `Error` type.
+//! See `lib.rs` for the modules.
 
 #![no_std]
 #![no_main]
@@ -11,17 +12,15 @@
 #![feature(panic_info_message)]
 
 
-// define modules
-mod entry;
-mod error;
+use nervos_dao_extended_ownership_script::entry;
 
 use ckb_std::{
     default_alloc,
 };
 
 ckb_std::entry!(program_entry);
-// Alloc 4K fast HEAP + 2M HEAP to receives PrefilledData
-default_alloc!(4 * 1024, 2048 * 1024, 64);
+// Alloc 4K fast HEAP + 128K HEAP, the secp256k1 PrefilledData is static
+default_alloc!(4 * 1024, 128 * 1024, 64);
 
 /// program entry
 fn program_entry() -> i8 {
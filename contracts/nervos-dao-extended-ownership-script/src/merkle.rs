@@ -0,0 +1,56 @@
+use crate::entry::new_blake2b;
+use crate::witness::MerkleProof;
+
+// domain separation between leaves and inner nodes, so that an inner node
+// can never be passed off as a leaf
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+pub fn leaf_hash(pubkey_hash: &[u8]) -> [u8; 32] {
+    let mut buf = [0u8; 32];
+    let mut hasher = new_blake2b();
+    hasher.update(&[LEAF_PREFIX]);
+    hasher.update(pubkey_hash);
+    hasher.finalize(&mut buf);
+    buf
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut buf = [0u8; 32];
+    let mut hasher = new_blake2b();
+    hasher.update(&[NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize(&mut buf);
+    buf
+}
+
+/// Fold the proof from the leaf up to the root, bit `i` of the leaf index
+/// tells whether the node is the right (1) or the left (0) child at level `i`.
+///
+/// An odd node out is paired with a zero hash. A node paired with itself
+/// would be the same child on either side, giving its leaf a second index,
+/// so such a sibling is refused.
+pub fn verify(root: &[u8; 32], proof: &MerkleProof) -> bool {
+    // a 32-level tree is more than any allowlist needs, and it keeps every
+    // index bit meaningful so one leaf has exactly one valid proof
+    if proof.siblings.len() > 32 {
+        return false;
+    }
+    if proof.siblings.len() < 32 && proof.index >> proof.siblings.len() != 0 {
+        return false;
+    }
+
+    let mut node = leaf_hash(&proof.leaf);
+    for (level, sibling) in proof.siblings.iter().enumerate() {
+        if *sibling == node {
+            return false;
+        }
+        node = if (proof.index >> level) & 1 == 0 {
+            node_hash(&node, sibling)
+        } else {
+            node_hash(sibling, &node)
+        };
+    }
+    node == *root
+}
@@ -0,0 +1,57 @@
+//! The signature of the puppet's own lock standing in for the lock's,
+//! compiled in with the `owner-lock` feature, see `load_shared_signature`
+//! in `entry.rs`.
+
+// Import CKB syscalls and structures
+use ckb_std::{
+    high_level::{load_cell_lock, load_cell_lock_hash, load_witness_args},
+    ckb_types::{bytes::Bytes, prelude::*},
+    ckb_constants::Source,
+};
+
+use ckb_lib_secp256k1::LibLocator;
+
+use crate::entry::Signature;
+use crate::error::Error;
+use crate::library;
+use crate::puppet::PUBKEY_HASH_LEN;
+use crate::sighash;
+use crate::witness::{is_sighash_all, LockWitness};
+
+/// The signature of the puppet input's secp256k1 lock.
+///
+/// When the puppet is locked by the very secp256k1 library we use, that
+/// lock verifies its signature in the same transaction, so its args are
+/// taken as the signer without recovering the signature a second time.
+pub(crate) fn puppet_lock_signature(puppet_id: usize, locator: &LibLocator) -> Result<Signature, Error> {
+    let puppet_lock = load_cell_lock(puppet_id, Source::Input)?;
+    let args: Bytes = puppet_lock.args().unpack();
+    let same_code = match library::script_code(locator) {
+        Some((code_hash, hash_type)) => {
+            puppet_lock.code_hash().raw_data()[..] == code_hash[..]
+                && puppet_lock.hash_type().as_slice()[0] == hash_type
+        },
+        None => false,
+    };
+    if same_code && args.len() == PUBKEY_HASH_LEN {
+        let mut pubkey_hash = [0u8; PUBKEY_HASH_LEN];
+        pubkey_hash.copy_from_slice(&args);
+        return Ok(Signature::Delegated(pubkey_hash));
+    }
+
+    let puppet_lock_hash = load_cell_lock_hash(puppet_id, Source::Input)?;
+    let (first, message) = sighash::sighash_all_message(&puppet_lock_hash)?;
+    let witness: Bytes = match load_witness_args(first, Source::Input) {
+        Ok(witness_args) => witness_args
+            .lock()
+            .to_opt()
+            .ok_or(Error::WitnessArgsEncoding)?
+            .unpack(),
+        Err(_err) => return Err(Error::LoadWitnessArgs),
+    };
+    // a sighash-all signature carries no auth kind
+    if !is_sighash_all(&witness) {
+        return Err(Error::WitnessArgsEncoding);
+    }
+    Ok(Signature::Recoverable(LockWitness::from_slice(&witness)?, message))
+}
@@ -0,0 +1,138 @@
+//! The puppet's type script.
+//!
+//! It is a type id, so there is only ever one puppet per position, and it
+//! applies the cooldown rules of `cooldown::check_update` whenever the
+//! puppet is rewritten or destroyed. The lock checks the same rules when the
+//! DAO cells move, this keeps them when the puppet moves on its own.
+//!
+//! A pending transfer keeps the puppet under the guard lock of the args,
+//! the ownership lock with the puppet type hash as its args, until it is
+//! settled or cancelled. Under the new owner's own lock the previous owner
+//! couldn't spend the puppet to take it back, the guard lets either of them
+//! in and leaves it to the cooldown rules who may do what.
+//!
+//! args: type id (32 bytes), `blake2b(first input | output index (u64 LE))`
+//! of the transaction creating the puppet, optionally followed by the code
+//! hash (32 bytes) and hash type (1 byte) of the guard lock. Without a guard
+//! the puppet can't open a pending transfer.
+
+// Import heap related library from `alloc`
+use alloc::vec::Vec;
+
+// Import CKB syscalls and structures
+use ckb_std::{
+    ckb_constants::Source,
+    ckb_types::{bytes::Bytes, packed::{Byte, Script}, prelude::*},
+    error::SysError,
+    high_level::{
+        load_cell_data, load_cell_lock_hash, load_cell_type_hash, load_header, load_input, load_input_since,
+        load_script, load_script_hash,
+    },
+};
+
+use crate::cooldown;
+use crate::entry::new_blake2b;
+use crate::error::Error;
+use crate::puppet::Puppet;
+use crate::since;
+
+const TYPE_ID_LEN: usize = 32;
+/// code hash and hash type of the guard lock
+const GUARD_LEN: usize = 33;
+
+pub fn main() -> Result<(), Error> {
+    let args: Bytes = load_script()?.args().unpack();
+    if args.len() != TYPE_ID_LEN && args.len() != TYPE_ID_LEN + GUARD_LEN {
+        return Err(Error::PuppetTypeIdMismatch);
+    }
+    let input = load_group_puppet(Source::GroupInput)?;
+    let output = match load_group_puppet(Source::GroupOutput)? {
+        Some(data) => Some(Puppet::from_slice(&data)?),
+        None => None,
+    };
+    match input {
+        Some(input) => {
+            let since = load_input_since(0, Source::GroupInput)?;
+            cooldown::check_update(since, &Puppet::from_slice(&input)?, output.as_ref(), || {
+                let epoch: u64 = load_header(0, Source::GroupInput)?.raw().epoch().unpack();
+                Ok(since::epoch_number(epoch))
+            })?;
+        },
+        // a new puppet, whatever it holds is up to its creator
+        None => check_type_id(&args)?,
+    }
+    check_guard(&args, output.as_ref())
+}
+
+/// a pending transfer leaves the puppet under the guard lock of the args
+fn check_guard(args: &[u8], output: Option<&Puppet>) -> Result<(), Error> {
+    let pending = output
+        .and_then(|puppet| puppet.cooldown.as_ref())
+        .map_or(false, |cooldown| cooldown.is_pending());
+    if !pending {
+        return Ok(());
+    }
+    if args.len() != TYPE_ID_LEN + GUARD_LEN {
+        return Err(Error::CooldownGuardMismatch);
+    }
+    let mut code_hash = [0u8; 32];
+    code_hash.copy_from_slice(&args[TYPE_ID_LEN..TYPE_ID_LEN + 32]);
+    let script_hash = load_script_hash()?;
+    let guard = Script::new_builder()
+        .code_hash(code_hash.pack())
+        .hash_type(Byte::new(args[TYPE_ID_LEN + 32]))
+        .args(script_hash[..].pack())
+        .build();
+    let mut guard_hash = [0u8; 32];
+    let mut hasher = new_blake2b();
+    hasher.update(guard.as_slice());
+    hasher.finalize(&mut guard_hash);
+    if load_cell_lock_hash(0, Source::GroupOutput)? != guard_hash {
+        return Err(Error::CooldownGuardMismatch);
+    }
+    Ok(())
+}
+
+/// data of the only cell of the group in `source`, if any
+fn load_group_puppet(source: Source) -> Result<Option<Vec<u8>>, Error> {
+    match load_cell_data(1, source) {
+        Ok(_) => return Err(Error::RequiredOnlyOnePuppet),
+        Err(SysError::IndexOutOfBound) => {},
+        Err(err) => return Err(err.into()),
+    }
+    match load_cell_data(0, source) {
+        Ok(data) => Ok(Some(data)),
+        Err(SysError::IndexOutOfBound) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// a puppet created by this transaction carries the type id of its first
+/// input and its own output index
+fn check_type_id(args: &[u8]) -> Result<(), Error> {
+    let script_hash = load_script_hash()?;
+    let mut output_index = None;
+    for i in 0.. {
+        match load_cell_type_hash(i, Source::Output) {
+            Ok(Some(hash)) if hash == script_hash => {
+                output_index = Some(i as u64);
+                break;
+            },
+            Ok(_) => {},
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    let output_index = output_index.ok_or(Error::PuppetTypeIdMismatch)?;
+
+    let first_input = load_input(0, Source::Input)?;
+    let mut type_id = [0u8; TYPE_ID_LEN];
+    let mut hasher = new_blake2b();
+    hasher.update(first_input.as_slice());
+    hasher.update(&output_index.to_le_bytes());
+    hasher.finalize(&mut type_id);
+    if args[..TYPE_ID_LEN] != type_id[..] {
+        return Err(Error::PuppetTypeIdMismatch);
+    }
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use ckb_std::ckb_constants::Source;
+
+use crate::action::{self, Action};
+use crate::chain::{Chain, PuppetCell};
+use crate::cooldown;
+use crate::entry::new_blake2b;
+use crate::error::Error;
+use crate::lease;
+use crate::merkle;
+use crate::puppet::{Puppet, SCOPE_PHASE1, SCOPE_PHASE2};
+use crate::witness::Auth;
+
+/// Whether `signer` proves to be the owner with `auth`. `sign_pubkey` is the
+/// recovered pubkey, which only the commitment auth needs.
+pub fn is_owner(puppet: &Puppet, signer: &[u8], auth: &Auth, sign_pubkey: Option<&[u8; 33]>) -> Result<bool, Error> {
+    match auth {
+        Auth::PubkeyHash | Auth::Eth | Auth::Rsa(_) => Ok(signer == &puppet.owner[..]),
+        // any member of the allowlist is as good as the owner
+        Auth::Merkle(ref proof) => {
+            let root = puppet.merkle_root.as_ref().ok_or(Error::MerkleProofInvalid)?;
+            if signer != &proof.leaf[..] || !merkle::verify(root, proof) {
+                return Err(Error::MerkleProofInvalid);
+            }
+            Ok(true)
+        },
+        // the commitment hides the pubkey, opening it proves ownership
+        Auth::Commitment(ref salt) => {
+            let commitment = puppet.owner_commitment.as_ref().ok_or(Error::CommitmentMismatch)?;
+            let sign_pubkey = sign_pubkey.ok_or(Error::CommitmentMismatch)?;
+            let opened = {
+                let mut buf = [0u8; 32];
+                let mut hasher = new_blake2b();
+                hasher.update(sign_pubkey);
+                hasher.update(salt);
+                hasher.finalize(&mut buf);
+                buf
+            };
+            if opened != *commitment {
+                return Err(Error::CommitmentMismatch);
+            }
+            Ok(true)
+        },
+    }
+}
+
+/// Decide whether `signer` may do what the transaction does with the
+/// position, once its signature has been checked.
+pub fn authorize<C: Chain>(
+    chain: &C,
+    puppet_type_hash: &[u8],
+    puppet_cell: PuppetCell,
+    puppet: &Puppet,
+    signer: &[u8],
+    is_owner: bool,
+) -> Result<(), Error> {
+    if puppet_cell.source != Source::Input {
+        check_cell_dep_proof(puppet, signer)?;
+    }
+    let action = action::classify(chain, puppet_type_hash, puppet_cell)?;
+
+    if let Some(ref cooldown) = puppet.cooldown {
+        if cooldown.is_pending() {
+            if signer == &cooldown.previous_owner[..] && !is_owner {
+                return cooldown::check_cancel(chain, puppet_type_hash, cooldown, action);
+            }
+            cooldown::check_elapsed(chain, puppet_cell, cooldown)?;
+        }
+    }
+
+    if let Some(ref lease) = puppet.lease {
+        if signer == &lease.lessee[..] {
+            lease::check_lessee(chain, puppet_cell, lease)?;
+            check_scope(lease.scope, action)?;
+            return check_payout(chain, puppet_type_hash, puppet, action);
+        }
+        // the lessor and its operators wait for the lease to expire
+        if is_owner || puppet.operator(signer).is_some() {
+            lease::check_expired(chain, puppet_cell, lease)?;
+        }
+    }
+
+    if is_owner {
+        if action == Action::Transfer {
+            cooldown::check_transfer(chain, puppet_type_hash, puppet_cell, puppet)?;
+        }
+        return Ok(());
+    }
+
+    // not the owner, maybe an operator acting within its scope
+    let operator = puppet
+            .operator(signer)
+            .ok_or(Error::WrongPublicKey)?;
+    check_scope(operator.scope, action)?;
+    check_payout(chain, puppet_type_hash, puppet, action)
+}
+
+/// A cell dep puppet proves no epoch, see `PuppetCell::since`: the lessee
+/// can't show its lease is running, nor anyone that a cooldown has elapsed.
+/// Refuse them outright rather than leave it to the since checks.
+fn check_cell_dep_proof(puppet: &Puppet, signer: &[u8]) -> Result<(), Error> {
+    let cooldown_pending = puppet.cooldown.as_ref().map_or(false, |cooldown| cooldown.is_pending());
+    let lessee = puppet.lease.as_ref().map_or(false, |lease| signer == &lease.lessee[..]);
+    if cooldown_pending || lessee {
+        return Err(Error::CellDepProofDenied);
+    }
+    Ok(())
+}
+
+/// whoever isn't the owner can't send the withdrawn capacity elsewhere
+fn check_payout<C: Chain>(chain: &C, puppet_type_hash: &[u8], puppet: &Puppet, action: Action) -> Result<(), Error> {
+    match action {
+        Action::Phase2 => action::check_payout(chain, puppet_type_hash, puppet),
+        _ => Ok(()),
+    }
+}
+
+fn check_scope(scope: u8, action: Action) -> Result<(), Error> {
+    match action {
+        Action::Phase1 if scope & SCOPE_PHASE1 != 0 => Ok(()),
+        Action::Phase1 => Err(Error::OperatorPhase1Denied),
+        Action::Phase2 if scope & SCOPE_PHASE2 != 0 => Ok(()),
+        Action::Phase2 => Err(Error::OperatorPhase2Denied),
+        Action::Transfer => Err(Error::OperatorTransferDenied),
+        Action::Other => Err(Error::OperatorActionDenied),
+    }
+}
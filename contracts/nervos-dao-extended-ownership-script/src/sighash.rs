@@ -0,0 +1,104 @@
+// Import heap related library from `alloc`
+use alloc::{vec, vec::Vec};
+
+// Import CKB syscalls and structures
+use ckb_std::{
+    syscalls,
+    high_level::{load_cell_lock_hash, load_tx_hash},
+    ckb_types::{packed::WitnessArgs, prelude::*},
+    ckb_constants::Source,
+    error::SysError,
+};
+
+use blake2b_ref::Blake2b;
+
+use crate::entry::new_blake2b;
+use crate::error::Error;
+use crate::witness::SIGNATURE_LEN;
+
+/// load a whole witness, `load_witness_args` only gives the parsed fields
+pub fn load_witness(index: usize, source: Source) -> Result<Vec<u8>, SysError> {
+    match syscalls::load_witness(&mut [], 0, index, source) {
+        Ok(_) => Ok(Vec::new()),
+        Err(SysError::LengthNotEnough(len)) => {
+            let mut witness = vec![0u8; len];
+            syscalls::load_witness(&mut witness, 0, index, source)?;
+            Ok(witness)
+        },
+        Err(err) => Err(err),
+    }
+}
+
+/// The message secp256k1_blake2b_sighash_all signs for the lock group with
+/// `lock_hash`, together with the input index holding the signature.
+///
+/// blake2b(tx hash | the group's witnesses, the first one with its lock
+/// zeroed | witnesses beyond the inputs), each witness prefixed by its
+/// length as u64 LE.
+pub fn sighash_all_message(lock_hash: &[u8; 32]) -> Result<(usize, [u8; 32]), Error> {
+    let tx_hash = load_tx_hash()?;
+    let mut hasher = new_blake2b();
+    hasher.update(&tx_hash);
+
+    let mut first = None;
+    let mut inputs_len = 0;
+    for i in 0.. {
+        match load_cell_lock_hash(i, Source::Input) {
+            Ok(hash) => {
+                inputs_len += 1;
+                if hash != *lock_hash {
+                    continue;
+                }
+                let mut witness = match load_witness(i, Source::Input) {
+                    Ok(witness) => witness,
+                    Err(SysError::IndexOutOfBound) if first.is_some() => continue,
+                    Err(SysError::IndexOutOfBound) => return Err(Error::WitnessArgsEncoding),
+                    Err(err) => return Err(err.into()),
+                };
+                if first.is_none() {
+                    zero_lock(&mut witness)?;
+                    first = Some(i);
+                }
+                hash_witness(&mut hasher, &witness);
+            },
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(err.into()),
+        };
+    }
+
+    for i in inputs_len.. {
+        match load_witness(i, Source::Input) {
+            Ok(witness) => hash_witness(&mut hasher, &witness),
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(err.into()),
+        };
+    }
+
+    let mut message = [0u8; 32];
+    hasher.finalize(&mut message);
+    Ok((first.ok_or(Error::WitnessArgsEncoding)?, message))
+}
+
+fn hash_witness(hasher: &mut Blake2b, witness: &[u8]) {
+    hasher.update(&(witness.len() as u64).to_le_bytes());
+    hasher.update(witness);
+}
+
+fn zero_lock(witness: &mut [u8]) -> Result<(), Error> {
+    let witness_args = WitnessArgs::from_slice(witness).map_err(|_| Error::WitnessArgsEncoding)?;
+    match witness_args.lock().to_opt() {
+        Some(lock) if lock.raw_data().len() == SIGNATURE_LEN => {},
+        _ => return Err(Error::WitnessArgsEncoding),
+    }
+    // lock is the first field: total size | field offsets | lock length | lock
+    let mut offset = [0u8; 4];
+    offset.copy_from_slice(witness.get(4..8).ok_or(Error::WitnessArgsEncoding)?);
+    let start = u32::from_le_bytes(offset) as usize + 4;
+    let signature = witness
+        .get_mut(start..start + SIGNATURE_LEN)
+        .ok_or(Error::WitnessArgsEncoding)?;
+    for byte in signature.iter_mut() {
+        *byte = 0;
+    }
+    Ok(())
+}
@@ -0,0 +1,29 @@
+/// `since` field helpers, see RFC 0017 transaction valid since
+const SINCE_RELATIVE_FLAG: u64 = 0x8000_0000_0000_0000;
+const SINCE_METRIC_MASK: u64 = 0x6000_0000_0000_0000;
+const SINCE_METRIC_EPOCH: u64 = 0x2000_0000_0000_0000;
+const EPOCH_NUMBER_MASK: u64 = 0x00ff_ffff;
+
+/// epoch number of a header's epoch, or of an epoch since's value, without
+/// the fraction
+pub fn epoch_number(epoch: u64) -> u64 {
+    epoch & EPOCH_NUMBER_MASK
+}
+
+/// epoch number of an absolute epoch since, the transaction can only be
+/// committed from this epoch on
+pub fn absolute_epoch(since: u64) -> Option<u64> {
+    if since & SINCE_RELATIVE_FLAG != 0 || since & SINCE_METRIC_MASK != SINCE_METRIC_EPOCH {
+        return None;
+    }
+    Some(epoch_number(since))
+}
+
+/// whole epochs of a relative epoch since, the input cell must be at least
+/// this old when the transaction is committed
+pub fn relative_epochs(since: u64) -> Option<u64> {
+    if since & SINCE_RELATIVE_FLAG == 0 || since & SINCE_METRIC_MASK != SINCE_METRIC_EPOCH {
+        return None;
+    }
+    Some(since & EPOCH_NUMBER_MASK)
+}
@@ -0,0 +1,114 @@
+//! Structured debug output, compiled in with the `trace` feature.
+//!
+//! Each event is a single debug syscall, `trace:<event> key=value ...`,
+//! with byte strings in hex and errors as their code:
+//!
+//! - `witness kind= sighash=`, the group's own lock witness
+//! - `scan source= puppets=`, the puppet cells found in `source`
+//! - `puppet index= source=`, the puppet cell taken
+//! - `signer kind= hash= delegated=`, the auth kind and the signer's hash
+//! - `decision allow= code=`, the outcome, `code` is the exit code
+//!
+//! `tests/src/helper.rs` decodes them. Without the feature `trace!` expands
+//! to nothing, its arguments aren't even evaluated, so the default binary
+//! carries no trace code.
+
+#[cfg(feature = "trace")]
+macro_rules! trace {
+    ($event:literal $(, $key:ident = $value:expr)* $(,)?) => {{
+        let mut message = alloc::string::String::from(concat!("trace:", $event));
+        $(
+            message.push_str(concat!(" ", stringify!($key), "="));
+            $crate::trace::Field::write(&$value, &mut message);
+        )*
+        ckb_std::syscalls::debug(message);
+    }};
+}
+
+#[cfg(not(feature = "trace"))]
+macro_rules! trace {
+    ($($tokens:tt)*) => {};
+}
+
+#[cfg(feature = "trace")]
+pub use field::Field;
+
+#[cfg(feature = "trace")]
+mod field {
+    use alloc::string::String;
+    use core::fmt::Write;
+
+    use ckb_std::ckb_constants::Source;
+
+    use crate::error::Error;
+
+    /// A value of a trace event.
+    pub trait Field {
+        fn write(&self, out: &mut String);
+    }
+
+    macro_rules! display_field {
+        ($($ty:ty),*) => {
+            $(
+                impl Field for $ty {
+                    fn write(&self, out: &mut String) {
+                        let _ = write!(out, "{}", self);
+                    }
+                }
+            )*
+        };
+    }
+
+    display_field!(bool, u8, u32, u64, usize, i8, i32, str);
+
+    impl Field for [u8] {
+        fn write(&self, out: &mut String) {
+            for byte in self {
+                let _ = write!(out, "{:02x}", byte);
+            }
+        }
+    }
+
+    macro_rules! bytes_field {
+        ($($len:expr),*) => {
+            $(
+                impl Field for [u8; $len] {
+                    fn write(&self, out: &mut String) {
+                        self[..].write(out);
+                    }
+                }
+            )*
+        };
+    }
+
+    bytes_field!(20, 32, 33);
+
+    impl<T: Field + ?Sized> Field for &T {
+        fn write(&self, out: &mut String) {
+            (**self).write(out);
+        }
+    }
+
+    impl Field for Source {
+        fn write(&self, out: &mut String) {
+            out.push_str(match self {
+                Source::Input => "input",
+                Source::Output => "output",
+                Source::CellDep => "cell_dep",
+                Source::HeaderDep => "header_dep",
+                Source::GroupInput => "group_input",
+                Source::GroupOutput => "group_output",
+            });
+        }
+    }
+
+    /// 0 or the error code
+    impl Field for Result<(), Error> {
+        fn write(&self, out: &mut String) {
+            match self {
+                Ok(_) => out.push('0'),
+                Err(err) => (*err as i8).write(out),
+            }
+        }
+    }
+}
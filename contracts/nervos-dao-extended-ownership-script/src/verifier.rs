@@ -0,0 +1,167 @@
+//! The signature verifiers, one per library.
+//!
+//! Each is compiled in with its cargo feature, `secp256k1` for the pubkey
+//! hash, merkle and commitment auths, `eth` and `rsa`. A build without the
+//! feature refuses the auth kinds it verifies with `UnsupportedAuth`.
+
+#[cfg(any(feature = "secp256k1", feature = "eth", feature = "rsa"))]
+use ckb_std::dynamic_loading::CKBDLContext;
+#[cfg(any(feature = "eth", feature = "rsa"))]
+use ckb_dynamic_verifier::DynamicVerifier;
+#[cfg(feature = "eth")]
+use ckb_lib_eth::{EthVerifier, LibEth, CODE_HASH_ETH};
+#[cfg(feature = "rsa")]
+use ckb_lib_rsa::{LibRsa, CODE_HASH_RSA};
+use ckb_lib_secp256k1::LibLocator;
+#[cfg(feature = "secp256k1")]
+use ckb_lib_secp256k1::{LibSecp256k1, LibVerifier, Verifier};
+#[cfg(any(feature = "secp256k1", feature = "eth"))]
+use ckb_lib_secp256k1::StaticPrefilledData;
+
+#[cfg(feature = "secp256k1")]
+use crate::entry::new_blake2b;
+use crate::error::Error;
+#[cfg(any(feature = "eth", feature = "rsa"))]
+use crate::library;
+use crate::puppet::PUBKEY_HASH_LEN;
+#[cfg(feature = "secp256k1")]
+use crate::witness::COMPACT_SIGNATURE_LEN;
+use crate::witness::{Auth, LockWitness, SIGNATURE_LEN};
+
+/// The secp256k1 library's 1 MB precomputed table lives here rather than
+/// on the heap. Only one signature is verified per run, a second one would
+/// find it taken.
+#[cfg(any(feature = "secp256k1", feature = "eth"))]
+static PREFILLED_DATA: StaticPrefilledData = StaticPrefilledData::new();
+
+/// The signer's identity, checked by the verifier of the auth kind.
+///
+/// Ethereum and RSA signatures go to their own libraries, found by their
+/// code hash or by the locator in the args, every other auth kind is a
+/// secp256k1 signature, whose pubkey is returned as well for the commitment
+/// auth.
+pub fn verify_signer(
+    locator: &LibLocator,
+    lock_witness: &LockWitness,
+    message: &[u8; 32],
+) -> Result<([u8; PUBKEY_HASH_LEN], Option<[u8; 33]>), Error> {
+    match lock_witness.auth {
+        Auth::Eth => Ok((eth_signer(locator, &lock_witness.signature, message)?, None)),
+        Auth::Rsa(ref signature) => Ok((rsa_signer(locator, signature, message)?, None)),
+        _ => secp256k1_signer(locator, lock_witness, message),
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+fn secp256k1_signer(
+    locator: &LibLocator,
+    lock_witness: &LockWitness,
+    message: &[u8; 32],
+) -> Result<([u8; PUBKEY_HASH_LEN], Option<[u8; 33]>), Error> {
+    let mut context = unsafe{ CKBDLContext::<[u8; 128 * 1024]>::new()};
+    let sign_pubkey = signer_pubkey(&mut context, locator, lock_witness, message)?;
+    let sign_pubkey_hash = {
+        let mut buf = [0u8; 32];
+        let mut hasher = new_blake2b();
+        hasher.update(&sign_pubkey);
+        hasher.finalize(&mut buf);
+        buf
+    };
+    let mut signer = [0u8; PUBKEY_HASH_LEN];
+    signer.copy_from_slice(&sign_pubkey_hash[..PUBKEY_HASH_LEN]);
+    Ok((signer, Some(sign_pubkey)))
+}
+
+#[cfg(not(feature = "secp256k1"))]
+fn secp256k1_signer(
+    _locator: &LibLocator,
+    _lock_witness: &LockWitness,
+    _message: &[u8; 32],
+) -> Result<([u8; PUBKEY_HASH_LEN], Option<[u8; 33]>), Error> {
+    Err(Error::UnsupportedAuth)
+}
+
+/// The signer of the group's sighash-all, a bare signature in the lock
+/// witness. The library verifies it with its own precomputed table, so the
+/// 1 MB table isn't copied.
+#[cfg(feature = "secp256k1")]
+pub fn sighash_all_signer(locator: &LibLocator) -> Result<[u8; PUBKEY_HASH_LEN], Error> {
+    let mut context = unsafe{ CKBDLContext::<[u8; 128 * 1024]>::new()};
+    let lib = LibSecp256k1::try_load_by(&mut context, locator)?;
+    lib.sighash_all_signer().map_err(Error::from)
+}
+
+#[cfg(not(feature = "secp256k1"))]
+pub fn sighash_all_signer(_locator: &LibLocator) -> Result<[u8; PUBKEY_HASH_LEN], Error> {
+    Err(Error::UnsupportedAuth)
+}
+
+/// The pubkey which signed `message`, with the secp256k1 dual library.
+///
+/// A recoverable signature gives its pubkey, a compact one is verified
+/// against the pubkey in the witness. Either way the pubkey is compressed,
+/// so both hash to the same blake160.
+#[cfg(feature = "secp256k1")]
+fn signer_pubkey(
+    context: &mut CKBDLContext<[u8; 128 * 1024]>,
+    locator: &LibLocator,
+    lock_witness: &LockWitness,
+    message: &[u8; 32],
+) -> Result<[u8; 33], Error> {
+    // Validate the signatures of puppet cell
+    let lib = LibSecp256k1::try_load_by(context, locator)?;
+
+    // recover pubkey_hash
+    let buffer = PREFILLED_DATA.take()?;
+    let verifier = LibVerifier::with_buffer(lib, buffer)?;
+
+    let signature = &lock_witness.signature;
+    let result = match lock_witness.pubkey {
+        Some(ref pubkey) => verifier.verify(pubkey, &signature[..COMPACT_SIGNATURE_LEN], message),
+        None => verifier.recover_pubkey(signature, message),
+    };
+    result.map_err(Error::from)
+}
+
+#[cfg(feature = "eth")]
+fn eth_signer(
+    locator: &LibLocator,
+    signature: &[u8; SIGNATURE_LEN],
+    message: &[u8; 32],
+) -> Result<[u8; PUBKEY_HASH_LEN], Error> {
+    let mut context = unsafe{ CKBDLContext::<[u8; 128 * 1024]>::new()};
+    let lib = LibEth::try_load_by(&mut context, &library::with_code_hash(locator, CODE_HASH_ETH))?;
+    let buffer = PREFILLED_DATA.take()?;
+    let verifier = EthVerifier::with_buffer(lib, buffer)?;
+    dynamic_signer(&verifier, signature, message)
+}
+
+#[cfg(not(feature = "eth"))]
+fn eth_signer(
+    _locator: &LibLocator,
+    _signature: &[u8; SIGNATURE_LEN],
+    _message: &[u8; 32],
+) -> Result<[u8; PUBKEY_HASH_LEN], Error> {
+    Err(Error::UnsupportedAuth)
+}
+
+#[cfg(feature = "rsa")]
+fn rsa_signer(locator: &LibLocator, signature: &[u8], message: &[u8; 32]) -> Result<[u8; PUBKEY_HASH_LEN], Error> {
+    let mut context = unsafe{ CKBDLContext::<[u8; 128 * 1024]>::new()};
+    let lib = LibRsa::try_load_by(&mut context, &library::with_code_hash(locator, CODE_HASH_RSA))?;
+    dynamic_signer(&lib, signature, message)
+}
+
+#[cfg(not(feature = "rsa"))]
+fn rsa_signer(_locator: &LibLocator, _signature: &[u8], _message: &[u8; 32]) -> Result<[u8; PUBKEY_HASH_LEN], Error> {
+    Err(Error::UnsupportedAuth)
+}
+
+#[cfg(any(feature = "eth", feature = "rsa"))]
+fn dynamic_signer<V>(verifier: &V, signature: &[u8], message: &[u8; 32]) -> Result<[u8; PUBKEY_HASH_LEN], Error>
+where
+    V: DynamicVerifier,
+    Error: From<V::Error>,
+{
+    verifier.verify_signer(signature, message).map_err(Error::from)
+}
@@ -0,0 +1,28 @@
+//! The extended ownership lock with the secp256k1 verifier only.
+//!
+//! The pubkey hash, merkle and commitment auths work as in the full lock,
+//! Ethereum and RSA signatures and the puppet's own lock signing for the DAO
+//! cell are refused with `UnsupportedAuth`. See the features of
+//! `nervos-dao-extended-ownership-script`.
+
+#![no_std]
+#![no_main]
+#![feature(lang_items)]
+#![feature(alloc_error_handler)]
+#![feature(panic_info_message)]
+
+use nervos_dao_extended_ownership_script::entry;
+
+use ckb_std::default_alloc;
+
+ckb_std::entry!(program_entry);
+// Alloc 4K fast HEAP + 128K HEAP, the secp256k1 PrefilledData is static
+default_alloc!(4 * 1024, 128 * 1024, 64);
+
+/// program entry
+fn program_entry() -> i8 {
+    match entry::main() {
+        Ok(_) => 0,
+        Err(err) => err as i8,
+    }
+}
@@ -0,0 +1,27 @@
+//! The extended ownership lock with the `trace` feature.
+//!
+//! The same lock as `nervos-dao-extended-ownership-script`, which also
+//! reports what it checks through debug syscalls, see `trace.rs` there.
+//! For tests and debugging only, deploy the lock built without it.
+
+#![no_std]
+#![no_main]
+#![feature(lang_items)]
+#![feature(alloc_error_handler)]
+#![feature(panic_info_message)]
+
+use nervos_dao_extended_ownership_script::entry;
+
+use ckb_std::default_alloc;
+
+ckb_std::entry!(program_entry);
+// Alloc 4K fast HEAP + 128K HEAP, the secp256k1 PrefilledData is static
+default_alloc!(4 * 1024, 128 * 1024, 64);
+
+/// program entry
+fn program_entry() -> i8 {
+    match entry::main() {
+        Ok(_) => 0,
+        Err(err) => err as i8,
+    }
+}
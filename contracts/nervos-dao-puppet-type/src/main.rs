@@ -0,0 +1,24 @@
+//! The puppet's type script, a type id which keeps the cooldown record of
+//! the puppet when it moves without the DAO cells. See `puppet_type.rs` in
+//! `nervos-dao-extended-ownership-script`.
+
+#![no_std]
+#![no_main]
+#![feature(lang_items)]
+#![feature(alloc_error_handler)]
+#![feature(panic_info_message)]
+
+use nervos_dao_extended_ownership_script::puppet_type;
+
+use ckb_std::default_alloc;
+
+ckb_std::entry!(program_entry);
+default_alloc!(4 * 1024, 64 * 1024, 64);
+
+/// program entry
+fn program_entry() -> i8 {
+    match puppet_type::main() {
+        Ok(_) => 0,
+        Err(err) => err as i8,
+    }
+}
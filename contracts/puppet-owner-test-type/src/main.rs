@@ -0,0 +1,54 @@
+//! A type script for the tests only: it passes when the owner of the puppet
+//! named in its args authorizes the transaction, asking the ownership lock
+//! built as a library.
+//!
+//! args: puppet type hash (32 bytes) | library data hash (32 bytes)
+
+#![no_std]
+#![no_main]
+#![feature(lang_items)]
+#![feature(alloc_error_handler)]
+#![feature(panic_info_message)]
+
+use ckb_std::{
+    ckb_types::{bytes::Bytes, prelude::*},
+    default_alloc,
+    dynamic_loading::CKBDLContext,
+    high_level::load_script,
+};
+use ckb_lib_dao_ownership::{LibDaoOwnership, LibError, LibLocator};
+
+ckb_std::entry!(program_entry);
+default_alloc!(4 * 1024, 64 * 1024, 64);
+
+// the library's own error codes are below 100
+const ERROR_ARGS: i8 = 100;
+const ERROR_LOAD_LIBRARY: i8 = 101;
+
+/// program entry
+fn program_entry() -> i8 {
+    let script = match load_script() {
+        Ok(script) => script,
+        Err(_) => return ERROR_ARGS,
+    };
+    let args: Bytes = script.args().unpack();
+    if args.len() != 64 {
+        return ERROR_ARGS;
+    }
+    let mut puppet_type_hash = [0u8; 32];
+    puppet_type_hash.copy_from_slice(&args[..32]);
+    let mut data_hash = [0u8; 32];
+    data_hash.copy_from_slice(&args[32..]);
+
+    // the library carries the secp256k1 table, 1 MB
+    let mut context = unsafe { CKBDLContext::<[u8; 1280 * 1024]>::new() };
+    let lib = match LibDaoOwnership::try_load_by(&mut context, &LibLocator::DataHash(data_hash)) {
+        Ok(lib) => lib,
+        Err(_) => return ERROR_LOAD_LIBRARY,
+    };
+    match lib.verify_puppet_owner(&puppet_type_hash) {
+        Ok(_) => 0,
+        Err(LibError::Denied(code)) => code as i8,
+        Err(_) => ERROR_LOAD_LIBRARY,
+    }
+}
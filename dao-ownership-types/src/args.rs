@@ -0,0 +1,196 @@
+// Import heap related library from `alloc`
+use alloc::vec::Vec;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::witness::AUTH_KIND_MASK;
+
+/// the lock args start with the puppet type hash (32 bytes), the legacy
+/// form stops there or adds a library locator
+pub const PUPPET_TYPE_HASH_LEN: usize = 32;
+
+/// locator: the cell dep with this type hash (32 bytes), e.g. a type id
+pub const LOCATOR_TYPE_HASH: u8 = 0x01;
+/// locator: the cell dep at this index (1 byte), which must carry this type
+/// hash (32 bytes), so it is found without scanning the cell deps
+pub const LOCATOR_CELL_DEP: u8 = 0x02;
+
+/// The version/flags byte following the puppet type hash: the version in
+/// the high nibble, flags in the low one. Legacy locators have version 0.
+pub const ARGS_VERSION_1: u8 = 0x10;
+const ARGS_VERSION_MASK: u8 = 0xf0;
+/// a library locator follows, as in the legacy form
+pub const ARGS_FLAG_LIBRARY: u8 = 0x01;
+/// the puppet may be a cell dep instead of an input, no extension bytes
+pub const ARGS_FLAG_CELL_DEP_PROOF: u8 = 0x02;
+/// only this auth kind (1 byte) may sign
+pub const ARGS_FLAG_AUTH_KIND: u8 = 0x04;
+/// deposits stay locked for at least this many epochs (u64 LE)
+pub const ARGS_FLAG_LOCK_PERIOD: u8 = 0x08;
+
+/// Where the lock finds the library verifying the signature, the secp256k1
+/// library unless it is an Ethereum or RSA signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LibraryLocator {
+    /// the cell dep with the bundled library's data hash
+    Default,
+    TypeHash([u8; 32]),
+    CellDep { type_hash: [u8; 32], index: u8 },
+}
+
+/// The lock args.
+///
+/// Legacy: `puppet type hash (32 bytes) [| locator kind (1 byte) | locator body]`.
+///
+/// Version 1: `puppet type hash (32 bytes) | 0x1 (4 bits) | flags (4 bits) |
+/// extensions`, one extension per flag in the order of the flag bits:
+/// locator kind and body, nothing, auth kind (1 byte), lock period in
+/// epochs (u64 LE). Nothing may follow the last one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LockArgs {
+    pub puppet_type_hash: [u8; PUPPET_TYPE_HASH_LEN],
+    pub library: LibraryLocator,
+    pub cell_dep_proof: bool,
+    pub auth_kind: Option<u8>,
+    pub lock_period: Option<u64>,
+}
+
+impl LockArgs {
+    pub fn new(puppet_type_hash: [u8; PUPPET_TYPE_HASH_LEN]) -> Self {
+        LockArgs {
+            puppet_type_hash,
+            library: LibraryLocator::Default,
+            cell_dep_proof: false,
+            auth_kind: None,
+            lock_period: None,
+        }
+    }
+
+    pub fn from_slice(args: &[u8]) -> Result<Self, Error> {
+        if args.len() < PUPPET_TYPE_HASH_LEN {
+            return Err(Error::InvalidArgs);
+        }
+        let mut puppet_type_hash = [0u8; PUPPET_TYPE_HASH_LEN];
+        puppet_type_hash.copy_from_slice(&args[..PUPPET_TYPE_HASH_LEN]);
+        let mut lock_args = LockArgs::new(puppet_type_hash);
+
+        let rest = &args[PUPPET_TYPE_HASH_LEN..];
+        let byte = match rest.first() {
+            Some(byte) => *byte,
+            None => return Ok(lock_args),
+        };
+        if byte & ARGS_VERSION_MASK == 0 {
+            lock_args.library = parse_locator(rest)?;
+            return Ok(lock_args);
+        }
+        if byte & ARGS_VERSION_MASK != ARGS_VERSION_1 {
+            return Err(Error::InvalidArgs);
+        }
+
+        let mut rest = &rest[1..];
+        if byte & ARGS_FLAG_LIBRARY != 0 {
+            let len = match rest.first() {
+                Some(&LOCATOR_TYPE_HASH) => 1 + 32,
+                Some(&LOCATOR_CELL_DEP) => 1 + 33,
+                _ => return Err(Error::InvalidArgs),
+            };
+            if rest.len() < len {
+                return Err(Error::InvalidArgs);
+            }
+            lock_args.library = parse_locator(&rest[..len])?;
+            rest = &rest[len..];
+        }
+        lock_args.cell_dep_proof = byte & ARGS_FLAG_CELL_DEP_PROOF != 0;
+        if byte & ARGS_FLAG_AUTH_KIND != 0 {
+            match rest.split_first() {
+                Some((&auth_kind, tail)) if auth_kind & !AUTH_KIND_MASK == 0 => {
+                    lock_args.auth_kind = Some(auth_kind);
+                    rest = tail;
+                },
+                _ => return Err(Error::InvalidArgs),
+            }
+        }
+        if byte & ARGS_FLAG_LOCK_PERIOD != 0 {
+            if rest.len() < 8 {
+                return Err(Error::InvalidArgs);
+            }
+            let mut epochs = [0u8; 8];
+            epochs.copy_from_slice(&rest[..8]);
+            lock_args.lock_period = Some(u64::from_le_bytes(epochs));
+            rest = &rest[8..];
+        }
+        if !rest.is_empty() {
+            return Err(Error::InvalidArgs);
+        }
+
+        Ok(lock_args)
+    }
+
+    /// The legacy form when nothing but the locator is set, version 1
+    /// otherwise.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut args = self.puppet_type_hash.to_vec();
+        let mut flags = 0;
+        if self.cell_dep_proof {
+            flags |= ARGS_FLAG_CELL_DEP_PROOF;
+        }
+        if self.auth_kind.is_some() {
+            flags |= ARGS_FLAG_AUTH_KIND;
+        }
+        if self.lock_period.is_some() {
+            flags |= ARGS_FLAG_LOCK_PERIOD;
+        }
+        if flags == 0 {
+            push_locator(&mut args, &self.library);
+            return args;
+        }
+
+        if self.library != LibraryLocator::Default {
+            flags |= ARGS_FLAG_LIBRARY;
+        }
+        args.push(ARGS_VERSION_1 | flags);
+        push_locator(&mut args, &self.library);
+        if let Some(auth_kind) = self.auth_kind {
+            args.push(auth_kind);
+        }
+        if let Some(epochs) = self.lock_period {
+            args.extend_from_slice(&epochs.to_le_bytes());
+        }
+        args
+    }
+}
+
+/// `locator` is exactly a locator kind and its body
+fn parse_locator(locator: &[u8]) -> Result<LibraryLocator, Error> {
+    let mut type_hash = [0u8; 32];
+    match locator.split_first() {
+        Some((&LOCATOR_TYPE_HASH, body)) if body.len() == 32 => {
+            type_hash.copy_from_slice(body);
+            Ok(LibraryLocator::TypeHash(type_hash))
+        },
+        Some((&LOCATOR_CELL_DEP, body)) if body.len() == 33 => {
+            type_hash.copy_from_slice(&body[..32]);
+            Ok(LibraryLocator::CellDep { type_hash, index: body[32] })
+        },
+        _ => Err(Error::InvalidArgs),
+    }
+}
+
+fn push_locator(args: &mut Vec<u8>, library: &LibraryLocator) {
+    match *library {
+        LibraryLocator::Default => {},
+        LibraryLocator::TypeHash(ref type_hash) => {
+            args.push(LOCATOR_TYPE_HASH);
+            args.extend_from_slice(type_hash);
+        },
+        LibraryLocator::CellDep { ref type_hash, index } => {
+            args.push(LOCATOR_CELL_DEP);
+            args.extend_from_slice(type_hash);
+            args.push(index);
+        },
+    }
+}
@@ -0,0 +1,48 @@
+use core::fmt;
+
+/// Why args, puppet data or a lock witness don't parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// the args are shorter than the puppet type hash, or the library
+    /// locator after it is unknown
+    InvalidArgs,
+    /// the puppet data is shorter than the owner pubkey hash
+    WrongPubkeyHashLength,
+    /// a puppet extension record is truncated, repeated or unknown
+    InvalidPuppetData,
+    /// the puppet index or the otx ranges of the lock witness are truncated
+    WitnessEncoding,
+    /// the sighash flags are combined in a way the lock doesn't support
+    UnknownSighashMode,
+    UnknownAuthKind,
+    /// the lock witness is shorter than a signature
+    WitnessTooShort,
+    /// the pubkey for a compact signature is malformed, or the signature
+    /// isn't padded with a zero byte
+    InvalidWitnessPubkey,
+    /// the auth payload or proof doesn't fit the auth kind
+    InvalidAuthPayload,
+    /// the lock witness isn't a valid molecule `LockWitness`
+    WitnessMolecule,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            Error::InvalidArgs => "invalid lock args",
+            Error::WrongPubkeyHashLength => "puppet data shorter than a pubkey hash",
+            Error::InvalidPuppetData => "invalid puppet extension record",
+            Error::WitnessEncoding => "invalid lock witness",
+            Error::UnknownSighashMode => "unknown sighash mode",
+            Error::UnknownAuthKind => "unknown auth kind",
+            Error::WitnessTooShort => "lock witness shorter than a signature",
+            Error::InvalidWitnessPubkey => "invalid pubkey in the lock witness",
+            Error::InvalidAuthPayload => "auth payload doesn't fit the auth kind",
+            Error::WitnessMolecule => "invalid molecule lock witness",
+        };
+        f.write_str(reason)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
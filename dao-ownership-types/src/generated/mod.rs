@@ -0,0 +1,7 @@
+//! Bindings generated by `moleculec` from the schemas in `schemas/`,
+//! regenerate them rather than editing.
+
+#![allow(clippy::all)]
+#![allow(unused_imports)]
+
+pub mod lock_witness;
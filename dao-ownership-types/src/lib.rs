@@ -0,0 +1,25 @@
+//! The lock args, puppet records and lock witnesses of the extended
+//! ownership lock, parsed and serialized in one place.
+//!
+//! The contract, the tests and off-chain tooling all build on this crate so
+//! their layouts can't drift apart. It is `no_std`, the `std` feature adds
+//! `std::error::Error` and the `serde` feature derives `Serialize` and
+//! `Deserialize` for every type.
+//!
+//! The molecule layout of the lock witness is defined in
+//! `schemas/lock_witness.mol`, its bindings are in `generated`.
+
+#![no_std]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod args;
+pub mod error;
+pub mod generated;
+pub mod puppet;
+pub mod witness;
+
+pub use error::Error;
+pub use molecule;
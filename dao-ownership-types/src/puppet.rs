@@ -0,0 +1,252 @@
+// Import heap related library from `alloc`
+use alloc::vec::Vec;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// length of a blake160 public key hash
+pub const PUBKEY_HASH_LEN: usize = 20;
+
+/// extension record: operator pubkey hash (20 bytes) | scope (1 byte)
+pub const TAG_OPERATOR: u8 = 0x01;
+const OPERATOR_LEN: usize = PUBKEY_HASH_LEN + 1;
+
+/// extension record: cooldown epochs (u64 LE) | last transfer epoch (u64 LE)
+/// | previous owner pubkey hash (20 bytes), all zero when no transfer is pending
+pub const TAG_COOLDOWN: u8 = 0x02;
+const COOLDOWN_LEN: usize = 8 + 8 + PUBKEY_HASH_LEN;
+
+/// extension record: lessee pubkey hash (20 bytes) | expiry epoch (u64 LE)
+/// | scope (1 byte)
+pub const TAG_LEASE: u8 = 0x03;
+const LEASE_LEN: usize = PUBKEY_HASH_LEN + 8 + 1;
+
+/// extension record: merkle root (32 bytes) of pubkey hashes sharing ownership
+pub const TAG_MERKLE_ROOT: u8 = 0x04;
+const MERKLE_ROOT_LEN: usize = 32;
+
+/// extension record: owner commitment blake2b(pubkey | salt) (32 bytes), so the
+/// owner can't be linked to its other cells by pubkey hash
+pub const TAG_OWNER_COMMITMENT: u8 = 0x05;
+const COMMITMENT_LEN: usize = 32;
+
+/// extension record: lock hash (32 bytes) operators and the lessee must pay
+/// phase 2 withdrawals to
+pub const TAG_PAYOUT_LOCK: u8 = 0x06;
+
+/// operator scope bits, an operator with scope 0 can do nothing
+pub const SCOPE_PHASE1: u8 = 0b01;
+pub const SCOPE_PHASE2: u8 = 0b10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Operator {
+    pub pubkey_hash: [u8; PUBKEY_HASH_LEN],
+    pub scope: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Cooldown {
+    pub epochs: u64,
+    pub last_transfer_epoch: u64,
+    pub previous_owner: [u8; PUBKEY_HASH_LEN],
+}
+
+impl Cooldown {
+    pub fn is_pending(&self) -> bool {
+        self.previous_owner.iter().any(|b| *b != 0)
+    }
+}
+
+/// The lessee acts like an operator until the expiry epoch, the owner and
+/// its operators are locked out meanwhile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Lease {
+    pub lessee: [u8; PUBKEY_HASH_LEN],
+    pub expiry_epoch: u64,
+    pub scope: u8,
+}
+
+/// The ownership record stored in the puppet cell data.
+///
+/// The legacy layout is a bare 20-byte owner pubkey hash. Longer data is the
+/// owner pubkey hash followed by extension records, each one encoded as
+/// `tag (1 byte) | body length (1 byte) | body`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Puppet {
+    pub owner: [u8; PUBKEY_HASH_LEN],
+    pub operators: Vec<Operator>,
+    pub cooldown: Option<Cooldown>,
+    pub lease: Option<Lease>,
+    pub merkle_root: Option<[u8; MERKLE_ROOT_LEN]>,
+    pub owner_commitment: Option<[u8; COMMITMENT_LEN]>,
+    pub payout_lock: Option<[u8; 32]>,
+}
+
+impl Puppet {
+    /// a puppet with no extension records, the legacy layout
+    pub fn new(owner: [u8; PUBKEY_HASH_LEN]) -> Self {
+        Puppet {
+            owner,
+            operators: Vec::new(),
+            cooldown: None,
+            lease: None,
+            merkle_root: None,
+            owner_commitment: None,
+            payout_lock: None,
+        }
+    }
+
+    pub fn from_slice(data: &[u8]) -> Result<Self, Error> {
+        if data.len() < PUBKEY_HASH_LEN {
+            return Err(Error::WrongPubkeyHashLength);
+        }
+
+        let mut owner = [0u8; PUBKEY_HASH_LEN];
+        owner.copy_from_slice(&data[..PUBKEY_HASH_LEN]);
+        let mut puppet = Puppet::new(owner);
+
+        let mut rest = &data[PUBKEY_HASH_LEN..];
+        while !rest.is_empty() {
+            if rest.len() < 2 {
+                return Err(Error::InvalidPuppetData);
+            }
+            let tag = rest[0];
+            let body_len = rest[1] as usize;
+            if rest.len() < 2 + body_len {
+                return Err(Error::InvalidPuppetData);
+            }
+            let body = &rest[2..2 + body_len];
+            match tag {
+                TAG_OPERATOR => puppet.operators.push(parse_operator(body)?),
+                TAG_COOLDOWN if puppet.cooldown.is_none() => {
+                    puppet.cooldown = Some(parse_cooldown(body)?)
+                },
+                TAG_LEASE if puppet.lease.is_none() => {
+                    puppet.lease = Some(parse_lease(body)?)
+                },
+                TAG_MERKLE_ROOT if puppet.merkle_root.is_none() => {
+                    puppet.merkle_root = Some(parse_hash(body)?);
+                },
+                TAG_OWNER_COMMITMENT if puppet.owner_commitment.is_none() => {
+                    puppet.owner_commitment = Some(parse_hash(body)?);
+                },
+                TAG_PAYOUT_LOCK if puppet.payout_lock.is_none() => {
+                    puppet.payout_lock = Some(parse_hash(body)?);
+                },
+                // refuse what we don't understand rather than ignore a restriction
+                _ => return Err(Error::InvalidPuppetData),
+            }
+            rest = &rest[2 + body_len..];
+        }
+
+        Ok(puppet)
+    }
+
+    /// The puppet cell data, records in the order of their tags. A puppet
+    /// without records serializes to the legacy 20-byte layout.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut data = self.owner.to_vec();
+        for operator in &self.operators {
+            push_record(&mut data, TAG_OPERATOR, &[&operator.pubkey_hash[..], &[operator.scope]]);
+        }
+        if let Some(ref cooldown) = self.cooldown {
+            push_record(&mut data, TAG_COOLDOWN, &[
+                &cooldown.epochs.to_le_bytes()[..],
+                &cooldown.last_transfer_epoch.to_le_bytes()[..],
+                &cooldown.previous_owner[..],
+            ]);
+        }
+        if let Some(ref lease) = self.lease {
+            push_record(&mut data, TAG_LEASE, &[
+                &lease.lessee[..],
+                &lease.expiry_epoch.to_le_bytes()[..],
+                &[lease.scope],
+            ]);
+        }
+        if let Some(ref root) = self.merkle_root {
+            push_record(&mut data, TAG_MERKLE_ROOT, &[&root[..]]);
+        }
+        if let Some(ref commitment) = self.owner_commitment {
+            push_record(&mut data, TAG_OWNER_COMMITMENT, &[&commitment[..]]);
+        }
+        if let Some(ref lock_hash) = self.payout_lock {
+            push_record(&mut data, TAG_PAYOUT_LOCK, &[&lock_hash[..]]);
+        }
+        data
+    }
+
+    pub fn operator(&self, pubkey_hash: &[u8]) -> Option<&Operator> {
+        self.operators
+            .iter()
+            .find(|operator| operator.pubkey_hash[..] == pubkey_hash[..])
+    }
+}
+
+fn push_record(data: &mut Vec<u8>, tag: u8, body: &[&[u8]]) {
+    let body_len: usize = body.iter().map(|part| part.len()).sum();
+    data.push(tag);
+    data.push(body_len as u8);
+    for part in body {
+        data.extend_from_slice(part);
+    }
+}
+
+fn parse_operator(body: &[u8]) -> Result<Operator, Error> {
+    if body.len() != OPERATOR_LEN {
+        return Err(Error::InvalidPuppetData);
+    }
+    let mut pubkey_hash = [0u8; PUBKEY_HASH_LEN];
+    pubkey_hash.copy_from_slice(&body[..PUBKEY_HASH_LEN]);
+    Ok(Operator {
+        pubkey_hash,
+        scope: body[PUBKEY_HASH_LEN],
+    })
+}
+
+fn parse_cooldown(body: &[u8]) -> Result<Cooldown, Error> {
+    if body.len() != COOLDOWN_LEN {
+        return Err(Error::InvalidPuppetData);
+    }
+    let mut epochs = [0u8; 8];
+    epochs.copy_from_slice(&body[..8]);
+    let mut last_transfer_epoch = [0u8; 8];
+    last_transfer_epoch.copy_from_slice(&body[8..16]);
+    let mut previous_owner = [0u8; PUBKEY_HASH_LEN];
+    previous_owner.copy_from_slice(&body[16..]);
+    Ok(Cooldown {
+        epochs: u64::from_le_bytes(epochs),
+        last_transfer_epoch: u64::from_le_bytes(last_transfer_epoch),
+        previous_owner,
+    })
+}
+
+fn parse_lease(body: &[u8]) -> Result<Lease, Error> {
+    if body.len() != LEASE_LEN {
+        return Err(Error::InvalidPuppetData);
+    }
+    let mut lessee = [0u8; PUBKEY_HASH_LEN];
+    lessee.copy_from_slice(&body[..PUBKEY_HASH_LEN]);
+    let mut expiry_epoch = [0u8; 8];
+    expiry_epoch.copy_from_slice(&body[PUBKEY_HASH_LEN..PUBKEY_HASH_LEN + 8]);
+    Ok(Lease {
+        lessee,
+        expiry_epoch: u64::from_le_bytes(expiry_epoch),
+        scope: body[PUBKEY_HASH_LEN + 8],
+    })
+}
+
+/// the merkle root, the owner commitment and the payout lock are 32-byte hashes
+fn parse_hash(body: &[u8]) -> Result<[u8; 32], Error> {
+    if body.len() != MERKLE_ROOT_LEN {
+        return Err(Error::InvalidPuppetData);
+    }
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(body);
+    Ok(hash)
+}
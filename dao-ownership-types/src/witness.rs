@@ -0,0 +1,543 @@
+// Import heap related library from `alloc`
+use alloc::vec::Vec;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use molecule::prelude::{Builder, Byte, Entity, Reader};
+
+use crate::error::Error;
+use crate::generated::lock_witness as packed;
+use crate::puppet::PUBKEY_HASH_LEN;
+
+pub const SIGNATURE_LEN: usize = 65;
+
+/// auth kinds, a bare 65-byte signature is a pubkey hash auth
+pub const AUTH_PUBKEY_HASH: u8 = 0x00;
+pub const AUTH_MERKLE: u8 = 0x01;
+pub const AUTH_COMMITMENT: u8 = 0x02;
+/// signed by an Ethereum key, whose address takes the place of the pubkey hash
+pub const AUTH_ETH: u8 = 0x03;
+/// signed by an RSA key, the signature goes to the payload
+pub const AUTH_RSA: u8 = 0x04;
+pub const AUTH_KIND_MASK: u8 = 0x07;
+
+/// the flags byte announcing the molecule layout, auth kind 7 without flags
+pub const FLAGS_MOLECULE: u8 = 0x07;
+
+/// sighash flags, the high bits of the auth kind byte, choose what the
+/// signature covers: SINGLE signs only the outputs at the group inputs'
+/// indexes, ANYONECANPAY signs only the group inputs and the puppet
+pub const SIGHASH_ALL: u8 = 0x00;
+pub const SIGHASH_SINGLE: u8 = 0x10;
+pub const SIGHASH_ANYONECANPAY: u8 = 0x80;
+/// open transaction, the witness declares the ranges the signature covers,
+/// it can't be combined with the other flags
+pub const SIGHASH_OTX: u8 = 0x20;
+const SIGHASH_MASK: u8 = 0xb0;
+
+/// the witness carries the puppet's input index (u32 LE) so the lock doesn't
+/// have to scan every input for it, it combines with any sighash flags
+pub const FLAG_PUPPET_INDEX: u8 = 0x40;
+
+/// the signature is a 64-byte compact signature, padded with a zero byte, by
+/// the pubkey the witness carries, 33 bytes compressed or 65 uncompressed
+pub const FLAG_PUBKEY: u8 = 0x08;
+pub const COMPACT_SIGNATURE_LEN: usize = 64;
+
+/// four ranges of `start (u32 LE) | count (u32 LE)`
+pub const OTX_RANGES_LEN: usize = 4 * 8;
+
+pub const SALT_LEN: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Auth {
+    /// the signer's pubkey hash is matched against the puppet record
+    PubkeyHash,
+    /// the signer is a leaf of the puppet's merkle allowlist
+    Merkle(MerkleProof),
+    /// the signer's pubkey and the revealed salt open the owner commitment
+    Commitment([u8; SALT_LEN]),
+    /// the signer's Ethereum address is matched like a pubkey hash
+    Eth,
+    /// the RSA signature with its public key, whose blake160 is matched like
+    /// a pubkey hash
+    Rsa(Vec<u8>),
+}
+
+impl Auth {
+    /// the auth kind in the witness, one of the AUTH_* constants
+    pub fn kind(&self) -> u8 {
+        match self {
+            Auth::PubkeyHash => AUTH_PUBKEY_HASH,
+            Auth::Merkle(_) => AUTH_MERKLE,
+            Auth::Commitment(_) => AUTH_COMMITMENT,
+            Auth::Eth => AUTH_ETH,
+            Auth::Rsa(_) => AUTH_RSA,
+        }
+    }
+}
+
+/// `count` items from `start`, the start isn't signed so that an aggregator
+/// can move the open transaction within the merged one
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OtxRange {
+    pub start: u32,
+    pub count: u32,
+}
+
+impl OtxRange {
+    pub fn end(&self) -> usize {
+        self.start as usize + self.count as usize
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        index >= self.start as usize && index < self.end()
+    }
+}
+
+/// the parts of the transaction an open transaction signature covers
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OtxRanges {
+    pub inputs: OtxRange,
+    pub outputs: OtxRange,
+    pub cell_deps: OtxRange,
+    pub header_deps: OtxRange,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MerkleProof {
+    pub leaf: [u8; PUBKEY_HASH_LEN],
+    pub index: u32,
+    pub siblings: Vec<[u8; 32]>,
+}
+
+/// The lock field of the witness, in the compact or the molecule layout.
+///
+/// Compact: `signature (65 bytes) | flags + auth kind (1 byte) [| puppet index | otx ranges | pubkey | auth payload]`,
+/// the puppet index is only present with FLAG_PUPPET_INDEX, the otx ranges
+/// only with SIGHASH_OTX, they are the inputs, outputs, cell deps and header
+/// deps ranges in that order, the pubkey only with FLAG_PUBKEY. The merkle
+/// payload is `leaf pubkey hash (20 bytes) | leaf index (u32 LE) |
+/// sibling count (1 byte) | siblings (32 bytes each)`, the commitment payload
+/// is the 32-byte salt, the RSA payload is the signature for the RSA library
+/// and the signature field is left zero.
+///
+/// Molecule: `signature (65 bytes) | FLAGS_MOLECULE | LockWitness`, the
+/// table of `schemas/lock_witness.mol`.
+///
+/// A bare 65-byte signature in the DAO cell's witness is not a lock witness
+/// but the sighash-all signature of the secp256k1 library, see
+/// `is_sighash_all`. `from_slice` still parses it as a pubkey hash auth, for
+/// the fields holding a domain signed one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LockWitness {
+    #[cfg_attr(feature = "serde", serde(with = "signature_serde"))]
+    pub signature: [u8; SIGNATURE_LEN],
+    pub auth: Auth,
+    pub sighash: u8,
+    pub otx: Option<OtxRanges>,
+    pub puppet_index: Option<u32>,
+    /// the signer's pubkey as given, when the signature isn't recoverable
+    pub pubkey: Option<Vec<u8>>,
+}
+
+impl LockWitness {
+    /// a pubkey hash auth, signing the domain message of the whole transaction
+    pub fn new(signature: [u8; SIGNATURE_LEN]) -> Self {
+        LockWitness {
+            signature,
+            auth: Auth::PubkeyHash,
+            sighash: SIGHASH_ALL,
+            otx: None,
+            puppet_index: None,
+            pubkey: None,
+        }
+    }
+
+    pub fn from_slice(witness: &[u8]) -> Result<Self, Error> {
+        if is_molecule(witness) {
+            return Self::from_molecule(witness);
+        }
+        if witness.len() < SIGNATURE_LEN {
+            return Err(Error::WitnessTooShort);
+        }
+        let mut signature = [0u8; SIGNATURE_LEN];
+        signature.copy_from_slice(&witness[..SIGNATURE_LEN]);
+
+        let byte = match witness.get(SIGNATURE_LEN) {
+            Some(byte) => *byte,
+            None => return Ok(LockWitness::new(signature)),
+        };
+        let sighash = parse_sighash(byte)?;
+        let mut payload = &witness[SIGNATURE_LEN + 1..];
+        let puppet_index = if byte & FLAG_PUPPET_INDEX != 0 {
+            if payload.len() < 4 {
+                return Err(Error::WitnessEncoding);
+            }
+            let mut index = [0u8; 4];
+            index.copy_from_slice(&payload[..4]);
+            payload = &payload[4..];
+            Some(u32::from_le_bytes(index))
+        } else {
+            None
+        };
+        let otx = if sighash == SIGHASH_OTX {
+            if payload.len() < OTX_RANGES_LEN {
+                return Err(Error::WitnessEncoding);
+            }
+            let ranges = OtxRanges::from_slice(&payload[..OTX_RANGES_LEN]);
+            payload = &payload[OTX_RANGES_LEN..];
+            Some(ranges)
+        } else {
+            None
+        };
+
+        let pubkey = if byte & FLAG_PUBKEY != 0 {
+            let len = pubkey_len(&signature, payload)?;
+            let pubkey = payload[..len].to_vec();
+            payload = &payload[len..];
+            Some(pubkey)
+        } else {
+            None
+        };
+
+        let auth = match byte & AUTH_KIND_MASK {
+            AUTH_PUBKEY_HASH | AUTH_ETH if !payload.is_empty() => return Err(Error::InvalidAuthPayload),
+            AUTH_PUBKEY_HASH => Auth::PubkeyHash,
+            AUTH_MERKLE => Auth::Merkle(parse_merkle_proof(payload)?),
+            AUTH_COMMITMENT => Auth::Commitment(parse_salt(payload)?),
+            AUTH_ETH => Auth::Eth,
+            AUTH_RSA => Auth::Rsa(payload.to_vec()),
+            _ => return Err(Error::UnknownAuthKind),
+        };
+        check_auth(&auth, &signature, &pubkey)?;
+
+        Ok(LockWitness { signature, auth, sighash, otx, puppet_index, pubkey })
+    }
+
+    /// The molecule layout, verified strictly: a witness with fields this
+    /// version doesn't know is refused rather than half understood.
+    pub fn from_molecule(witness: &[u8]) -> Result<Self, Error> {
+        if !is_molecule(witness) {
+            return Err(Error::WitnessMolecule);
+        }
+        let reader = packed::LockWitnessReader::from_slice(&witness[SIGNATURE_LEN + 1..])
+            .map_err(|_| Error::WitnessMolecule)?;
+
+        // the options say whether there's a puppet index or a pubkey
+        let byte = reader.flags().as_slice()[0];
+        if byte & (FLAG_PUPPET_INDEX | FLAG_PUBKEY) != 0 {
+            return Err(Error::WitnessMolecule);
+        }
+        let sighash = parse_sighash(byte)?;
+
+        let mut signature = [0u8; SIGNATURE_LEN];
+        signature.copy_from_slice(&witness[..SIGNATURE_LEN]);
+
+        let puppet_index = reader.puppet_index().to_opt().map(|index| {
+            let mut bytes = [0u8; 4];
+            bytes.copy_from_slice(index.raw_data());
+            u32::from_le_bytes(bytes)
+        });
+
+        let otx = match reader.otx().to_opt() {
+            Some(ranges) if sighash == SIGHASH_OTX => Some(OtxRanges::from_slice(ranges.as_slice())),
+            None if sighash != SIGHASH_OTX => None,
+            _ => return Err(Error::WitnessMolecule),
+        };
+
+        let pubkey = match reader.pubkey().to_opt() {
+            Some(pubkey) => {
+                let pubkey = pubkey.raw_data();
+                if pubkey_len(&signature, pubkey)? != pubkey.len() {
+                    return Err(Error::InvalidWitnessPubkey);
+                }
+                Some(pubkey.to_vec())
+            },
+            None => None,
+        };
+
+        let proof = reader.proof().to_opt().map(|proof| proof.to_enum());
+        let auth = match (byte & AUTH_KIND_MASK, proof) {
+            (AUTH_PUBKEY_HASH, None) => Auth::PubkeyHash,
+            (AUTH_MERKLE, Some(packed::AuthProofUnionReader::MerkleProof(proof))) => {
+                let mut leaf = [0u8; PUBKEY_HASH_LEN];
+                leaf.copy_from_slice(proof.leaf().raw_data());
+                let mut index = [0u8; 4];
+                index.copy_from_slice(proof.index().raw_data());
+                let siblings = proof
+                    .siblings()
+                    .iter()
+                    .map(|sibling| {
+                        let mut node = [0u8; 32];
+                        node.copy_from_slice(sibling.raw_data());
+                        node
+                    })
+                    .collect();
+                Auth::Merkle(MerkleProof { leaf, index: u32::from_le_bytes(index), siblings })
+            },
+            (AUTH_COMMITMENT, Some(packed::AuthProofUnionReader::Byte32(salt))) => {
+                let mut bytes = [0u8; SALT_LEN];
+                bytes.copy_from_slice(salt.raw_data());
+                Auth::Commitment(bytes)
+            },
+            (AUTH_ETH, None) => Auth::Eth,
+            (AUTH_RSA, Some(packed::AuthProofUnionReader::Bytes(rsa_signature))) => {
+                Auth::Rsa(rsa_signature.raw_data().to_vec())
+            },
+            (AUTH_PUBKEY_HASH, _) | (AUTH_MERKLE, _) | (AUTH_COMMITMENT, _) | (AUTH_ETH, _) | (AUTH_RSA, _) => {
+                return Err(Error::InvalidAuthPayload)
+            },
+            _ => return Err(Error::UnknownAuthKind),
+        };
+        check_auth(&auth, &signature, &pubkey)?;
+
+        Ok(LockWitness { signature, auth, sighash, otx, puppet_index, pubkey })
+    }
+
+    /// The lock field in the compact layout, always with the flags byte, so
+    /// the lock doesn't take it for a bare sighash-all signature. The compact
+    /// layout counts merkle siblings in a byte, a deeper proof fails with
+    /// `InvalidAuthPayload`, the molecule layout has no limit.
+    pub fn to_vec(&self) -> Result<Vec<u8>, Error> {
+        let mut witness = self.signature.to_vec();
+        let mut byte = self.auth.kind() | self.sighash;
+        if self.puppet_index.is_some() {
+            byte |= FLAG_PUPPET_INDEX;
+        }
+        if self.pubkey.is_some() {
+            byte |= FLAG_PUBKEY;
+        }
+        witness.push(byte);
+        if let Some(index) = self.puppet_index {
+            witness.extend_from_slice(&index.to_le_bytes());
+        }
+        if let Some(ref ranges) = self.otx {
+            witness.extend_from_slice(&ranges.to_vec());
+        }
+        if let Some(ref pubkey) = self.pubkey {
+            witness.extend_from_slice(pubkey);
+        }
+        match self.auth {
+            Auth::PubkeyHash | Auth::Eth => {},
+            Auth::Merkle(ref proof) => {
+                witness.extend_from_slice(&proof.leaf);
+                witness.extend_from_slice(&proof.index.to_le_bytes());
+                if proof.siblings.len() > u8::MAX as usize {
+                    return Err(Error::InvalidAuthPayload);
+                }
+                witness.push(proof.siblings.len() as u8);
+                for sibling in &proof.siblings {
+                    witness.extend_from_slice(sibling);
+                }
+            },
+            Auth::Commitment(ref salt) => witness.extend_from_slice(salt),
+            Auth::Rsa(ref signature) => witness.extend_from_slice(signature),
+        }
+        Ok(witness)
+    }
+
+    /// The lock field in the molecule layout.
+    pub fn to_molecule(&self) -> Vec<u8> {
+        let proof = match self.auth {
+            Auth::PubkeyHash | Auth::Eth => None,
+            Auth::Merkle(ref proof) => {
+                let siblings = proof
+                    .siblings
+                    .iter()
+                    .map(|sibling| packed::Byte32::new_unchecked(sibling.to_vec().into()))
+                    .collect();
+                let proof = packed::MerkleProof::new_builder()
+                    .leaf(packed::Byte20::new_unchecked(proof.leaf.to_vec().into()))
+                    .index(packed::Uint32::new_unchecked(proof.index.to_le_bytes().to_vec().into()))
+                    .siblings(packed::Byte32Vec::new_builder().set(siblings).build())
+                    .build();
+                Some(packed::AuthProofUnion::MerkleProof(proof))
+            },
+            Auth::Commitment(ref salt) => {
+                Some(packed::AuthProofUnion::Byte32(packed::Byte32::new_unchecked(salt.to_vec().into())))
+            },
+            Auth::Rsa(ref signature) => Some(packed::AuthProofUnion::Bytes(molecule_bytes(signature))),
+        };
+
+        let puppet_index = self
+            .puppet_index
+            .map(|index| packed::Uint32::new_unchecked(index.to_le_bytes().to_vec().into()));
+        let otx = self
+            .otx
+            .as_ref()
+            .map(|ranges| packed::OtxRanges::new_unchecked(ranges.to_vec().into()));
+        let pubkey = self.pubkey.as_ref().map(|pubkey| molecule_bytes(pubkey));
+        let proof = proof.map(|proof| packed::AuthProof::new_builder().set(proof).build());
+
+        let table = packed::LockWitness::new_builder()
+            .flags(Byte::new(self.auth.kind() | self.sighash))
+            .puppet_index(packed::Uint32Opt::new_builder().set(puppet_index).build())
+            .otx(packed::OtxRangesOpt::new_builder().set(otx).build())
+            .pubkey(packed::BytesOpt::new_builder().set(pubkey).build())
+            .proof(packed::AuthProofOpt::new_builder().set(proof).build())
+            .build();
+        [&self.signature[..], &[FLAGS_MOLECULE][..], table.as_slice()].concat()
+    }
+}
+
+/// Whether the lock field is a bare signature, the sighash-all signature of
+/// the secp256k1 library rather than a lock witness over the domain message.
+pub fn is_sighash_all(witness: &[u8]) -> bool {
+    witness.len() == SIGNATURE_LEN
+}
+
+/// Whether the lock field is in the molecule layout, by the flags byte after
+/// the signature. A compact witness never has that byte, it is an unknown
+/// auth kind there.
+pub fn is_molecule(witness: &[u8]) -> bool {
+    witness.get(SIGNATURE_LEN) == Some(&FLAGS_MOLECULE)
+}
+
+/// the sighash flags of the flags byte, if the lock supports them
+fn parse_sighash(byte: u8) -> Result<u8, Error> {
+    let sighash = byte & SIGHASH_MASK;
+    if sighash != SIGHASH_OTX && sighash & !(SIGHASH_SINGLE | SIGHASH_ANYONECANPAY) != 0 {
+        return Err(Error::UnknownSighashMode);
+    }
+    Ok(sighash)
+}
+
+/// length of the pubkey at the start of `data`, by its prefix, for the
+/// compact signature in `signature`
+fn pubkey_len(signature: &[u8; SIGNATURE_LEN], data: &[u8]) -> Result<usize, Error> {
+    if signature[COMPACT_SIGNATURE_LEN] != 0 {
+        return Err(Error::InvalidWitnessPubkey);
+    }
+    let len = match data.first() {
+        Some(0x02) | Some(0x03) => 33,
+        Some(0x04) => 65,
+        _ => return Err(Error::InvalidWitnessPubkey),
+    };
+    if data.len() < len {
+        return Err(Error::InvalidWitnessPubkey);
+    }
+    Ok(len)
+}
+
+/// what an auth kind asks of the rest of the witness
+fn check_auth(auth: &Auth, signature: &[u8; SIGNATURE_LEN], pubkey: &Option<Vec<u8>>) -> Result<(), Error> {
+    let valid = match auth {
+        Auth::Eth => pubkey.is_none(),
+        // the RSA signature stands in for the secp256k1 one
+        Auth::Rsa(rsa_signature) => {
+            !rsa_signature.is_empty() && pubkey.is_none() && signature.iter().all(|b| *b == 0)
+        },
+        _ => true,
+    };
+    if !valid {
+        return Err(Error::InvalidAuthPayload);
+    }
+    Ok(())
+}
+
+fn molecule_bytes(data: &[u8]) -> packed::Bytes {
+    packed::Bytes::new_builder()
+        .set(data.iter().map(|b| Byte::new(*b)).collect())
+        .build()
+}
+
+impl OtxRanges {
+    /// `data` is exactly OTX_RANGES_LEN bytes
+    pub fn from_slice(data: &[u8]) -> Self {
+        let range = |i: usize| {
+            let mut start = [0u8; 4];
+            start.copy_from_slice(&data[i * 8..i * 8 + 4]);
+            let mut count = [0u8; 4];
+            count.copy_from_slice(&data[i * 8 + 4..i * 8 + 8]);
+            OtxRange {
+                start: u32::from_le_bytes(start),
+                count: u32::from_le_bytes(count),
+            }
+        };
+        OtxRanges {
+            inputs: range(0),
+            outputs: range(1),
+            cell_deps: range(2),
+            header_deps: range(3),
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(OTX_RANGES_LEN);
+        for range in &[&self.inputs, &self.outputs, &self.cell_deps, &self.header_deps] {
+            data.extend_from_slice(&range.start.to_le_bytes());
+            data.extend_from_slice(&range.count.to_le_bytes());
+        }
+        data
+    }
+}
+
+fn parse_merkle_proof(payload: &[u8]) -> Result<MerkleProof, Error> {
+    const HEADER_LEN: usize = PUBKEY_HASH_LEN + 4 + 1;
+    if payload.len() < HEADER_LEN {
+        return Err(Error::InvalidAuthPayload);
+    }
+    let mut leaf = [0u8; PUBKEY_HASH_LEN];
+    leaf.copy_from_slice(&payload[..PUBKEY_HASH_LEN]);
+    let mut index = [0u8; 4];
+    index.copy_from_slice(&payload[PUBKEY_HASH_LEN..PUBKEY_HASH_LEN + 4]);
+    let count = payload[HEADER_LEN - 1] as usize;
+
+    let siblings = &payload[HEADER_LEN..];
+    if siblings.len() != count * 32 {
+        return Err(Error::InvalidAuthPayload);
+    }
+    let siblings = siblings
+        .chunks(32)
+        .map(|chunk| {
+            let mut sibling = [0u8; 32];
+            sibling.copy_from_slice(chunk);
+            sibling
+        })
+        .collect();
+
+    Ok(MerkleProof {
+        leaf,
+        index: u32::from_le_bytes(index),
+        siblings,
+    })
+}
+
+fn parse_salt(payload: &[u8]) -> Result<[u8; SALT_LEN], Error> {
+    if payload.len() != SALT_LEN {
+        return Err(Error::InvalidAuthPayload);
+    }
+    let mut salt = [0u8; SALT_LEN];
+    salt.copy_from_slice(payload);
+    Ok(salt)
+}
+
+/// serde derives stop at 32-byte arrays, the signature goes as bytes
+#[cfg(feature = "serde")]
+mod signature_serde {
+    use alloc::vec::Vec;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use super::SIGNATURE_LEN;
+
+    pub fn serialize<S: Serializer>(signature: &[u8; SIGNATURE_LEN], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(signature)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; SIGNATURE_LEN], D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        if bytes.len() != SIGNATURE_LEN {
+            return Err(D::Error::invalid_length(bytes.len(), &"a 65-byte signature"));
+        }
+        let mut signature = [0u8; SIGNATURE_LEN];
+        signature.copy_from_slice(&bytes);
+        Ok(signature)
+    }
+}
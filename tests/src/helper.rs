@@ -1,12 +1,42 @@
 use ckb_tool::ckb_crypto::secp::{Generator, Privkey, Pubkey};
 use ckb_tool::ckb_types::{
     bytes::Bytes,
-    core::{TransactionView},
+    core::{EpochNumberWithFraction, HeaderBuilder, HeaderView, ScriptHashType, TransactionBuilder, TransactionView},
     packed::{self, *},
     prelude::*,
     H256,
 };
-use ckb_tool::ckb_hash::{blake2b_256};
+use ckb_tool::ckb_hash::{blake2b_256, new_blake2b};
+use ckb_testtool::{builtin::ALWAYS_SUCCESS, context::Context};
+use ckb_system_scripts::BUNDLED_CELL;
+use std::fs;
+use tiny_keccak::{Hasher, Keccak};
+use openssl::{hash::MessageDigest, pkey::{PKey, Private}, rsa::Rsa, sign::Signer};
+use std::ops::Range;
+use dao_ownership_types::{
+    generated::lock_witness as mol,
+    puppet::{Cooldown, Lease, Operator, Puppet},
+    witness::{self, Auth, LockWitness, MerkleProof, OtxRange, COMPACT_SIGNATURE_LEN, FLAGS_MOLECULE},
+};
+
+use nervos_dao_extended_ownership_script::action::{DAO_TYPE_HASH, SECP256K1_BLAKE160_CODE_HASH};
+
+use super::Loader;
+
+// the layouts the tests build come from the contract's types crate
+pub use dao_ownership_types::puppet::{SCOPE_PHASE1, SCOPE_PHASE2};
+pub use dao_ownership_types::witness::{
+    AUTH_ETH, AUTH_PUBKEY_HASH, SIGHASH_ANYONECANPAY, SIGHASH_OTX, SIGHASH_SINGLE, SIGNATURE_LEN,
+};
+pub const SCOPE_NONE: u8 = 0;
+
+// the dual-mode libraries, built in ckb-miscellaneous-scripts
+pub const SECP256K1_LIBRARY: &str = "../ckb-miscellaneous-scripts/build/secp256k1_blake2b_sighash_all_dual";
+pub const ETH_LIBRARY: &str = "../ckb-miscellaneous-scripts/build/secp256k1_keccak256_sighash_all_dual";
+pub const RSA_LIBRARY: &str = "../ckb-miscellaneous-scripts/build/rsa_sighash_all";
+
+// see contract's domain.rs
+pub const DOMAIN_TAG: &[u8] = b"nervos-dao-extended-ownership";
 
 pub fn blake160(data: &[u8]) -> [u8; 20] {
     let mut buf = [0u8; 20];
@@ -19,14 +49,536 @@ pub fn generate_key_pair() -> (Privkey, Pubkey) {
     return Generator::random_keypair();
 }
 
+/// the Ethereum address of `pubkey`, the last 20 bytes of keccak256(x | y)
+pub fn eth_address(pubkey: &Pubkey) -> [u8; 20] {
+    let mut hash = [0u8; 32];
+    let mut keccak = Keccak::v256();
+    keccak.update(pubkey.as_bytes());
+    keccak.finalize(&mut hash);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
 
-pub fn prepare_witnesses(tx: TransactionView, index: usize, key: &Privkey) -> TransactionView {
-    const SIGNATURE_SIZE: usize = 65;
-    
-    let tx_hash = tx.hash();
-    let message: [u8; 32] = tx_hash.clone().unpack();
-    let message = H256::from(message);
-    let sig = key.sign_recoverable(&message).expect("sign");
+/// `algorithm id` of an RSA key in the `rsa_sighash_all` signatures
+pub const RSA_ALGORITHM_ID: u32 = 1;
+pub const RSA_KEY_SIZE: u32 = 1024;
+
+pub fn generate_rsa_key() -> PKey<Private> {
+    PKey::from_rsa(Rsa::generate(RSA_KEY_SIZE).expect("rsa key")).expect("pkey")
+}
+
+/// `algorithm id | key size | E | N`, the numbers u32 LE and N little
+/// endian, the RSA library identifies the signer by its blake160
+pub fn rsa_pubkey(key: &PKey<Private>) -> Vec<u8> {
+    let rsa = key.rsa().expect("rsa");
+    let mut e = rsa.e().to_vec();
+    e.reverse();
+    e.resize(4, 0);
+    let mut n = rsa.n().to_vec();
+    n.reverse();
+    [&RSA_ALGORITHM_ID.to_le_bytes()[..], &RSA_KEY_SIZE.to_le_bytes(), &e, &n].concat()
+}
+
+/// The RSA payload of a lock witness: the pubkey and the PKCS#1 v1.5
+/// signature of the SHA-256 of `message`.
+pub fn rsa_sign(key: &PKey<Private>, message: &[u8]) -> Vec<u8> {
+    let mut signer = Signer::new(MessageDigest::sha256(), key).expect("signer");
+    signer.update(message).expect("sign");
+    [rsa_pubkey(key), signer.sign_to_vec().expect("sign")].concat()
+}
+
+/// `0x04 | x | y`, CKB only ever hashes the compressed form
+pub fn uncompressed_pubkey(pubkey: &Pubkey) -> Vec<u8> {
+    [&[0x04u8][..], pubkey.as_bytes()].concat()
+}
+
+/// A fresh key and salt for a stealth owner, returns the key, the salt and
+/// the commitment blake2b(pubkey | salt) to store in the puppet.
+pub fn generate_one_time_owner() -> (Privkey, [u8; 32], [u8; 32]) {
+    let (privkey, pubkey) = generate_key_pair();
+    // salt from a throwaway key, nothing links it to the owner key
+    let (_, salt_source) = generate_key_pair();
+    let salt = blake2b_256(salt_source.serialize());
+    let commitment = blake2b_256([&pubkey.serialize()[..], &salt[..]].concat());
+    (privkey, salt, commitment)
+}
+
+/// a genesis block header, transactions reference it to bind signatures to the chain
+pub fn insert_genesis_header(context: &mut Context) -> HeaderView {
+    let genesis = HeaderBuilder::default()
+        .number(0u64.pack())
+        .timestamp(1_573_852_190_812u64.pack())
+        .build();
+    context.insert_header(genesis.clone());
+    genesis
+}
+
+/// What a lock witness signature is bound to besides the transaction.
+pub struct SigningDomain {
+    pub genesis_hash: [u8; 32],
+    pub lock_hash: [u8; 32],
+    pub puppet_type_hash: [u8; 32],
+}
+
+impl SigningDomain {
+    pub fn new(genesis_hash: &Byte32, lock_script: &Script, puppet_type_script: &Script) -> Self {
+        SigningDomain {
+            genesis_hash: genesis_hash.unpack(),
+            lock_hash: lock_script.calc_script_hash().unpack(),
+            puppet_type_hash: puppet_type_script.calc_script_hash().unpack(),
+        }
+    }
+
+    /// blake2b(DOMAIN_TAG | genesis hash | lock script hash | puppet type hash | tx hash)
+    pub fn signing_message(&self, tx: &TransactionView) -> H256 {
+        let tx_hash: [u8; 32] = tx.hash().unpack();
+        let message = blake2b_256(
+            [
+                DOMAIN_TAG,
+                &self.genesis_hash[..],
+                &self.lock_hash[..],
+                &self.puppet_type_hash[..],
+                &tx_hash[..],
+            ]
+            .concat(),
+        );
+        H256::from(message)
+    }
+
+    /// blake2b(DOMAIN_TAG | genesis hash | lock script hash | puppet type hash),
+    /// the `output_type` of a witness with a bare signature
+    pub fn digest(&self) -> [u8; 32] {
+        blake2b_256(
+            [
+                DOMAIN_TAG,
+                &self.genesis_hash[..],
+                &self.lock_hash[..],
+                &self.puppet_type_hash[..],
+            ]
+            .concat(),
+        )
+    }
+
+    /// The message for sighash flags other than ALL, `group` are the indexes
+    /// of the inputs locked by the ownership lock.
+    pub fn partial_signing_message(
+        &self,
+        tx: &TransactionView,
+        sighash: u8,
+        group: &[usize],
+        puppet_index: usize,
+    ) -> H256 {
+        let mut blake2b = new_blake2b();
+        blake2b.update(DOMAIN_TAG);
+        blake2b.update(&self.genesis_hash);
+        blake2b.update(&self.lock_hash);
+        blake2b.update(&self.puppet_type_hash);
+        blake2b.update(&[sighash]);
+
+        if sighash & SIGHASH_ANYONECANPAY != 0 {
+            for i in group.iter().chain(&[puppet_index]) {
+                blake2b.update(tx.inputs().get(*i).expect("input").as_slice());
+            }
+        } else {
+            for input in tx.inputs().into_iter() {
+                blake2b.update(input.as_slice());
+            }
+        }
+
+        let outputs: Vec<usize> = if sighash & SIGHASH_SINGLE != 0 {
+            group.to_vec()
+        } else {
+            (0..tx.outputs().len()).collect()
+        };
+        for i in outputs {
+            let data = tx.outputs_data().get(i).expect("output data").raw_data();
+            blake2b.update(tx.outputs().get(i).expect("output").as_slice());
+            blake2b.update(&(data.len() as u64).to_le_bytes());
+            blake2b.update(&data);
+        }
+
+        let mut message = [0u8; 32];
+        blake2b.finalize(&mut message);
+        H256::from(message)
+    }
+}
+
+/// The ranges of an open transaction covered by a SIGHASH_OTX signature.
+#[derive(Clone)]
+pub struct OtxRanges {
+    pub inputs: Range<usize>,
+    pub outputs: Range<usize>,
+    pub cell_deps: Range<usize>,
+    pub header_deps: Range<usize>,
+}
+
+impl OtxRanges {
+    /// everything in `tx`
+    pub fn whole(tx: &TransactionView) -> Self {
+        OtxRanges {
+            inputs: 0..tx.inputs().len(),
+            outputs: 0..tx.outputs().len(),
+            cell_deps: 0..tx.cell_deps().len(),
+            header_deps: 0..tx.header_deps().len(),
+        }
+    }
+
+    /// the ranges as the lock witness carries them
+    pub fn to_witness(&self) -> witness::OtxRanges {
+        let range = |range: &Range<usize>| OtxRange {
+            start: range.start as u32,
+            count: range.len() as u32,
+        };
+        witness::OtxRanges {
+            inputs: range(&self.inputs),
+            outputs: range(&self.outputs),
+            cell_deps: range(&self.cell_deps),
+            header_deps: range(&self.header_deps),
+        }
+    }
+
+    pub fn from_witness(ranges: &witness::OtxRanges) -> Self {
+        let range = |range: &OtxRange| range.start as usize..range.end();
+        OtxRanges {
+            inputs: range(&ranges.inputs),
+            outputs: range(&ranges.outputs),
+            cell_deps: range(&ranges.cell_deps),
+            header_deps: range(&ranges.header_deps),
+        }
+    }
+
+}
+
+impl SigningDomain {
+    /// The message of a SIGHASH_OTX signature, only the items in `ranges`
+    /// and their counts are hashed.
+    pub fn otx_signing_message(&self, tx: &TransactionView, ranges: &OtxRanges) -> H256 {
+        let mut blake2b = new_blake2b();
+        blake2b.update(DOMAIN_TAG);
+        blake2b.update(&self.genesis_hash);
+        blake2b.update(&self.lock_hash);
+        blake2b.update(&self.puppet_type_hash);
+        blake2b.update(&[SIGHASH_OTX]);
+        for range in &[&ranges.inputs, &ranges.outputs, &ranges.cell_deps, &ranges.header_deps] {
+            blake2b.update(&(range.len() as u32).to_le_bytes());
+        }
+
+        for i in ranges.inputs.clone() {
+            blake2b.update(tx.inputs().get(i).expect("input").as_slice());
+        }
+        for i in ranges.outputs.clone() {
+            let data = tx.outputs_data().get(i).expect("output data").raw_data();
+            blake2b.update(tx.outputs().get(i).expect("output").as_slice());
+            blake2b.update(&(data.len() as u64).to_le_bytes());
+            blake2b.update(&data);
+        }
+        for i in ranges.cell_deps.clone() {
+            blake2b.update(tx.cell_deps().get(i).expect("cell dep").as_slice());
+        }
+        for i in ranges.header_deps.clone() {
+            blake2b.update(&tx.header_deps().get(i).expect("header dep").raw_data());
+        }
+
+        let mut message = [0u8; 32];
+        blake2b.finalize(&mut message);
+        H256::from(message)
+    }
+}
+
+/// Sign the `ranges` of an open transaction, the signature, the flags and
+/// the ranges go to the lock field of the witness at `index`.
+pub fn sign_otx(
+    tx: TransactionView,
+    index: usize,
+    key: &Privkey,
+    domain: &SigningDomain,
+    ranges: &OtxRanges,
+) -> TransactionView {
+    let message = domain.otx_signing_message(&tx, ranges);
+    let mut lock_witness = LockWitness::new(signature(key, &message));
+    lock_witness.sighash = SIGHASH_OTX;
+    lock_witness.otx = Some(ranges.to_witness());
+    let lock = lock_witness.to_vec().expect("lock witness");
+    let mut witnesses: Vec<packed::Bytes> = tx.witnesses().into_iter().collect();
+    witnesses[index] = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(lock)).pack())
+        .build()
+        .as_bytes()
+        .pack();
+    tx.as_advanced_builder()
+        .set_witnesses(witnesses)
+        .build()
+}
+
+/// the ranges declared by the witness at `index`, if it holds an otx signature
+pub fn otx_ranges(tx: &TransactionView, index: usize) -> Option<OtxRanges> {
+    let lock: Bytes = WitnessArgs::from_slice(&tx.witnesses().get(index)?.raw_data())
+        .ok()?
+        .lock()
+        .to_opt()?
+        .unpack();
+    let lock_witness = LockWitness::from_slice(&lock).ok()?;
+    lock_witness.otx.as_ref().map(OtxRanges::from_witness)
+}
+
+/// rewrite the ranges of the otx signature in the witness at `index`, they
+/// aren't signed
+pub fn set_otx_ranges(tx: TransactionView, index: usize, ranges: &OtxRanges) -> TransactionView {
+    let mut witnesses: Vec<packed::Bytes> = tx.witnesses().into_iter().collect();
+    let witness_args = WitnessArgs::from_slice(&witnesses[index].raw_data()).expect("witness args");
+    let lock: Vec<u8> = witness_args.lock().to_opt().expect("lock").raw_data().to_vec();
+    let mut lock_witness = LockWitness::from_slice(&lock).expect("lock witness");
+    lock_witness.otx = Some(ranges.to_witness());
+    let lock = lock_witness.to_vec().expect("lock witness");
+    witnesses[index] = witness_args
+        .as_builder()
+        .lock(Some(Bytes::from(lock)).pack())
+        .build()
+        .as_bytes()
+        .pack();
+    tx.as_advanced_builder()
+        .set_witnesses(witnesses)
+        .build()
+}
+
+/// Merge two open transactions into one.
+///
+/// Inputs, outputs and witnesses of `b` follow those of `a`. Cell deps and
+/// header deps are shared, `b` only adds the ones `a` doesn't have. The
+/// ranges of every otx signature are moved to where the covered items ended
+/// up, which doesn't invalidate the signatures since the starts aren't signed.
+pub fn merge_otxs(a: &TransactionView, b: &TransactionView) -> TransactionView {
+    assert_eq!(a.witnesses().len(), a.inputs().len(), "one witness per input");
+    assert_eq!(b.witnesses().len(), b.inputs().len(), "one witness per input");
+
+    let mut cell_deps: Vec<CellDep> = a.cell_deps().into_iter().collect();
+    for dep in b.cell_deps().into_iter() {
+        if !cell_deps.iter().any(|known| known.as_slice() == dep.as_slice()) {
+            cell_deps.push(dep);
+        }
+    }
+    let mut header_deps: Vec<Byte32> = a.header_deps().into_iter().collect();
+    for dep in b.header_deps().into_iter() {
+        if !header_deps.iter().any(|known| known.as_slice() == dep.as_slice()) {
+            header_deps.push(dep);
+        }
+    }
+
+    let mut merged = a
+        .as_advanced_builder()
+        .inputs(b.inputs())
+        .outputs(b.outputs())
+        .outputs_data(b.outputs_data())
+        .witnesses(b.witnesses())
+        .set_cell_deps(cell_deps.clone())
+        .set_header_deps(header_deps.clone())
+        .build();
+
+    for (otx, offset) in &[(a, 0), (b, a.inputs().len())] {
+        let output_offset = if *offset == 0 { 0 } else { a.outputs().len() };
+        for index in 0..otx.witnesses().len() {
+            let ranges = match otx_ranges(otx, index) {
+                Some(ranges) => ranges,
+                None => continue,
+            };
+            let covered_cell_deps: Vec<CellDep> = ranges
+                .cell_deps
+                .clone()
+                .map(|i| otx.cell_deps().get(i).expect("cell dep"))
+                .collect();
+            let covered_header_deps: Vec<Byte32> = ranges
+                .header_deps
+                .clone()
+                .map(|i| otx.header_deps().get(i).expect("header dep"))
+                .collect();
+            let moved = OtxRanges {
+                inputs: shift(&ranges.inputs, *offset),
+                outputs: shift(&ranges.outputs, output_offset),
+                cell_deps: find_window(&cell_deps, &covered_cell_deps),
+                header_deps: find_window(&header_deps, &covered_header_deps),
+            };
+            merged = set_otx_ranges(merged, offset + index, &moved);
+        }
+    }
+    merged
+}
+
+fn shift(range: &Range<usize>, offset: usize) -> Range<usize> {
+    range.start + offset..range.end + offset
+}
+
+fn find_window<T: Entity>(items: &[T], window: &[T]) -> Range<usize> {
+    if window.is_empty() {
+        return 0..0;
+    }
+    let start = items
+        .windows(window.len())
+        .position(|candidate| {
+            candidate
+                .iter()
+                .zip(window)
+                .all(|(item, covered)| item.as_slice() == covered.as_slice())
+        })
+        .expect("covered deps aren't contiguous in the merged transaction");
+    start..start + window.len()
+}
+
+/// Sign only the parts of `tx` the sighash flags cover, the signature and
+/// the flags go to the lock field of the witness at `index`.
+pub fn sign_with_sighash(
+    tx: TransactionView,
+    index: usize,
+    key: &Privkey,
+    domain: &SigningDomain,
+    sighash: u8,
+    group: &[usize],
+    puppet_index: usize,
+) -> TransactionView {
+    let message = domain.partial_signing_message(&tx, sighash, group, puppet_index);
+    let mut lock_witness = LockWitness::new(signature(key, &message));
+    lock_witness.sighash = sighash;
+    let lock = lock_witness.to_vec().expect("lock witness");
+    let mut witnesses: Vec<packed::Bytes> = tx.witnesses().into_iter().collect();
+    witnesses[index] = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(lock)).pack())
+        .build()
+        .as_bytes()
+        .pack();
+    tx.as_advanced_builder()
+        .set_witnesses(witnesses)
+        .build()
+}
+
+pub fn prepare_witnesses(tx: TransactionView, index: usize, key: &Privkey, domain: &SigningDomain) -> TransactionView {
+    prepare_witnesses_with_auth(tx, index, key, domain, &[])
+}
+
+/// A bare signature for the lock group made of the inputs at `group`: the
+/// sighash-all of secp256k1_blake2b_sighash_all, with the domain in the
+/// `output_type` of the group's first witness, where the signature covers it.
+pub fn sign_bare(tx: TransactionView, group: &[usize], key: &Privkey, domain: &SigningDomain) -> TransactionView {
+    let mut witnesses: Vec<packed::Bytes> = tx.witnesses().into_iter().collect();
+    let first = WitnessArgs::from_slice(&witnesses[group[0]].raw_data()).unwrap_or_default();
+    witnesses[group[0]] = first
+        .as_builder()
+        .output_type(Some(Bytes::from(domain.digest().to_vec())).pack())
+        .build()
+        .as_bytes()
+        .pack();
+    let tx = tx.as_advanced_builder()
+        .set_witnesses(witnesses)
+        .build();
+    sign_sighash_all(tx, group, key)
+}
+
+/// Sign like `prepare_witnesses`, but with a 64-byte compact signature and
+/// an explicit `pubkey`, the way HSMs and threshold signers sign.
+pub fn prepare_witnesses_with_pubkey(
+    tx: TransactionView,
+    index: usize,
+    key: &Privkey,
+    domain: &SigningDomain,
+    pubkey: &[u8],
+) -> TransactionView {
+    let tx = prepare_witnesses(tx, index, key, domain);
+    map_lock(tx, index, |mut lock_witness| {
+        // drop the recovery id, the signature field is padded with a zero byte
+        lock_witness.signature[COMPACT_SIGNATURE_LEN] = 0;
+        lock_witness.pubkey = Some(pubkey.to_vec());
+        lock_witness.to_vec().expect("lock witness")
+    })
+}
+
+/// Sign like `prepare_witnesses`, with the lock field in the molecule layout
+/// as `build` leaves it.
+pub fn prepare_molecule_witnesses<F>(
+    tx: TransactionView,
+    index: usize,
+    key: &Privkey,
+    domain: &SigningDomain,
+    build: F,
+) -> TransactionView
+where
+    F: FnOnce(MoleculeWitnessBuilder) -> MoleculeWitnessBuilder,
+{
+    let tx = prepare_witnesses(tx, index, key, domain);
+    map_lock(tx, index, |lock_witness| build(MoleculeWitnessBuilder::new(&lock_witness)).build())
+}
+
+/// replace the lock field at `index` by what `f` makes of it
+fn map_lock<F>(tx: TransactionView, index: usize, f: F) -> TransactionView
+where
+    F: FnOnce(LockWitness) -> Vec<u8>,
+{
+    let mut witnesses: Vec<packed::Bytes> = tx.witnesses().into_iter().collect();
+    let witness = WitnessArgs::from_slice(&witnesses[index].raw_data()).expect("witness args");
+    let lock: Bytes = witness.lock().to_opt().expect("lock").unpack();
+    let lock = f(LockWitness::from_slice(&lock).expect("lock witness"));
+    witnesses[index] = witness
+        .as_builder()
+        .lock(Some(Bytes::from(lock)).pack())
+        .build()
+        .as_bytes()
+        .pack();
+    tx.as_advanced_builder()
+        .set_witnesses(witnesses)
+        .build()
+}
+
+/// Builds the lock field in the molecule layout, starting from a
+/// `LockWitness` and overriding fields of its table one by one, so tests
+/// can also build witnesses the lock has to refuse.
+pub struct MoleculeWitnessBuilder([u8; SIGNATURE_LEN], mol::LockWitnessBuilder);
+
+impl MoleculeWitnessBuilder {
+    pub fn new(lock_witness: &LockWitness) -> Self {
+        let molecule = lock_witness.to_molecule();
+        let table = mol::LockWitness::from_slice(&molecule[SIGNATURE_LEN + 1..]).expect("molecule witness");
+        MoleculeWitnessBuilder(lock_witness.signature, table.as_builder())
+    }
+
+    /// the auth kind and sighash flags byte as it is
+    pub fn flags(self, flags: u8) -> Self {
+        MoleculeWitnessBuilder(self.0, self.1.flags(Byte::new(flags)))
+    }
+
+    pub fn pubkey(self, pubkey: Option<&[u8]>) -> Self {
+        let pubkey = pubkey.map(|pubkey| {
+            mol::Bytes::new_builder()
+                .set(pubkey.iter().map(|b| Byte::new(*b)).collect())
+                .build()
+        });
+        MoleculeWitnessBuilder(self.0, self.1.pubkey(mol::BytesOpt::new_builder().set(pubkey).build()))
+    }
+
+    pub fn proof(self, proof: Option<mol::AuthProofUnion>) -> Self {
+        let proof = proof.map(|proof| mol::AuthProof::new_builder().set(proof).build());
+        MoleculeWitnessBuilder(self.0, self.1.proof(mol::AuthProofOpt::new_builder().set(proof).build()))
+    }
+
+    pub fn build(self) -> Vec<u8> {
+        [&self.0[..], &[FLAGS_MOLECULE][..], self.1.build().as_slice()].concat()
+    }
+}
+
+/// Sign the domain separated message, appending `auth` (auth kind and
+/// payload) after the signature. Without `auth` the lock field is the pubkey
+/// hash auth as `LockWitness::to_vec` writes it.
+pub fn prepare_witnesses_with_auth(
+    tx: TransactionView,
+    index: usize,
+    key: &Privkey,
+    domain: &SigningDomain,
+    auth: &[u8],
+) -> TransactionView {
+    let message = domain.signing_message(&tx);
+    let sig = signature(key, &message);
+    let lock = if auth.is_empty() {
+        LockWitness::new(sig).to_vec().expect("lock witness")
+    } else {
+        [&sig[..], auth].concat()
+    };
 
     let witnesses_len = tx.inputs().len();
 
@@ -38,7 +590,7 @@ pub fn prepare_witnesses(tx: TransactionView, index: usize, key: &Privkey) -> Tr
             signed_witnesses.push(
                 witness.clone()
                     .as_builder()
-                    .lock(Some(Bytes::from(sig.serialize())).pack())
+                    .lock(Some(Bytes::from(lock.clone())).pack())
                     .build()
                     .as_bytes()
                     .pack(),
@@ -47,7 +599,7 @@ pub fn prepare_witnesses(tx: TransactionView, index: usize, key: &Privkey) -> Tr
             // push empty witness in other field
             let zero_lock: Bytes = {
                 let mut buf = Vec::new();
-                buf.resize(SIGNATURE_SIZE, 0);
+                buf.resize(SIGNATURE_LEN, 0);
                 buf.into()
             };
             let witness_for_digest = witness
@@ -65,3 +617,662 @@ pub fn prepare_witnesses(tx: TransactionView, index: usize, key: &Privkey) -> Tr
         .set_witnesses(signed_witnesses)
         .build()
 }
+
+/// Share one signature through the puppet's witness: the signature goes to
+/// the `input_type` field of the witness at `puppet_index`, the other
+/// witnesses are left empty.
+pub fn sign_puppet_input_type(
+    tx: TransactionView,
+    puppet_index: usize,
+    key: &Privkey,
+    domain: &SigningDomain,
+    auth: &[u8],
+) -> TransactionView {
+    let sig = key.sign_recoverable(&domain.signing_message(&tx)).expect("sign");
+    let witnesses: Vec<packed::Bytes> = (0..tx.inputs().len())
+        .map(|i| {
+            if i == puppet_index {
+                WitnessArgs::new_builder()
+                    .input_type(Some(Bytes::from([&sig.serialize()[..], auth].concat())).pack())
+                    .build()
+                    .as_bytes()
+                    .pack()
+            } else {
+                Bytes::new().pack()
+            }
+        })
+        .collect();
+    tx.as_advanced_builder()
+        .set_witnesses(witnesses)
+        .build()
+}
+
+/// Sign the lock group made of the inputs at `group` the way
+/// secp256k1_blake2b_sighash_all does, the signature goes to the lock field
+/// of the group's first witness.
+pub fn sign_sighash_all(tx: TransactionView, group: &[usize], key: &Privkey) -> TransactionView {
+    const SIGNATURE_SIZE: usize = 65;
+
+    let mut witnesses: Vec<packed::Bytes> = tx.witnesses().into_iter().collect();
+    let first = WitnessArgs::from_slice(&witnesses[group[0]].raw_data()).unwrap_or_default();
+    let zero_lock = first
+        .clone()
+        .as_builder()
+        .lock(Some(Bytes::from(vec![0u8; SIGNATURE_SIZE])).pack())
+        .build()
+        .as_bytes();
+
+    let mut blake2b = new_blake2b();
+    blake2b.update(&tx.hash().raw_data());
+    blake2b.update(&(zero_lock.len() as u64).to_le_bytes());
+    blake2b.update(&zero_lock);
+    let others = group[1..].iter().map(|i| &witnesses[*i]);
+    for witness in others.chain(witnesses.iter().skip(tx.inputs().len())) {
+        let witness = witness.raw_data();
+        blake2b.update(&(witness.len() as u64).to_le_bytes());
+        blake2b.update(&witness);
+    }
+    let mut message = [0u8; 32];
+    blake2b.finalize(&mut message);
+
+    let sig = key.sign_recoverable(&H256::from(message)).expect("sign");
+    witnesses[group[0]] = first
+        .as_builder()
+        .lock(Some(Bytes::from(sig.serialize())).pack())
+        .build()
+        .as_bytes()
+        .pack();
+    tx.as_advanced_builder()
+        .set_witnesses(witnesses)
+        .build()
+}
+
+/// the recoverable signature of `message` by `key`
+pub fn signature(key: &Privkey, message: &H256) -> [u8; SIGNATURE_LEN] {
+    let mut signature = [0u8; SIGNATURE_LEN];
+    signature.copy_from_slice(&key.sign_recoverable(message).expect("sign").serialize());
+    signature
+}
+
+/// encode puppet cell data: owner pubkey hash followed by operator records
+pub fn puppet_data(owner: &[u8; 20], operators: &[([u8; 20], u8)]) -> Bytes {
+    let mut puppet = Puppet::new(*owner);
+    puppet.operators = operators
+        .iter()
+        .map(|(pubkey_hash, scope)| Operator { pubkey_hash: *pubkey_hash, scope: *scope })
+        .collect();
+    puppet.to_vec().into()
+}
+
+/// rewrite the puppet cell `data` with `update` applied
+fn update_puppet(data: Bytes, update: impl FnOnce(&mut Puppet)) -> Bytes {
+    let mut puppet = Puppet::from_slice(&data).expect("puppet data");
+    update(&mut puppet);
+    puppet.to_vec().into()
+}
+
+/// add a cooldown record, a zero previous owner means no transfer is pending
+pub fn push_cooldown(data: Bytes, epochs: u64, last_transfer_epoch: u64, previous_owner: &[u8; 20]) -> Bytes {
+    update_puppet(data, |puppet| {
+        puppet.cooldown = Some(Cooldown {
+            epochs,
+            last_transfer_epoch,
+            previous_owner: *previous_owner,
+        })
+    })
+}
+
+/// add a lease record, `lessee` acts within `scope` until `expiry_epoch`
+pub fn push_lease(data: Bytes, lessee: &[u8; 20], expiry_epoch: u64, scope: u8) -> Bytes {
+    update_puppet(data, |puppet| {
+        puppet.lease = Some(Lease {
+            lessee: *lessee,
+            expiry_epoch,
+            scope,
+        })
+    })
+}
+
+/// add a merkle root of pubkey hashes sharing ownership
+pub fn push_merkle_root(data: Bytes, root: &[u8; 32]) -> Bytes {
+    update_puppet(data, |puppet| puppet.merkle_root = Some(*root))
+}
+
+/// add an owner commitment blake2b(pubkey | salt)
+pub fn push_owner_commitment(data: Bytes, commitment: &[u8; 32]) -> Bytes {
+    update_puppet(data, |puppet| puppet.owner_commitment = Some(*commitment))
+}
+
+/// add the lock hash operators and the lessee must pay withdrawals to
+pub fn push_payout_lock(data: Bytes, lock_hash: &[u8; 32]) -> Bytes {
+    update_puppet(data, |puppet| puppet.payout_lock = Some(*lock_hash))
+}
+
+/// the sibling of an odd node out, no leaf or node hashes to it
+pub const MERKLE_PADDING: [u8; 32] = [0u8; 32];
+
+pub fn merkle_leaf(pubkey_hash: &[u8; 20]) -> [u8; 32] {
+    blake2b_256([&[0u8][..], &pubkey_hash[..]].concat())
+}
+
+pub fn merkle_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    blake2b_256([&[1u8][..], &left[..], &right[..]].concat())
+}
+
+/// merkle root over `members` and the sibling path of the member at `index`,
+/// an odd node out is paired with a zero hash
+pub fn merkle_proof(members: &[[u8; 20]], index: usize) -> ([u8; 32], Vec<[u8; 32]>) {
+    let mut level: Vec<[u8; 32]> = members.iter().map(merkle_leaf).collect();
+    let mut position = index;
+    let mut siblings = Vec::new();
+    while level.len() > 1 {
+        let sibling = position ^ 1;
+        siblings.push(*level.get(sibling).unwrap_or(&MERKLE_PADDING));
+        level = level
+            .chunks(2)
+            .map(|pair| merkle_node(&pair[0], pair.get(1).unwrap_or(&MERKLE_PADDING)))
+            .collect();
+        position /= 2;
+    }
+    (level[0], siblings)
+}
+
+/// what follows the signature in the lock witness
+fn auth_bytes(lock_witness: LockWitness) -> Vec<u8> {
+    lock_witness.to_vec().expect("lock witness").split_off(SIGNATURE_LEN)
+}
+
+/// witness auth for a merkle allowlist member
+pub fn merkle_auth(leaf: &[u8; 20], index: u32, siblings: &[[u8; 32]]) -> Vec<u8> {
+    let mut lock_witness = LockWitness::new([0u8; SIGNATURE_LEN]);
+    lock_witness.auth = Auth::Merkle(MerkleProof {
+        leaf: *leaf,
+        index,
+        siblings: siblings.to_vec(),
+    });
+    auth_bytes(lock_witness)
+}
+
+/// witness auth revealing the salt of the owner commitment
+pub fn commitment_auth(salt: &[u8; 32]) -> Vec<u8> {
+    let mut lock_witness = LockWitness::new([0u8; SIGNATURE_LEN]);
+    lock_witness.auth = Auth::Commitment(*salt);
+    auth_bytes(lock_witness)
+}
+
+/// a pubkey hash auth hinting the puppet's input index
+pub fn puppet_index_auth(index: u32) -> Vec<u8> {
+    let mut lock_witness = LockWitness::new([0u8; SIGNATURE_LEN]);
+    lock_witness.puppet_index = Some(index);
+    auth_bytes(lock_witness)
+}
+
+/// since of an input which can only be committed from epoch `number` on
+pub fn since_absolute_epoch(number: u64) -> u64 {
+    // epoch with fraction 0/1, see RFC 0017
+    0x2000_0000_0000_0000 | (1 << 40) | number
+}
+
+/// since of an input which must be at least `number` epochs old
+pub fn since_relative_epoch(number: u64) -> u64 {
+    0xa000_0000_0000_0000 | (1 << 40) | number
+}
+
+/// nervosDAO cell data for a deposit (zero) or a withdrawing cell (block number)
+pub fn dao_data(block_number: u64) -> Bytes {
+    block_number.to_le_bytes().to_vec().into()
+}
+
+/// The always success script deployed as the nervosDAO: in a cell with the
+/// type id of the genesis DAO cell, so its type script is the real nervosDAO
+/// type script the lock checks for.
+fn deploy_dao_type(context: &mut Context) -> (OutPoint, Script) {
+    // the genesis DAO cell is output 2 of the genesis cellbase
+    let mut type_id_args = [0u8; 32];
+    let mut hasher = new_blake2b();
+    hasher.update(CellInput::new_cellbase_input(0).as_slice());
+    hasher.update(&2u64.to_le_bytes());
+    hasher.finalize(&mut type_id_args);
+    let mut type_id_code_hash = [0u8; 32];
+    type_id_code_hash[25..].copy_from_slice(b"TYPE_ID");
+    let type_id = Script::new_builder()
+        .code_hash(type_id_code_hash.pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(type_id_args.to_vec()).pack())
+        .build();
+    let output = CellOutput::new_builder()
+        .capacity((ALWAYS_SUCCESS.len() as u64).pack())
+        .type_(Some(type_id.clone()).pack())
+        .build();
+    let out_point = context.create_cell(output, ALWAYS_SUCCESS.clone());
+
+    let dao_type_script = Script::new_builder()
+        .code_hash(type_id.calc_script_hash())
+        .hash_type(ScriptHashType::Type.into())
+        .build();
+    let dao_type_hash: [u8; 32] = dao_type_script.calc_script_hash().unpack();
+    assert_eq!(dao_type_hash, DAO_TYPE_HASH, "not the nervosDAO type script");
+    (out_point, dao_type_script)
+}
+
+/// the genesis secp256k1 blake160 sighash-all lock of `owner`, it isn't
+/// deployed as output locks don't run
+pub fn owner_lock_script(owner: &[u8; 20]) -> Script {
+    Script::new_builder()
+        .code_hash(SECP256K1_BLAKE160_CODE_HASH.pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args(Bytes::from(owner.to_vec()).pack())
+        .build()
+}
+
+/// A context with the ownership lock, the secp256k1 library, a faked
+/// puppet type script and the nervosDAO already deployed.
+pub struct DaoEnv {
+    pub context: Context,
+    pub lock_out_point: OutPoint,
+    pub as_lock_script: Script,
+    pub puppet_type_script: Script,
+    pub dao_type_script: Script,
+    pub secp256k1_out_point: OutPoint,
+    pub cell_deps: Vec<CellDep>,
+    pub genesis: HeaderView,
+    /// headers of the blocks cells were created in, see `create_puppet_in_epoch`
+    pub header_deps: Vec<Byte32>,
+    /// the real puppet type script, once deployed by `use_puppet_type`
+    pub puppet_type_out_point: Option<OutPoint>,
+    /// appended to the ownership lock args after the puppet type hash
+    pub lock_args_suffix: Bytes,
+}
+
+impl DaoEnv {
+    pub fn new() -> Self {
+        let mut context = Context::default();
+        let genesis = insert_genesis_header(&mut context);
+        let contract_bin: Bytes = Loader::default().load_binary("nervos-dao-extended-ownership-script");
+        let lock_out_point = context.deploy_cell(contract_bin);
+
+        // deploy secp256 contract
+        let secp256k1_bin: Bytes = fs::read(SECP256K1_LIBRARY).expect("load secp256k1").into();
+        let secp256k1_out_point = context.deploy_cell(secp256k1_bin);
+        let secp256k1_data_bin = BUNDLED_CELL.get("specs/cells/secp256k1_data").unwrap();
+        let secp256k1_data_out_point = context.deploy_cell(secp256k1_data_bin.to_vec().into());
+
+        // the always success script plays the lock of every other cell,
+        // as well as the puppet type-id with unique args
+        let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+        let as_lock_script = context
+            .build_script(&always_success_out_point, Default::default())
+            .expect("script");
+        let puppet_type_script = context
+            .build_script(&always_success_out_point, Bytes::from("0x11000000".to_string()))
+            .expect("script");
+        let (dao_out_point, dao_type_script) = deploy_dao_type(&mut context);
+
+        let cell_deps = vec![
+            lock_out_point.clone(),
+            always_success_out_point,
+            secp256k1_out_point.clone(),
+            secp256k1_data_out_point,
+            dao_out_point,
+        ]
+        .into_iter()
+        .map(|out_point| CellDep::new_builder().out_point(out_point).build())
+        .collect();
+
+        DaoEnv {
+            context,
+            lock_out_point,
+            as_lock_script,
+            puppet_type_script,
+            dao_type_script,
+            secp256k1_out_point,
+            cell_deps,
+            genesis,
+            header_deps: Vec::new(),
+            puppet_type_out_point: None,
+            lock_args_suffix: Bytes::new(),
+        }
+    }
+
+    /// the signing domain of the ownership lock on this chain
+    pub fn domain(&mut self) -> SigningDomain {
+        let lock_script = self.dao_lock_script();
+        SigningDomain::new(&self.genesis.hash(), &lock_script, &self.puppet_type_script)
+    }
+
+    /// the ownership lock bound to the puppet type script
+    pub fn dao_lock_script(&mut self) -> Script {
+        let dao_ownership_id: [u8; 32] = self.puppet_type_script.calc_script_hash().unpack();
+        let args = [&dao_ownership_id[..], &self.lock_args_suffix[..]].concat();
+        self.context
+            .build_script(&self.lock_out_point, args.into())
+            .expect("script")
+    }
+
+    /// Redeploy the secp256k1 library in a cell with a type id, in place of
+    /// the cell dep found by data hash. Returns the type hash.
+    pub fn use_secp256k1_type_id(&mut self) -> [u8; 32] {
+        let (out_point, type_hash) = self.create_type_id_cell(SECP256K1_LIBRARY, 0x42);
+        let secp256k1_dep = CellDep::new_builder()
+            .out_point(self.secp256k1_out_point.clone())
+            .build();
+        for dep in self.cell_deps.iter_mut() {
+            if dep.as_slice() == secp256k1_dep.as_slice() {
+                *dep = CellDep::new_builder().out_point(out_point.clone()).build();
+            }
+        }
+        type_hash
+    }
+
+    /// Deploy the dual-mode library at `path`, e.g. `ETH_LIBRARY`, as one
+    /// more cell dep, found by its data hash.
+    pub fn deploy_library(&mut self, path: &str) {
+        let library_bin: Bytes = fs::read(path).expect("load library").into();
+        let out_point = self.context.deploy_cell(library_bin);
+        self.cell_deps.push(CellDep::new_builder().out_point(out_point).build());
+    }
+
+    /// Deploy the library at `path` as one more cell dep, in a cell with the
+    /// type id `args`. Returns the type hash.
+    pub fn deploy_library_with_type_id(&mut self, path: &str, args: u8) -> [u8; 32] {
+        let (out_point, type_hash) = self.create_type_id_cell(path, args);
+        self.cell_deps.push(CellDep::new_builder().out_point(out_point).build());
+        type_hash
+    }
+
+    fn create_type_id_cell(&mut self, path: &str, args: u8) -> (OutPoint, [u8; 32]) {
+        let library_bin: Bytes = fs::read(path).expect("load library").into();
+        // the type id script only runs on inputs and outputs, not cell deps
+        let mut type_id_code_hash = [0u8; 32];
+        type_id_code_hash[25..].copy_from_slice(b"TYPE_ID");
+        let type_id = Script::new_builder()
+            .code_hash(type_id_code_hash.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(vec![args; 32]).pack())
+            .build();
+        let output = CellOutput::new_builder()
+            .capacity((library_bin.len() as u64).pack())
+            .lock(self.as_lock_script.clone())
+            .type_(Some(type_id.clone()).pack())
+            .build();
+        let out_point = self.context.create_cell(output, library_bin);
+        (out_point, type_id.calc_script_hash().unpack())
+    }
+
+    /// Put the puppets under the real puppet type script, in place of the
+    /// faked type id, with `args` as their type id. Call it before creating
+    /// any cell, the ownership lock args depend on it.
+    pub fn use_puppet_type(&mut self, args: &[u8; 32]) {
+        self.puppet_type_script = self.puppet_type_script_with(args);
+    }
+
+    /// Like `use_puppet_type`, with the ownership lock as the guard lock
+    /// of pending transfers, see `guard_lock_script`.
+    pub fn use_guarded_puppet_type(&mut self, type_id: &[u8; 32]) {
+        let lock_script = self
+            .context
+            .build_script(&self.lock_out_point, Bytes::new())
+            .expect("script");
+        let args = [&type_id[..], lock_script.code_hash().as_slice(), lock_script.hash_type().as_slice()].concat();
+        self.puppet_type_script = self.puppet_type_script_with(&args);
+    }
+
+    /// the guard lock of a pending transfer, the ownership lock with the
+    /// bare puppet type hash as args
+    pub fn guard_lock_script(&mut self) -> Script {
+        let puppet_type_hash: [u8; 32] = self.puppet_type_script.calc_script_hash().unpack();
+        self.context
+            .build_script(&self.lock_out_point, puppet_type_hash.to_vec().into())
+            .expect("script")
+    }
+
+    /// the puppet type script with the type id `args`, and maybe a guard
+    pub fn puppet_type_script_with(&mut self, args: &[u8]) -> Script {
+        let out_point = match self.puppet_type_out_point {
+            Some(ref out_point) => out_point.clone(),
+            None => {
+                let type_bin: Bytes = Loader::default().load_binary(PUPPET_TYPE);
+                let out_point = self.context.deploy_cell(type_bin);
+                self.cell_deps.push(CellDep::new_builder().out_point(out_point.clone()).build());
+                self.puppet_type_out_point = Some(out_point.clone());
+                out_point
+            },
+        };
+        self.context
+            .build_script(&out_point, Bytes::from(args.to_vec()))
+            .expect("script")
+    }
+
+    /// Swap the ownership lock for another build of it, e.g.
+    /// `TRACE_LOCK`, redeployed in place of the default binary.
+    pub fn use_lock_binary(&mut self, name: &str) {
+        let lock_bin: Bytes = Loader::default().load_binary(name);
+        let out_point = self.context.deploy_cell(lock_bin);
+        let lock_dep = CellDep::new_builder()
+            .out_point(self.lock_out_point.clone())
+            .build();
+        for dep in self.cell_deps.iter_mut() {
+            if dep.as_slice() == lock_dep.as_slice() {
+                *dep = CellDep::new_builder().out_point(out_point.clone()).build();
+            }
+        }
+        self.lock_out_point = out_point;
+    }
+
+    /// Use the lock built with the `trace` feature and capture its debug
+    /// messages, read them back with `trace_events`.
+    pub fn use_trace_lock(&mut self) {
+        self.use_lock_binary(TRACE_LOCK);
+        self.context.set_capture_debug(true);
+    }
+
+    /// Deploy the ownership lock built as a library, and the test type
+    /// script which asks it whether the puppet's owner signed. Returns the
+    /// test type script, its cell deps are added.
+    pub fn puppet_owner_type_script(&mut self) -> Script {
+        let lib_bin: Bytes = Loader::default().load_binary("nervos-dao-extended-ownership-lib");
+        let lib_hash = CellOutput::calc_data_hash(&lib_bin);
+        let lib_out_point = self.context.deploy_cell(lib_bin);
+        let type_bin: Bytes = Loader::default().load_binary("puppet-owner-test-type");
+        let type_out_point = self.context.deploy_cell(type_bin);
+        for out_point in vec![lib_out_point, type_out_point.clone()] {
+            self.cell_deps.push(CellDep::new_builder().out_point(out_point).build());
+        }
+
+        let puppet_type_hash: [u8; 32] = self.puppet_type_script.calc_script_hash().unpack();
+        let args = [&puppet_type_hash[..], lib_hash.as_slice()].concat();
+        self.context
+            .build_script(&type_out_point, args.into())
+            .expect("script")
+    }
+
+    pub fn puppet_output(&self) -> CellOutput {
+        CellOutput::new_builder()
+            .capacity(500u64.pack())
+            .lock(self.as_lock_script.clone())
+            .type_(Some(self.puppet_type_script.clone()).pack())
+            .build()
+    }
+
+    pub fn dao_output(&mut self) -> CellOutput {
+        CellOutput::new_builder()
+            .capacity(500u64.pack())
+            .lock(self.dao_lock_script())
+            .type_(Some(self.dao_type_script.clone()).pack())
+            .build()
+    }
+
+    /// a cell paid to the owner's default secp256k1 lock, where operators
+    /// and the lessee pay withdrawals of a puppet without payout record
+    pub fn owner_output(&self, owner: &[u8; 20]) -> CellOutput {
+        self.plain_output()
+            .as_builder()
+            .lock(owner_lock_script(owner))
+            .build()
+    }
+
+    pub fn plain_output(&self) -> CellOutput {
+        CellOutput::new_builder()
+            .capacity(200u64.pack())
+            .lock(self.as_lock_script.clone())
+            .build()
+    }
+
+    pub fn create_puppet(&mut self, data: Bytes) -> CellInput {
+        let output = self.puppet_output();
+        let out_point = self.context.create_cell(output, data);
+        CellInput::new_builder().previous_output(out_point).build()
+    }
+
+    /// A puppet created in a block of `epoch`, whose header the
+    /// transactions carry, so the lock can tell when the puppet was created.
+    pub fn create_puppet_in_epoch(&mut self, data: Bytes, epoch: u64) -> CellInput {
+        let header = self.push_header_dep(epoch * 1000 + 1, epoch);
+        let input = self.create_puppet(data);
+        self.context
+            .link_cell_with_block(input.previous_output(), header.hash(), 0);
+        input
+    }
+
+    /// a header dep of block `number` in `epoch`
+    pub fn push_header_dep(&mut self, number: u64, epoch: u64) -> HeaderView {
+        let header = HeaderBuilder::default()
+            .number(number.pack())
+            .epoch(EpochNumberWithFraction::new(epoch, 0, 1000).full_value().pack())
+            .build();
+        self.context.insert_header(header.clone());
+        self.header_deps.push(header.hash());
+        header
+    }
+
+    /// the dual secp256k1 library also runs as a sighash-all lock
+    pub fn secp256k1_lock_script(&mut self, pubkey_hash: &[u8; 20]) -> Script {
+        self.context
+            .build_script(&self.secp256k1_out_point, pubkey_hash.to_vec().into())
+            .expect("script")
+    }
+
+    /// a puppet guarded by a secp256k1 lock instead of always success
+    pub fn create_puppet_with_lock(&mut self, lock: Script, data: Bytes) -> CellInput {
+        let output = self.puppet_output().as_builder().lock(lock).build();
+        let out_point = self.context.create_cell(output, data);
+        CellInput::new_builder().previous_output(out_point).build()
+    }
+
+    /// `create_puppet_with_lock` in a block of `epoch`, see `create_puppet_in_epoch`
+    pub fn create_puppet_with_lock_in_epoch(&mut self, lock: Script, data: Bytes, epoch: u64) -> CellInput {
+        let header = self.push_header_dep(epoch * 1000 + 1, epoch);
+        let input = self.create_puppet_with_lock(lock, data);
+        self.context
+            .link_cell_with_block(input.previous_output(), header.hash(), 0);
+        input
+    }
+
+    /// a puppet that stays put, referenced as a cell dep
+    pub fn puppet_cell_dep(&mut self, data: Bytes) -> CellDep {
+        let output = self.puppet_output();
+        let out_point = self.context.create_cell(output, data);
+        CellDep::new_builder().out_point(out_point).build()
+    }
+
+    pub fn create_dao_cell(&mut self, data: Bytes) -> CellInput {
+        let output = self.dao_output();
+        let out_point = self.context.create_cell(output, data);
+        CellInput::new_builder().previous_output(out_point).build()
+    }
+
+    /// assemble a transaction, sign it with `key` in the witness at `index`
+    pub fn build_tx(
+        &mut self,
+        inputs: Vec<CellInput>,
+        outputs: Vec<(CellOutput, Bytes)>,
+        index: usize,
+        key: &Privkey,
+    ) -> TransactionView {
+        self.build_tx_with_auth(inputs, outputs, index, key, &[])
+    }
+
+    pub fn build_tx_with_auth(
+        &mut self,
+        inputs: Vec<CellInput>,
+        outputs: Vec<(CellOutput, Bytes)>,
+        index: usize,
+        key: &Privkey,
+        auth: &[u8],
+    ) -> TransactionView {
+        let tx = self.build_unsigned_tx(inputs, outputs);
+        let domain = self.domain();
+        prepare_witnesses_with_auth(tx, index, key, &domain, auth)
+    }
+
+    /// assemble a transaction with empty witnesses, to be signed by the caller
+    pub fn build_unsigned_tx(
+        &mut self,
+        inputs: Vec<CellInput>,
+        outputs: Vec<(CellOutput, Bytes)>,
+    ) -> TransactionView {
+        let witnesses = vec![Bytes::new(); inputs.len()];
+        let (outputs, outputs_data): (Vec<_>, Vec<_>) = outputs.into_iter().unzip();
+        let tx = TransactionBuilder::default()
+            .inputs(inputs)
+            .outputs(outputs)
+            .outputs_data(outputs_data.pack())
+            .cell_deps(self.cell_deps.clone())
+            .header_dep(self.genesis.hash())
+            .header_deps(self.header_deps.clone())
+            .witnesses(witnesses.pack())
+            .build();
+        self.context.complete_tx(tx)
+    }
+}
+
+/// the puppet's type id script, which guards its cooldown record
+pub const PUPPET_TYPE: &str = "nervos-dao-puppet-type";
+/// the ownership lock built with the `trace` feature
+pub const TRACE_LOCK: &str = "nervos-dao-extended-ownership-trace";
+/// what every trace message starts with, see the contract's trace.rs
+pub const TRACE_PREFIX: &str = "trace:";
+
+/// An event of the lock's `trace` feature, decoded from its debug message
+/// `trace:<name> key=value ...`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEvent {
+    pub name: String,
+    pub fields: Vec<(String, String)>,
+}
+
+impl TraceEvent {
+    /// `None` for other debug messages
+    pub fn decode(message: &str) -> Option<Self> {
+        let mut parts = message.strip_prefix(TRACE_PREFIX)?.split(' ');
+        let name = parts.next()?.to_string();
+        let fields = parts
+            .map(|field| {
+                let mut key_value = field.splitn(2, '=');
+                Some((key_value.next()?.to_string(), key_value.next()?.to_string()))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(TraceEvent { name, fields })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// the trace events captured in `context`, in the order they were sent
+pub fn trace_events(context: &Context) -> Vec<TraceEvent> {
+    context
+        .captured_messages()
+        .iter()
+        .filter_map(|message| TraceEvent::decode(&message.message))
+        .collect()
+}
+
+/// the hex form trace events give byte strings in
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -14,6 +14,10 @@ use std::str::FromStr;
 
 #[cfg(test)]
 mod tests;
+#[cfg(test)]
+mod native;
+#[cfg(test)]
+mod lib_build;
 mod helper;
 
 const TEST_ENV_VAR: &str = "CAPSULE_TEST_ENV";
@@ -0,0 +1,127 @@
+//! Unit tests of `ckb-lib-build`, the manifest check behind every
+//! `code_hashes.rs`, against binaries written to a scratch directory.
+use ckb_lib_build::{check_libraries, hash_file, parse_manifest, Library};
+use ckb_tool::ckb_hash::blake2b_256;
+use std::{env, fs, path::PathBuf, process};
+
+const BINARY: &[u8] = b"\x7fELF a dual-mode library";
+
+/// a fresh directory holding `lib.so` with `BINARY` in it
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("ckb-lib-build-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("scratch dir");
+    fs::write(dir.join("lib.so"), BINARY).expect("binary");
+    dir
+}
+
+fn hex(hash: &[u8; 32]) -> String {
+    hash.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn library(path: &str, hash: [u8; 32]) -> Library {
+    Library {
+        name: "LIB".to_string(),
+        path: path.to_string(),
+        hash,
+    }
+}
+
+#[test]
+fn test_parse_manifest() {
+    let hash = blake2b_256(BINARY);
+    let manifest = format!(
+        "# name  path  hash\n\n  # indented comment\nsecp256k1  ../build/lib  0x{}\nEth ../build/eth {}\n",
+        hex(&hash),
+        hex(&[0x11; 32])
+    );
+    let libraries = parse_manifest(&manifest).expect("manifest");
+    assert_eq!(
+        libraries,
+        vec![
+            Library {
+                name: "SECP256K1".to_string(),
+                path: "../build/lib".to_string(),
+                hash,
+            },
+            Library {
+                name: "ETH".to_string(),
+                path: "../build/eth".to_string(),
+                hash: [0x11; 32],
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_parse_manifest_refuses_malformed_lines() {
+    let short_hash = format!("LIB lib.so 0x{}", &hex(&[0u8; 32])[..62]);
+    let bad_hex = format!("LIB lib.so 0x{}zz", &hex(&[0u8; 32])[..62]);
+    let cases = [
+        ("LIB lib.so", "libraries.manifest:1: expected `name path hash`"),
+        ("# comment\nLIB lib.so 0x00 extra", "libraries.manifest:2: expected `name path hash`"),
+        (short_hash.as_str(), "libraries.manifest:1: the hash must be 32 bytes of hex"),
+        (bad_hex.as_str(), "libraries.manifest:1: invalid hex in the hash"),
+        // every library is pinned, there is no way to opt one out
+        ("LIB lib.so unpinned", "libraries.manifest:1: the hash must be 32 bytes of hex"),
+    ];
+    for (manifest, error) in cases.iter() {
+        assert_eq!(parse_manifest(manifest).as_ref(), Err(&error.to_string()), "{}", manifest);
+    }
+}
+
+#[test]
+fn test_check_libraries_matching_binary() {
+    let dir = scratch_dir("matching");
+    let hash = blake2b_256(BINARY);
+    assert_eq!(hash_file(&dir.join("lib.so")), hash);
+
+    let code_hashes = check_libraries(&dir, &[library("lib.so", hash)], false).expect("pinned");
+    assert_eq!(code_hashes.hashes, vec![("LIB".to_string(), hash)]);
+    assert!(code_hashes.warnings.is_empty());
+    assert_eq!(code_hashes.to_code(), format!("pub const CODE_HASH_LIB: [u8; 32] = {:?};\n", hash));
+}
+
+#[test]
+fn test_check_libraries_mismatch() {
+    let dir = scratch_dir("mismatch");
+    let actual = blake2b_256(BINARY);
+    let stale = [0x22; 32];
+
+    let err = check_libraries(&dir, &[library("lib.so", stale)], false).unwrap_err();
+    let expected = format!(
+        "LIB: binary lib.so hashes to 0x{}, but libraries.manifest expects 0x{}",
+        hex(&actual),
+        hex(&stale)
+    );
+    assert!(err.contains(&expected), "{}", err);
+    assert!(err.contains("CKB_LIB_SKIP_BINARY_CHECK=1"), "{}", err);
+}
+
+#[test]
+fn test_check_libraries_missing_binary() {
+    let dir = scratch_dir("missing");
+    let libraries = [library("lib.so", blake2b_256(BINARY)), library("gone.so", [0x33; 32])];
+
+    // every failing library is listed, not only the first
+    let err = check_libraries(&dir, &libraries, false).unwrap_err();
+    assert!(err.contains("LIB: binary gone.so is missing"), "{}", err);
+    assert!(!err.contains("lib.so"), "{}", err);
+}
+
+#[test]
+fn test_check_libraries_skip_check() {
+    let dir = scratch_dir("skip");
+    let stale = [0x22; 32];
+    let libraries = [library("lib.so", stale), library("gone.so", [0x33; 32])];
+
+    // the opt-out builds with the expected hashes and only warns
+    let code_hashes = check_libraries(&dir, &libraries, true).expect("skipped");
+    assert_eq!(
+        code_hashes.hashes,
+        vec![("LIB".to_string(), stale), ("LIB".to_string(), [0x33; 32])]
+    );
+    assert_eq!(code_hashes.warnings.len(), 2);
+    assert!(code_hashes.warnings[0].contains("hashes to"), "{:?}", code_hashes.warnings);
+    assert!(code_hashes.warnings[1].contains("is missing"), "{:?}", code_hashes.warnings);
+}
@@ -0,0 +1,883 @@
+//! Unit tests of the ownership rules, run natively against a mock
+//! transaction instead of through ckb-testtool.
+use ckb_dynamic_verifier::DynamicVerifier;
+use ckb_lib_eth::NativeEthVerifier;
+use ckb_lib_secp256k1::{
+    compress_pubkey, LibError, NativeVerifier, PrefilledData, StaticPrefilledData, Verifier, SECP256K1_DATA_SIZE,
+};
+use ckb_std::{ckb_constants::Source, error::SysError};
+use ckb_tool::ckb_types::H256;
+use dao_ownership_types::{
+    args::{LibraryLocator, LockArgs, ARGS_FLAG_LIBRARY, ARGS_VERSION_1, PUPPET_TYPE_HASH_LEN},
+    puppet::Lease,
+    witness::{
+        is_molecule, is_sighash_all, LockWitness, OtxRange, OtxRanges, AUTH_ETH, COMPACT_SIGNATURE_LEN, SIGHASH_ANYONECANPAY,
+        SIGHASH_OTX, SIGHASH_SINGLE, SIGNATURE_LEN,
+    },
+    Error as TypesError,
+};
+use nervos_dao_extended_ownership_script::{
+    action::{self, DAO_TYPE_HASH},
+    chain::{Chain, PuppetCell},
+    cooldown,
+    error::Error,
+    error_code::*,
+    lock_period,
+    puppet::Puppet,
+    rules,
+    witness::{Auth, MerkleProof},
+};
+
+use super::helper;
+
+const LOCK_HASH: [u8; 32] = [0x01; 32];
+const PUPPET_TYPE_HASH: [u8; 32] = [0x02; 32];
+
+#[derive(Clone)]
+struct MockCell {
+    lock_hash: [u8; 32],
+    type_hash: Option<[u8; 32]>,
+    data: Vec<u8>,
+    capacity: u64,
+    since: u64,
+    /// epoch of the block which created the cell, if its header is a dep
+    epoch: Option<u64>,
+}
+
+#[derive(Default)]
+struct MockChain {
+    inputs: Vec<MockCell>,
+    outputs: Vec<MockCell>,
+    cell_deps: Vec<MockCell>,
+    /// epoch of each header dep
+    header_deps: Vec<u64>,
+}
+
+impl MockChain {
+    fn cell(&self, index: usize, source: Source) -> Result<&MockCell, SysError> {
+        let cells = match source {
+            Source::Input => &self.inputs,
+            Source::Output => &self.outputs,
+            Source::CellDep => &self.cell_deps,
+            Source::GroupInput => {
+                return self
+                    .inputs
+                    .iter()
+                    .filter(|cell| cell.lock_hash == LOCK_HASH)
+                    .nth(index)
+                    .ok_or(SysError::IndexOutOfBound)
+            },
+            _ => return Err(SysError::IndexOutOfBound),
+        };
+        cells.get(index).ok_or(SysError::IndexOutOfBound)
+    }
+}
+
+impl Chain for MockChain {
+    fn script_hash(&self) -> Result<[u8; 32], SysError> {
+        Ok(LOCK_HASH)
+    }
+
+    fn cell_type_hash(&self, index: usize, source: Source) -> Result<Option<[u8; 32]>, SysError> {
+        Ok(self.cell(index, source)?.type_hash)
+    }
+
+    fn cell_lock_hash(&self, index: usize, source: Source) -> Result<[u8; 32], SysError> {
+        Ok(self.cell(index, source)?.lock_hash)
+    }
+
+    fn cell_data(&self, index: usize, source: Source) -> Result<Vec<u8>, SysError> {
+        Ok(self.cell(index, source)?.data.clone())
+    }
+
+    fn cell_capacity(&self, index: usize, source: Source) -> Result<u64, SysError> {
+        Ok(self.cell(index, source)?.capacity)
+    }
+
+    fn input_since(&self, index: usize, source: Source) -> Result<u64, SysError> {
+        Ok(self.cell(index, source)?.since)
+    }
+
+    fn input_epoch(&self, index: usize) -> Result<u64, SysError> {
+        self.cell(index, Source::Input)?.epoch.ok_or(SysError::ItemMissing)
+    }
+
+    fn header_dep_epoch(&self, index: usize) -> Result<u64, SysError> {
+        self.header_deps.get(index).copied().ok_or(SysError::IndexOutOfBound)
+    }
+}
+
+fn dao_cell(block_number: u64) -> MockCell {
+    MockCell {
+        lock_hash: LOCK_HASH,
+        type_hash: Some(DAO_TYPE_HASH),
+        data: block_number.to_le_bytes().to_vec(),
+        capacity: 500,
+        since: 0,
+        epoch: None,
+    }
+}
+
+fn puppet_cell(data: &[u8]) -> MockCell {
+    MockCell {
+        lock_hash: [0u8; 32],
+        type_hash: Some(PUPPET_TYPE_HASH),
+        data: data.to_vec(),
+        capacity: 0,
+        since: 0,
+        epoch: Some(0),
+    }
+}
+
+fn plain_cell() -> MockCell {
+    MockCell {
+        lock_hash: [0u8; 32],
+        type_hash: None,
+        data: Vec::new(),
+        capacity: 0,
+        since: 0,
+        epoch: None,
+    }
+}
+
+/// a cell locked by the owner's default secp256k1 lock
+fn owner_cell(puppet_data: &[u8]) -> MockCell {
+    let owner = Puppet::from_slice(puppet_data).expect("puppet").owner;
+    MockCell {
+        lock_hash: action::owner_lock_hash(&owner),
+        ..plain_cell()
+    }
+}
+
+/// a withdrawal of one DAO cell paid to the owner, the puppet at input 1
+/// kept as it is
+fn phase2_chain(puppet_data: &[u8]) -> MockChain {
+    MockChain {
+        inputs: vec![dao_cell(100), puppet_cell(puppet_data)],
+        outputs: vec![owner_cell(puppet_data), puppet_cell(puppet_data)],
+        cell_deps: Vec::new(),
+        header_deps: Vec::new(),
+    }
+}
+
+fn authorize(chain: &MockChain, signer: &[u8; 20], is_owner: bool) -> Result<(), Error> {
+    let puppet = Puppet::from_slice(&chain.inputs[1].data)?;
+    rules::authorize(chain, &PUPPET_TYPE_HASH, PuppetCell::input(1), &puppet, signer, is_owner)
+}
+
+#[test]
+fn test_owner_withdraws() {
+    let owner = [0x11; 20];
+    let chain = phase2_chain(&helper::puppet_data(&owner, &[]));
+    assert!(authorize(&chain, &owner, true).is_ok());
+}
+
+#[test]
+fn test_operator_scope() {
+    let owner = [0x11; 20];
+    let operator = [0x22; 20];
+    let chain = phase2_chain(&helper::puppet_data(&owner, &[(operator, helper::SCOPE_PHASE1)]));
+    assert!(matches!(authorize(&chain, &operator, false), Err(Error::OperatorPhase2Denied)));
+
+    let chain = phase2_chain(&helper::puppet_data(&owner, &[(operator, helper::SCOPE_PHASE2)]));
+    assert!(authorize(&chain, &operator, false).is_ok());
+}
+
+#[test]
+fn test_operator_payout() {
+    let owner = [0x11; 20];
+    let operator = [0x22; 20];
+    let puppet_data = helper::puppet_data(&owner, &[(operator, helper::SCOPE_PHASE2)]);
+    let mut chain = phase2_chain(&puppet_data);
+    chain.outputs.push(plain_cell());
+    assert!(matches!(authorize(&chain, &operator, false), Err(Error::PayoutLockMismatch)));
+    // the owner may send its withdrawal anywhere
+    assert!(authorize(&chain, &owner, true).is_ok());
+
+    // a payout record replaces the owner's lock
+    let puppet_data = helper::push_payout_lock(puppet_data, &[0x44; 32]);
+    let mut chain = phase2_chain(&puppet_data);
+    assert!(matches!(authorize(&chain, &operator, false), Err(Error::PayoutLockMismatch)));
+    chain.outputs[0].lock_hash = [0x44; 32];
+    assert!(authorize(&chain, &operator, false).is_ok());
+}
+
+#[test]
+fn test_operator_needs_dao_input() {
+    let owner = [0x11; 20];
+    let operator = [0x22; 20];
+    let puppet_data = helper::puppet_data(&owner, &[(operator, helper::SCOPE_PHASE1 | helper::SCOPE_PHASE2)]);
+    // looks like a withdrawing cell, but isn't a nervosDAO cell
+    let mut chain = phase2_chain(&puppet_data);
+    chain.inputs[0].type_hash = Some([0x03; 32]);
+    assert!(matches!(authorize(&chain, &operator, false), Err(Error::OperatorActionDenied)));
+}
+
+#[test]
+fn test_stranger_refused() {
+    let owner = [0x11; 20];
+    let chain = phase2_chain(&helper::puppet_data(&owner, &[]));
+    assert!(matches!(authorize(&chain, &[0x33; 20], false), Err(Error::WrongPublicKey)));
+}
+
+#[test]
+fn test_operator_cannot_transfer() {
+    let owner = [0x11; 20];
+    let operator = [0x22; 20];
+    let puppet_data = helper::puppet_data(&owner, &[(operator, helper::SCOPE_PHASE1 | helper::SCOPE_PHASE2)]);
+    let mut chain = phase2_chain(&puppet_data);
+    chain.outputs[1] = puppet_cell(&helper::puppet_data(&operator, &[]));
+    assert!(matches!(authorize(&chain, &operator, false), Err(Error::OperatorTransferDenied)));
+}
+
+#[test]
+fn test_cooldown_elapsed() {
+    let owner = [0x11; 20];
+    let previous_owner = [0x22; 20];
+    let puppet_data = helper::push_cooldown(helper::puppet_data(&owner, &[]), 6, 10, &previous_owner);
+    let mut chain = phase2_chain(&puppet_data);
+    assert!(matches!(authorize(&chain, &owner, true), Err(Error::CooldownActive)));
+
+    chain.inputs[1].since = helper::since_relative_epoch(6);
+    assert!(authorize(&chain, &owner, true).is_ok());
+}
+
+#[test]
+fn test_cooldown_cancel_keeps_dao_cells() {
+    let owner = [0x11; 20];
+    let previous_owner = [0x22; 20];
+    let puppet_data = helper::push_cooldown(helper::puppet_data(&owner, &[]), 6, 10, &previous_owner);
+    let restored = helper::push_cooldown(helper::puppet_data(&previous_owner, &[]), 6, 0, &[0u8; 20]);
+    let mut chain = MockChain {
+        inputs: vec![dao_cell(0), puppet_cell(&puppet_data)],
+        outputs: vec![dao_cell(0), puppet_cell(&restored)],
+        cell_deps: Vec::new(),
+        header_deps: Vec::new(),
+    };
+    assert!(authorize(&chain, &previous_owner, false).is_ok());
+    // a puppet under the guard lock, this very lock, isn't carried over
+    chain.inputs[1].lock_hash = LOCK_HASH;
+    assert!(authorize(&chain, &previous_owner, false).is_ok());
+
+    // the previous owner takes the puppet back and some of the deposit
+    chain.outputs[0].capacity = 400;
+    assert!(matches!(authorize(&chain, &previous_owner, false), Err(Error::CooldownCancelOnly)));
+    chain.outputs[0] = plain_cell();
+    assert!(matches!(authorize(&chain, &previous_owner, false), Err(Error::CooldownCancelOnly)));
+}
+
+#[test]
+fn test_cooldown_update_rules() {
+    let owner = [0x11; 20];
+    let previous_owner = [0x22; 20];
+    let buyer = [0x33; 20];
+    let with_cooldown = |owner: &[u8; 20], epochs, transfer_epoch, previous: &[u8; 20]| {
+        Puppet::from_slice(&helper::push_cooldown(helper::puppet_data(owner, &[]), epochs, transfer_epoch, previous))
+            .expect("puppet")
+    };
+    let settled = with_cooldown(&owner, 6, 0, &[0u8; 20]);
+    let pending = with_cooldown(&owner, 6, 10, &previous_owner);
+    let elapsed = helper::since_relative_epoch(6);
+    let transfer_epoch = helper::since_absolute_epoch(20);
+    // the puppet input was last written in epoch 15
+    let created = || Ok::<u64, SysError>(15);
+
+    // hand over, stamping the transfer
+    let handed = with_cooldown(&buyer, 6, 20, &owner);
+    assert!(cooldown::check_update(transfer_epoch, &settled, Some(&handed), created).is_ok());
+    assert!(matches!(
+        cooldown::check_update(transfer_epoch, &settled, Some(&with_cooldown(&buyer, 6, 19, &owner)), created),
+        Err(Error::CooldownRecordMismatch)
+    ));
+    // the stamp can't predate the puppet input, which needs its header
+    assert!(matches!(
+        cooldown::check_update(transfer_epoch, &settled, Some(&handed), || Ok(21)),
+        Err(Error::CooldownRecordMismatch)
+    ));
+    assert!(matches!(
+        cooldown::check_update(transfer_epoch, &settled, Some(&handed), || Err(SysError::IndexOutOfBound)),
+        Err(Error::CooldownRecordMismatch)
+    ));
+    // the record can't be dropped or shortened
+    assert!(matches!(
+        cooldown::check_update(transfer_epoch, &settled, Some(&Puppet::new(buyer)), created),
+        Err(Error::CooldownRecordMismatch)
+    ));
+    assert!(matches!(
+        cooldown::check_update(transfer_epoch, &settled, Some(&with_cooldown(&buyer, 5, 20, &owner)), created),
+        Err(Error::CooldownRecordMismatch)
+    ));
+
+    // a pending transfer can't be handed on, even once the cooldown passed
+    let rehanded = with_cooldown(&buyer, 6, 20, &owner);
+    assert!(matches!(
+        cooldown::check_update(transfer_epoch, &pending, Some(&rehanded), created),
+        Err(Error::CooldownActive)
+    ));
+    assert!(matches!(cooldown::check_update(elapsed, &pending, Some(&rehanded), created), Err(Error::CooldownActive)));
+    // but it can go back to the previous owner, and be settled or destroyed
+    // once the cooldown passed
+    let cancelled = with_cooldown(&previous_owner, 6, 0, &[0u8; 20]);
+    assert!(cooldown::check_update(0, &pending, Some(&cancelled), created).is_ok());
+    assert!(matches!(cooldown::check_update(0, &pending, Some(&settled), created), Err(Error::CooldownActive)));
+    assert!(cooldown::check_update(elapsed, &pending, Some(&settled), created).is_ok());
+    assert!(matches!(cooldown::check_update(0, &pending, None, created), Err(Error::CooldownActive)));
+    assert!(cooldown::check_update(elapsed, &pending, None, created).is_ok());
+    // left pending, the record can't be rewritten
+    assert!(cooldown::check_update(0, &pending, Some(&pending), created).is_ok());
+    assert!(matches!(
+        cooldown::check_update(0, &pending, Some(&with_cooldown(&owner, 6, 10, &buyer)), created),
+        Err(Error::CooldownRecordMismatch)
+    ));
+}
+
+#[test]
+fn test_lease_expiry() {
+    let owner = [0x11; 20];
+    let lessee = [0x22; 20];
+    let puppet_data = helper::push_lease(helper::puppet_data(&owner, &[]), &lessee, 50, helper::SCOPE_PHASE2);
+    let mut chain = phase2_chain(&puppet_data);
+    chain.inputs[1].epoch = Some(40);
+    chain.inputs[1].since = helper::since_absolute_epoch(45);
+    assert!(authorize(&chain, &lessee, false).is_ok());
+    assert!(matches!(authorize(&chain, &owner, true), Err(Error::LeaseActive)));
+
+    chain.inputs[1].since = helper::since_absolute_epoch(50);
+    assert!(matches!(authorize(&chain, &lessee, false), Err(Error::LeaseExpired)));
+    assert!(authorize(&chain, &owner, true).is_ok());
+}
+
+#[test]
+fn test_lessee_proves_lease_running() {
+    let owner = [0x11; 20];
+    let lessee = [0x22; 20];
+    let puppet_data = helper::push_lease(helper::puppet_data(&owner, &[]), &lessee, 50, helper::SCOPE_PHASE2);
+    let mut chain = phase2_chain(&puppet_data);
+    chain.inputs[1].epoch = Some(40);
+
+    // no since, or a relative one, says nothing about the lease
+    assert!(matches!(authorize(&chain, &lessee, false), Err(Error::LeaseExpired)));
+    chain.inputs[1].since = helper::since_relative_epoch(1);
+    assert!(matches!(authorize(&chain, &lessee, false), Err(Error::LeaseExpired)));
+    // a since older than the puppet itself
+    chain.inputs[1].since = helper::since_absolute_epoch(39);
+    assert!(matches!(authorize(&chain, &lessee, false), Err(Error::LeaseExpired)));
+    // without the puppet's header the since can't be checked
+    chain.inputs[1].since = helper::since_absolute_epoch(40);
+    assert!(authorize(&chain, &lessee, false).is_ok());
+    chain.inputs[1].epoch = None;
+    assert!(matches!(authorize(&chain, &lessee, false), Err(Error::LeaseExpired)));
+
+    // a header dep from the expiry on proves the lease is over, whatever the since
+    chain.inputs[1].epoch = Some(40);
+    chain.header_deps = vec![40, 49];
+    assert!(authorize(&chain, &lessee, false).is_ok());
+    chain.header_deps.push(50);
+    assert!(matches!(authorize(&chain, &lessee, false), Err(Error::LeaseExpired)));
+}
+
+#[test]
+fn test_merkle_duplicate_index() {
+    let members = [[0x11; 20], [0x22; 20], [0x33; 20]];
+    let (root, siblings) = helper::merkle_proof(&members, 2);
+    let puppet_data = helper::push_merkle_root(helper::puppet_data(&[0u8; 20], &[]), &root);
+    let puppet = Puppet::from_slice(&puppet_data).expect("puppet");
+    let proof = |index, siblings: &Vec<[u8; 32]>| {
+        Auth::Merkle(MerkleProof {
+            leaf: members[2],
+            index,
+            siblings: siblings.clone(),
+        })
+    };
+    assert!(matches!(rules::is_owner(&puppet, &members[2], &proof(2, &siblings), None), Ok(true)));
+    // the odd leaf out is paired with padding, it has no second index
+    assert!(matches!(
+        rules::is_owner(&puppet, &members[2], &proof(3, &siblings), None),
+        Err(Error::MerkleProofInvalid)
+    ));
+
+    // a tree pairing the odd leaf with itself would give it two indexes
+    let leaves: Vec<[u8; 32]> = members.iter().map(helper::merkle_leaf).collect();
+    let left = helper::merkle_node(&leaves[0], &leaves[1]);
+    let self_root = helper::merkle_node(&left, &helper::merkle_node(&leaves[2], &leaves[2]));
+    let puppet_data = helper::push_merkle_root(helper::puppet_data(&[0u8; 20], &[]), &self_root);
+    let puppet = Puppet::from_slice(&puppet_data).expect("puppet");
+    let self_siblings = vec![leaves[2], left];
+    for index in 2..4 {
+        assert!(matches!(
+            rules::is_owner(&puppet, &members[2], &proof(index, &self_siblings), None),
+            Err(Error::MerkleProofInvalid)
+        ));
+    }
+}
+
+#[test]
+fn test_merkle_member_is_owner() {
+    let members = [[0x11; 20], [0x22; 20], [0x33; 20]];
+    let (root, siblings) = helper::merkle_proof(&members, 1);
+    let puppet_data = helper::push_merkle_root(helper::puppet_data(&[0u8; 20], &[]), &root);
+    let puppet = Puppet::from_slice(&puppet_data).expect("puppet");
+
+    let auth = Auth::Merkle(MerkleProof {
+        leaf: members[1],
+        index: 1,
+        siblings,
+    });
+    assert!(matches!(rules::is_owner(&puppet, &members[1], &auth, None), Ok(true)));
+    assert!(matches!(
+        rules::is_owner(&puppet, &members[2], &auth, None),
+        Err(Error::MerkleProofInvalid)
+    ));
+}
+
+#[test]
+fn test_native_verifier_recovers_signer() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let message = [0x42u8; 32];
+    let signature = privkey
+        .sign_recoverable(&H256::from(message))
+        .expect("sign")
+        .serialize();
+
+    let verifier = NativeVerifier::new();
+    let recovered = verifier.recover_pubkey(&signature, &message).expect("recover");
+    assert_eq!(&recovered[..], &pubkey.serialize()[..]);
+
+    let mut sig = [0u8; 65];
+    sig.copy_from_slice(&signature);
+    let verifier = NativeVerifier::with_sighash_all(sig, message);
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+    assert!(verifier.validate_blake2b_sighash_all(&pubkey_hash).is_ok());
+    assert_eq!(
+        verifier.validate_blake2b_sighash_all(&[0u8; 20]),
+        Err(LibError::PubkeyBlake160Hash)
+    );
+}
+
+#[test]
+fn test_native_verifier_compact_signature() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let message = [0x42u8; 32];
+    let signature = privkey
+        .sign_recoverable(&H256::from(message))
+        .expect("sign")
+        .serialize();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let verifier = NativeVerifier::new();
+    let compressed = pubkey.serialize();
+    let uncompressed = helper::uncompressed_pubkey(&pubkey);
+    for key in [&compressed[..], &uncompressed[..]].iter() {
+        let verified = verifier.verify(key, &signature[..64], &message).expect("verify");
+        assert_eq!(helper::blake160(&verified), pubkey_hash);
+        // the on-chain verifier compares recovered keys in compressed form
+        assert_eq!(helper::blake160(&compress_pubkey(key).expect("compress")), pubkey_hash);
+    }
+
+    let (_, other) = helper::generate_key_pair();
+    assert_eq!(
+        verifier.verify(&other.serialize(), &signature[..64], &message),
+        Err(LibError::SecpVerification)
+    );
+    assert_eq!(
+        verifier.verify(&compressed, &signature, &message),
+        Err(LibError::InvalidSignatureSize)
+    );
+    assert_eq!(compress_pubkey(&uncompressed[..33]), Err(LibError::SecpParsePubkey));
+}
+
+#[test]
+fn test_dynamic_verifiers() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let message = [0x42u8; 32];
+    let signature = privkey
+        .sign_recoverable(&H256::from(message))
+        .expect("sign")
+        .serialize();
+
+    // one signature, one identity per scheme
+    let secp256k1 = NativeVerifier::new();
+    assert_eq!(
+        secp256k1.verify_signer(&signature, &message).expect("verify"),
+        helper::blake160(&pubkey.serialize())
+    );
+    let eth = NativeEthVerifier::new();
+    assert_eq!(
+        eth.verify_signer(&signature, &message).expect("verify"),
+        helper::eth_address(&pubkey)
+    );
+
+    // Ethereum wallets add 27 to the recovery id
+    let mut eth_signature = signature.clone();
+    eth_signature[64] += 27;
+    assert_eq!(
+        eth.verify_signer(&eth_signature, &message).expect("verify"),
+        helper::eth_address(&pubkey)
+    );
+}
+
+#[test]
+fn test_types_round_trip() {
+    let owner = [0x11; 20];
+    let mut puppet = Puppet::from_slice(&helper::puppet_data(&owner, &[([0x22; 20], helper::SCOPE_PHASE2)])).expect("puppet");
+    puppet.lease = Some(Lease {
+        lessee: [0x33; 20],
+        expiry_epoch: 50,
+        scope: helper::SCOPE_PHASE1,
+    });
+    puppet.merkle_root = Some([0x44; 32]);
+    puppet.payout_lock = Some([0x45; 32]);
+    assert_eq!(Puppet::from_slice(&puppet.to_vec()), Ok(puppet));
+    // no records, the legacy layout
+    assert_eq!(Puppet::new(owner).to_vec(), owner.to_vec());
+
+    let range = |start, count| OtxRange { start, count };
+    let mut lock_witness = LockWitness::new([0x55; SIGNATURE_LEN]);
+    // never a bare signature, which the lock takes for a sighash-all one
+    let lock = lock_witness.to_vec().expect("lock witness");
+    assert_eq!(lock.len(), SIGNATURE_LEN + 1);
+    assert!(!is_sighash_all(&lock));
+    assert!(is_sighash_all(&lock[..SIGNATURE_LEN]));
+    lock_witness.sighash = SIGHASH_OTX;
+    lock_witness.puppet_index = Some(3);
+    lock_witness.otx = Some(OtxRanges {
+        inputs: range(0, 2),
+        outputs: range(1, 1),
+        cell_deps: range(0, 4),
+        header_deps: range(0, 1),
+    });
+    lock_witness.auth = Auth::Merkle(MerkleProof {
+        leaf: owner,
+        index: 1,
+        siblings: vec![[0x66; 32]],
+    });
+    assert_eq!(LockWitness::from_slice(&lock_witness.to_vec().expect("lock witness")), Ok(lock_witness));
+
+    let type_hash = [0x77; 32];
+    for library in vec![
+        LibraryLocator::Default,
+        LibraryLocator::TypeHash(type_hash),
+        LibraryLocator::CellDep { type_hash, index: 2 },
+    ] {
+        let args = LockArgs { library, ..LockArgs::new(PUPPET_TYPE_HASH) };
+        assert_eq!(LockArgs::from_slice(&args.to_vec()), Ok(args));
+    }
+    assert_eq!(LockArgs::from_slice(&[0u8; 31]), Err(TypesError::InvalidArgs));
+    assert_eq!(LockArgs::from_slice(&[&PUPPET_TYPE_HASH[..], &[0x03]].concat()), Err(TypesError::InvalidArgs));
+}
+
+#[test]
+fn test_args_every_flag_combination() {
+    // the legacy 32-byte form
+    assert_eq!(LockArgs::from_slice(&PUPPET_TYPE_HASH), Ok(LockArgs::new(PUPPET_TYPE_HASH)));
+
+    let type_hash = [0x77; 32];
+    for flags in 0u8..16 {
+        let args = LockArgs {
+            puppet_type_hash: PUPPET_TYPE_HASH,
+            library: if flags & ARGS_FLAG_LIBRARY != 0 {
+                LibraryLocator::CellDep { type_hash, index: 2 }
+            } else {
+                LibraryLocator::Default
+            },
+            cell_dep_proof: flags & 0x02 != 0,
+            auth_kind: if flags & 0x04 != 0 { Some(AUTH_ETH) } else { None },
+            lock_period: if flags & 0x08 != 0 { Some(180) } else { None },
+        };
+        let bytes = args.to_vec();
+        assert_eq!(LockArgs::from_slice(&bytes), Ok(args.clone()), "flags {:#x}", flags);
+
+        // nothing but the locator keeps the legacy form
+        if flags & !ARGS_FLAG_LIBRARY == 0 {
+            assert!(bytes.len() == PUPPET_TYPE_HASH_LEN || bytes[PUPPET_TYPE_HASH_LEN] < ARGS_VERSION_1);
+            continue;
+        }
+        assert_eq!(bytes[PUPPET_TYPE_HASH_LEN], ARGS_VERSION_1 | flags);
+        // truncated or followed by anything, the args are refused
+        assert_eq!(LockArgs::from_slice(&bytes[..bytes.len() - 1]), Err(TypesError::InvalidArgs));
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert_eq!(LockArgs::from_slice(&longer), Err(TypesError::InvalidArgs));
+    }
+
+    let versioned = |rest: &[u8]| LockArgs::from_slice(&[&PUPPET_TYPE_HASH[..], rest].concat());
+    // an unknown version, an auth kind with flag bits, a locator of no kind
+    assert_eq!(versioned(&[0x20]), Err(TypesError::InvalidArgs));
+    assert_eq!(versioned(&[ARGS_VERSION_1 | 0x04, 0x13]), Err(TypesError::InvalidArgs));
+    assert_eq!(versioned(&[ARGS_VERSION_1 | 0x01, 0x03]), Err(TypesError::InvalidArgs));
+}
+
+fn deposit_chain(since: u64) -> MockChain {
+    let mut deposit = dao_cell(0);
+    deposit.since = since;
+    MockChain {
+        inputs: vec![deposit, dao_cell(100)],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_lock_period() {
+    assert!(matches!(lock_period::check(&deposit_chain(0), 180), Err(Error::LockPeriodActive)));
+    assert!(matches!(
+        lock_period::check(&deposit_chain(helper::since_relative_epoch(179)), 180),
+        Err(Error::LockPeriodActive)
+    ));
+    // an absolute since says nothing about the deposit's age
+    assert!(matches!(
+        lock_period::check(&deposit_chain(helper::since_absolute_epoch(1000)), 180),
+        Err(Error::LockPeriodActive)
+    ));
+    // the withdrawing cell at input 1 isn't held back
+    assert!(lock_period::check(&deposit_chain(helper::since_relative_epoch(180)), 180).is_ok());
+
+    // another lock's deposit in front of the group is none of its business
+    let mut chain = deposit_chain(helper::since_relative_epoch(180));
+    chain.inputs.insert(0, MockCell { lock_hash: [0u8; 32], ..dao_cell(0) });
+    assert!(lock_period::check(&chain, 180).is_ok());
+}
+
+#[test]
+fn test_cell_dep_puppet_proves_no_epoch() {
+    let owner = [0x11; 20];
+    let lessee = [0x22; 20];
+    let puppet_data = helper::push_lease(helper::puppet_data(&owner, &[]), &lessee, 50, helper::SCOPE_PHASE2);
+    let puppet = Puppet::from_slice(&puppet_data).expect("puppet");
+    let mut dep = puppet_cell(&puppet_data);
+    dep.since = helper::since_absolute_epoch(50);
+    let chain = MockChain {
+        inputs: vec![dao_cell(100)],
+        outputs: vec![owner_cell(&puppet_data)],
+        cell_deps: vec![dep],
+        header_deps: Vec::new(),
+    };
+    let puppet_cell = PuppetCell { index: 0, source: Source::CellDep };
+
+    // the lessor can't prove the lease is over without spending the puppet
+    assert!(matches!(
+        rules::authorize(&chain, &PUPPET_TYPE_HASH, puppet_cell, &puppet, &owner, true),
+        Err(Error::LeaseActive)
+    ));
+    // nor can the lessee prove it is running, it is refused outright
+    assert!(matches!(
+        rules::authorize(&chain, &PUPPET_TYPE_HASH, puppet_cell, &puppet, &lessee, false),
+        Err(Error::CellDepProofDenied)
+    ));
+
+    // nor can anyone show a pending cooldown has elapsed
+    let puppet_data = helper::push_cooldown(helper::puppet_data(&owner, &[]), 10, 0, &lessee);
+    let puppet = Puppet::from_slice(&puppet_data).expect("puppet");
+    let mut dep = puppet_cell(&puppet_data);
+    dep.since = helper::since_absolute_epoch(1000);
+    let chain = MockChain {
+        inputs: vec![dao_cell(100)],
+        outputs: vec![owner_cell(&puppet_data)],
+        cell_deps: vec![dep],
+        header_deps: Vec::new(),
+    };
+    assert!(matches!(
+        rules::authorize(&chain, &PUPPET_TYPE_HASH, puppet_cell, &puppet, &owner, true),
+        Err(Error::CellDepProofDenied)
+    ));
+}
+
+#[test]
+fn test_molecule_witness_round_trip() {
+    let signature = [0x55; SIGNATURE_LEN];
+    let mut merkle = LockWitness::new(signature);
+    merkle.puppet_index = Some(7);
+    merkle.auth = Auth::Merkle(MerkleProof {
+        leaf: [0x11; 20],
+        index: 5,
+        siblings: vec![[0x66; 32], [0x77; 32]],
+    });
+    let mut otx = LockWitness::new(signature);
+    otx.sighash = SIGHASH_OTX;
+    otx.otx = Some(OtxRanges::from_slice(&[0x01; 32]));
+    otx.auth = Auth::Commitment([0x22; 32]);
+    let mut compact = LockWitness::new(signature);
+    compact.signature[COMPACT_SIGNATURE_LEN] = 0;
+    compact.sighash = SIGHASH_SINGLE | SIGHASH_ANYONECANPAY;
+    compact.pubkey = Some([&[0x02][..], &[0x33; 32][..]].concat());
+    let mut rsa = LockWitness::new([0u8; SIGNATURE_LEN]);
+    rsa.auth = Auth::Rsa(vec![0x44; 520]);
+    let mut eth = LockWitness::new(signature);
+    eth.auth = Auth::Eth;
+
+    for lock_witness in vec![LockWitness::new(signature), merkle, otx, compact, rsa, eth] {
+        let molecule = lock_witness.to_molecule();
+        let compact = lock_witness.to_vec().expect("lock witness");
+        assert!(is_molecule(&molecule));
+        assert!(!is_molecule(&compact));
+        assert_eq!(LockWitness::from_slice(&molecule), Ok(lock_witness.clone()));
+        assert_eq!(LockWitness::from_slice(&compact), Ok(lock_witness));
+    }
+
+    // a signature starting with the witness length, as a table would, is
+    // still compact: only the flags byte after it announces molecule
+    let mut lookalike = LockWitness::new(signature);
+    lookalike.auth = Auth::Commitment([0x22; 32]);
+    let len = lookalike.to_vec().expect("lock witness").len() as u32;
+    lookalike.signature[..4].copy_from_slice(&len.to_le_bytes());
+    let compact = lookalike.to_vec().expect("lock witness");
+    assert!(!is_molecule(&compact));
+    assert_eq!(LockWitness::from_slice(&compact), Ok(lookalike));
+    // and the bare signature is told apart by its length
+    assert!(!is_molecule(&signature));
+
+    // the compact layout counts siblings in a byte, molecule has no limit
+    let mut deep = LockWitness::new(signature);
+    deep.auth = Auth::Merkle(MerkleProof { leaf: [0x11; 20], index: 0, siblings: vec![[0x66; 32]; 256] });
+    assert_eq!(deep.to_vec(), Err(TypesError::InvalidAuthPayload));
+    assert_eq!(LockWitness::from_slice(&deep.to_molecule()), Ok(deep.clone()));
+    if let Auth::Merkle(ref mut proof) = deep.auth {
+        proof.siblings.pop();
+    }
+    assert_eq!(LockWitness::from_slice(&deep.to_vec().expect("lock witness")), Ok(deep));
+
+    // malformed witnesses get their own errors
+    assert_eq!(LockWitness::from_slice(&[0x55; 64]), Err(TypesError::WitnessTooShort));
+    let mut padded = LockWitness::new(signature);
+    padded.pubkey = Some(vec![0x02; 33]);
+    assert_eq!(LockWitness::from_slice(&padded.to_molecule()), Err(TypesError::InvalidWitnessPubkey));
+    assert_eq!(LockWitness::from_slice(&padded.to_vec().expect("lock witness")), Err(TypesError::InvalidWitnessPubkey));
+    let eth_payload = [&signature[..], &[AUTH_ETH, 0]].concat();
+    assert_eq!(LockWitness::from_slice(&eth_payload), Err(TypesError::InvalidAuthPayload));
+    let mut otx_missing = LockWitness::new(signature);
+    otx_missing.sighash = SIGHASH_OTX;
+    assert_eq!(LockWitness::from_slice(&otx_missing.to_molecule()), Err(TypesError::WitnessMolecule));
+}
+
+/// stands in for the library's `load_prefilled_data`, which writes its table
+unsafe extern "C" fn fake_load_prefilled_data(data: *mut u8, len: *mut u64) -> i32 {
+    if *len != SECP256K1_DATA_SIZE as u64 {
+        return -41;
+    }
+    for i in 0..SECP256K1_DATA_SIZE {
+        *data.add(i) = i as u8;
+    }
+    0
+}
+
+unsafe extern "C" fn failing_load_prefilled_data(_data: *mut u8, _len: *mut u64) -> i32 {
+    -41
+}
+
+#[test]
+fn test_prefilled_data_static_buffer() {
+    static BUFFER: StaticPrefilledData = StaticPrefilledData::new();
+
+    let buffer = BUFFER.take().expect("first take");
+    let data = PrefilledData::in_buffer(buffer)
+        .fill_with(fake_load_prefilled_data)
+        .expect("filled");
+    assert_eq!(data.as_slice().len(), SECP256K1_DATA_SIZE);
+    assert!(data.as_slice().iter().enumerate().all(|(i, b)| *b == i as u8));
+
+    // handed out once, a second verifier can't get the same buffer
+    assert_eq!(BUFFER.take().err(), Some(LibError::AllocationFailed));
+    assert_eq!(Error::from(LibError::AllocationFailed) as i8, ERROR_PREFILLED_DATA_ALLOCATION);
+}
+
+#[test]
+fn test_prefilled_data_load_error() {
+    static BUFFER: StaticPrefilledData = StaticPrefilledData::new();
+
+    let buffer = BUFFER.take().expect("first take");
+    let result = PrefilledData::in_buffer(buffer).fill_with(failing_load_prefilled_data);
+    assert_eq!(result.err(), Some(LibError::InvalidPrefilledDataSize));
+}
+
+#[test]
+fn test_prefilled_data_allocation() {
+    unsafe fn no_memory(_layout: std::alloc::Layout) -> *mut u8 {
+        std::ptr::null_mut()
+    }
+    assert_eq!(PrefilledData::on_heap_with(no_memory).err(), Some(LibError::AllocationFailed));
+
+    let data = PrefilledData::on_heap()
+        .expect("allocated")
+        .fill_with(fake_load_prefilled_data)
+        .expect("filled");
+    assert_eq!(data.as_slice()[SECP256K1_DATA_SIZE - 1], (SECP256K1_DATA_SIZE - 1) as u8);
+}
+
+#[test]
+fn test_unknown_sys_error() {
+    // used to panic, it exits with its own code now
+    assert_eq!(Error::from(SysError::Unknown(42)) as i8, ERROR_UNKNOWN_SYS_ERROR);
+    assert_eq!(Error::from(SysError::Encoding) as i8, ERROR_ENCODING);
+}
+
+#[test]
+fn test_error_codes_stable_and_documented() {
+    let codes = [
+        ("ERROR_INDEX_OUT_OF_BOUND", ERROR_INDEX_OUT_OF_BOUND),
+        ("ERROR_ITEM_MISSING", ERROR_ITEM_MISSING),
+        ("ERROR_LENGTH_NOT_ENOUGH", ERROR_LENGTH_NOT_ENOUGH),
+        ("ERROR_ENCODING", ERROR_ENCODING),
+        ("ERROR_NO_VALID_ARGS", ERROR_NO_VALID_ARGS),
+        ("ERROR_NO_PUPPET_CELL_FOUND", ERROR_NO_PUPPET_CELL_FOUND),
+        ("ERROR_REQUIRED_ONLY_ONE_PUPPET", ERROR_REQUIRED_ONLY_ONE_PUPPET),
+        ("ERROR_LOAD_PREFILLED_DATA", ERROR_LOAD_PREFILLED_DATA),
+        ("ERROR_RECOVER_PUBKEY", ERROR_RECOVER_PUBKEY),
+        ("ERROR_WRONG_PUBLIC_KEY", ERROR_WRONG_PUBLIC_KEY),
+        ("ERROR_WRONG_PUBKEY_HASH_LENGTH", ERROR_WRONG_PUBKEY_HASH_LENGTH),
+        ("ERROR_LOAD_WITNESS_ARGS", ERROR_LOAD_WITNESS_ARGS),
+        ("ERROR_WITNESS_ARGS_ENCODING", ERROR_WITNESS_ARGS_ENCODING),
+        ("ERROR_INVALID_PUPPET_DATA", ERROR_INVALID_PUPPET_DATA),
+        ("ERROR_OPERATOR_PHASE1_DENIED", ERROR_OPERATOR_PHASE1_DENIED),
+        ("ERROR_OPERATOR_PHASE2_DENIED", ERROR_OPERATOR_PHASE2_DENIED),
+        ("ERROR_OPERATOR_TRANSFER_DENIED", ERROR_OPERATOR_TRANSFER_DENIED),
+        ("ERROR_OPERATOR_ACTION_DENIED", ERROR_OPERATOR_ACTION_DENIED),
+        ("ERROR_COOLDOWN_ACTIVE", ERROR_COOLDOWN_ACTIVE),
+        ("ERROR_COOLDOWN_CANCEL_ONLY", ERROR_COOLDOWN_CANCEL_ONLY),
+        ("ERROR_COOLDOWN_RECORD_MISMATCH", ERROR_COOLDOWN_RECORD_MISMATCH),
+        ("ERROR_LEASE_ACTIVE", ERROR_LEASE_ACTIVE),
+        ("ERROR_LEASE_EXPIRED", ERROR_LEASE_EXPIRED),
+        ("ERROR_UNKNOWN_AUTH_KIND", ERROR_UNKNOWN_AUTH_KIND),
+        ("ERROR_MERKLE_PROOF_INVALID", ERROR_MERKLE_PROOF_INVALID),
+        ("ERROR_COMMITMENT_MISMATCH", ERROR_COMMITMENT_MISMATCH),
+        ("ERROR_GENESIS_HEADER_MISSING", ERROR_GENESIS_HEADER_MISSING),
+        ("ERROR_UNKNOWN_SIGHASH_MODE", ERROR_UNKNOWN_SIGHASH_MODE),
+        ("ERROR_SIGHASH_SINGLE_MISSING_OUTPUT", ERROR_SIGHASH_SINGLE_MISSING_OUTPUT),
+        ("ERROR_OTX_RANGE_OUT_OF_BOUND", ERROR_OTX_RANGE_OUT_OF_BOUND),
+        ("ERROR_OTX_GROUP_NOT_COVERED", ERROR_OTX_GROUP_NOT_COVERED),
+        ("ERROR_PUPPET_INDEX_MISMATCH", ERROR_PUPPET_INDEX_MISMATCH),
+        ("ERROR_LIBRARY_NOT_FOUND", ERROR_LIBRARY_NOT_FOUND),
+        ("ERROR_LIBRARY_SYMBOL_MISSING", ERROR_LIBRARY_SYMBOL_MISSING),
+        ("ERROR_LIBRARY_MEMORY_NOT_ENOUGH", ERROR_LIBRARY_MEMORY_NOT_ENOUGH),
+        ("ERROR_LIBRARY_INVALID", ERROR_LIBRARY_INVALID),
+        ("ERROR_SECP_PARSE_SIGNATURE", ERROR_SECP_PARSE_SIGNATURE),
+        ("ERROR_SECP_SERIALIZE_PUBKEY", ERROR_SECP_SERIALIZE_PUBKEY),
+        ("ERROR_SECP_INVALID_SIGNATURE_SIZE", ERROR_SECP_INVALID_SIGNATURE_SIZE),
+        ("ERROR_SECP_INVALID_MESSAGE_SIZE", ERROR_SECP_INVALID_MESSAGE_SIZE),
+        ("ERROR_SECP_INVALID_OUTPUT_SIZE", ERROR_SECP_INVALID_OUTPUT_SIZE),
+        ("ERROR_SECP_UNKNOWN", ERROR_SECP_UNKNOWN),
+        ("ERROR_SECP_PARSE_PUBKEY", ERROR_SECP_PARSE_PUBKEY),
+        ("ERROR_SECP_VERIFICATION", ERROR_SECP_VERIFICATION),
+        ("ERROR_PREFILLED_DATA_ALLOCATION", ERROR_PREFILLED_DATA_ALLOCATION),
+        ("ERROR_ETH_VERIFICATION", ERROR_ETH_VERIFICATION),
+        ("ERROR_RSA_VERIFICATION", ERROR_RSA_VERIFICATION),
+        ("ERROR_AUTH_KIND_NOT_ALLOWED", ERROR_AUTH_KIND_NOT_ALLOWED),
+        ("ERROR_LOCK_PERIOD_ACTIVE", ERROR_LOCK_PERIOD_ACTIVE),
+        ("ERROR_WITNESS_TOO_SHORT", ERROR_WITNESS_TOO_SHORT),
+        ("ERROR_INVALID_WITNESS_PUBKEY", ERROR_INVALID_WITNESS_PUBKEY),
+        ("ERROR_INVALID_AUTH_PAYLOAD", ERROR_INVALID_AUTH_PAYLOAD),
+        ("ERROR_WITNESS_MOLECULE_ENCODING", ERROR_WITNESS_MOLECULE_ENCODING),
+        ("ERROR_UNKNOWN_SYS_ERROR", ERROR_UNKNOWN_SYS_ERROR),
+        ("ERROR_UNSUPPORTED_AUTH", ERROR_UNSUPPORTED_AUTH),
+        ("ERROR_PAYOUT_LOCK_MISMATCH", ERROR_PAYOUT_LOCK_MISMATCH),
+        ("ERROR_PUPPET_TYPE_ID_MISMATCH", ERROR_PUPPET_TYPE_ID_MISMATCH),
+        ("ERROR_CELL_DEP_PROOF_DENIED", ERROR_CELL_DEP_PROOF_DENIED),
+        ("ERROR_COOLDOWN_GUARD_MISMATCH", ERROR_COOLDOWN_GUARD_MISMATCH),
+    ];
+    let docs = include_str!("../../docs/error_codes.md");
+    for (i, (name, code)) in codes.iter().enumerate() {
+        // appended in order, never renumbered or reused
+        assert_eq!(*code as usize, i + 1, "{} was renumbered", name);
+        let row = format!("| {} | `{}` |", code, name);
+        assert!(docs.contains(&row), "{} isn't documented", name);
+    }
+    assert_eq!(Error::CooldownGuardMismatch as i8, codes.len() as i8);
+}
@@ -2,27 +2,26 @@ use super::*;
 use ckb_testtool::{builtin::ALWAYS_SUCCESS, context::Context};
 use ckb_tool::ckb_types::{
     bytes::Bytes,
-    core::TransactionBuilder,
+    core::{TransactionBuilder, TransactionView},
     packed::*,
     prelude::*,
 };
-use ckb_tool::ckb_error::assert_error_eq;
+use ckb_tool::ckb_crypto::secp::Privkey;
+use ckb_tool::ckb_error::{assert_error_eq, Error};
 use ckb_tool::ckb_script::ScriptError;
 use ckb_system_scripts::BUNDLED_CELL;
-
-use std::println;
+use openssl::pkey::{PKey, Private};
+use nervos_dao_extended_ownership_script::error_code::*;
+use dao_ownership_types::{
+    args::{LibraryLocator, LockArgs, PUPPET_TYPE_HASH_LEN},
+    generated::lock_witness as mol,
+    witness::{Auth, LockWitness, AUTH_MERKLE, AUTH_PUBKEY_HASH, FLAG_PUBKEY},
+};
 
 use helper;
 
 const MAX_CYCLES: u64 = 10_000_000;
 
-// error numbers
-const ERROR_EMPTY_ARGS: i8 = 5;
-const ERROR_NO_PUPPET_CELL: i8 = 6;
-const ERROR_ONLY_ONE_PUPPET_CELL: i8 = 7;
-const ERROR_WRONG_PUBKEY: i8 = 10;
-const ERROR_WRONG_PUBKEY_HASH_LEN: i8 = 11;
-
 #[test]
 fn test_with_empty_args() {
     // deploy contract
@@ -79,7 +78,7 @@ fn test_with_empty_args() {
     let script_cell_index = 0;
     assert_error_eq!(
         err,
-        ScriptError::ValidationFailure(ERROR_EMPTY_ARGS).input_lock_script(script_cell_index)
+        ScriptError::ValidationFailure(ERROR_NO_VALID_ARGS).input_lock_script(script_cell_index)
     );
 }
 
@@ -90,6 +89,7 @@ fn test_with_empty_pubkey() {
 
     // deploy contract
     let mut context = Context::default();
+    let genesis = helper::insert_genesis_header(&mut context);
     let contract_bin: Bytes = Loader::default().load_binary("nervos-dao-extended-ownership-script");
     let out_point = context.deploy_cell(contract_bin);
 
@@ -148,7 +148,7 @@ fn test_with_empty_pubkey() {
     // prepare nervos-dao cell
     // here we set wrong type script on popurse
     let dao_ownership_id: [u8; 32] = puppet_type_script.clone().calc_script_hash().unpack();
-    let dao_ownership_lock_args: Bytes = dao_ownership_id.to_vec().into();
+    let dao_ownership_lock_args: Bytes = LockArgs::new(dao_ownership_id).to_vec().into();
     let dao_ownership_lock_script = context
         .build_script(&out_point, dao_ownership_lock_args)
         .expect("script");
@@ -202,10 +202,12 @@ fn test_with_empty_pubkey() {
         .cell_dep(secp256k1_dep)
         .cell_dep(secp256k1_data_dep)
         .witnesses(witnesses.pack())
+        .header_dep(genesis.hash())
         .build();
         
     let signed_tx = context.complete_tx(tx);
-    let signed_tx = helper::prepare_witnesses(signed_tx, 0, &privkey.clone());
+    let domain = helper::SigningDomain::new(&genesis.hash(), &dao_ownership_lock_script, &puppet_type_script);
+    let signed_tx = helper::prepare_witnesses(signed_tx, 0, &privkey.clone(), &domain);
     
     // run
     let err = context
@@ -215,7 +217,7 @@ fn test_with_empty_pubkey() {
     let script_cell_index = 0;
     assert_error_eq!(
         err,
-        ScriptError::ValidationFailure(ERROR_WRONG_PUBKEY_HASH_LEN).input_lock_script(script_cell_index)
+        ScriptError::ValidationFailure(ERROR_WRONG_PUBKEY_HASH_LENGTH).input_lock_script(script_cell_index)
     );
 }
 
@@ -227,6 +229,7 @@ fn test_with_two_puppect_cells() {
 
     // deploy contract
     let mut context = Context::default();
+    let genesis = helper::insert_genesis_header(&mut context);
     let contract_bin: Bytes = Loader::default().load_binary("nervos-dao-extended-ownership-script");
     let out_point = context.deploy_cell(contract_bin);
 
@@ -294,7 +297,7 @@ fn test_with_two_puppect_cells() {
     // prepare nervos-dao cell
     // here we set wrong type script on popurse
     let dao_ownership_id: [u8; 32] = puppet_type_script.clone().calc_script_hash().unpack();
-    let dao_ownership_lock_args: Bytes = dao_ownership_id.to_vec().into();
+    let dao_ownership_lock_args: Bytes = LockArgs::new(dao_ownership_id).to_vec().into();
     let dao_ownership_lock_script = context
         .build_script(&out_point, dao_ownership_lock_args)
         .expect("script");
@@ -351,10 +354,12 @@ fn test_with_two_puppect_cells() {
         .cell_dep(secp256k1_dep)
         .cell_dep(secp256k1_data_dep)
         .witnesses(witnesses.pack())
+        .header_dep(genesis.hash())
         .build();
     
     let signed_tx = context.complete_tx(tx);
-    let signed_tx = helper::prepare_witnesses(signed_tx, 0, &privkey.clone());
+    let domain = helper::SigningDomain::new(&genesis.hash(), &dao_ownership_lock_script, &puppet_type_script);
+    let signed_tx = helper::prepare_witnesses(signed_tx, 0, &privkey.clone(), &domain);
     
     // run
     let err = context
@@ -364,7 +369,7 @@ fn test_with_two_puppect_cells() {
     let script_cell_index = 0;
     assert_error_eq!(
         err,
-        ScriptError::ValidationFailure(ERROR_ONLY_ONE_PUPPET_CELL).input_lock_script(script_cell_index)
+        ScriptError::ValidationFailure(ERROR_REQUIRED_ONLY_ONE_PUPPET).input_lock_script(script_cell_index)
     );
 }
 
@@ -376,6 +381,7 @@ fn test_with_wrong_puppet_cell() {
 
     // deploy contract
     let mut context = Context::default();
+    let genesis = helper::insert_genesis_header(&mut context);
     let contract_bin: Bytes = Loader::default().load_binary("nervos-dao-extended-ownership-script");
     let out_point = context.deploy_cell(contract_bin);
 
@@ -438,7 +444,7 @@ fn test_with_wrong_puppet_cell() {
     // prepare nervos-dao cell
     // here we set wrong type script on popurse
     let dao_ownership_id: [u8; 32] = wrong_type_script.clone().calc_script_hash().unpack();//puppet_type_script.clone().calc_script_hash().unpack();
-    let dao_ownership_lock_args: Bytes = dao_ownership_id.to_vec().into();
+    let dao_ownership_lock_args: Bytes = LockArgs::new(dao_ownership_id).to_vec().into();
     let dao_ownership_lock_script = context
         .build_script(&out_point, dao_ownership_lock_args)
         .expect("script");
@@ -492,10 +498,12 @@ fn test_with_wrong_puppet_cell() {
         .cell_dep(secp256k1_dep)
         .cell_dep(secp256k1_data_dep)
         .witnesses(witnesses.pack())
+        .header_dep(genesis.hash())
         .build();
         
     let signed_tx = context.complete_tx(tx);
-    let signed_tx = helper::prepare_witnesses(signed_tx, 0, &privkey.clone());
+    let domain = helper::SigningDomain::new(&genesis.hash(), &dao_ownership_lock_script, &puppet_type_script);
+    let signed_tx = helper::prepare_witnesses(signed_tx, 0, &privkey.clone(), &domain);
     
     // run
     let err = context
@@ -505,7 +513,7 @@ fn test_with_wrong_puppet_cell() {
     let script_cell_index = 0;
     assert_error_eq!(
         err,
-        ScriptError::ValidationFailure(ERROR_NO_PUPPET_CELL).input_lock_script(script_cell_index)
+        ScriptError::ValidationFailure(ERROR_NO_PUPPET_CELL_FOUND).input_lock_script(script_cell_index)
     );
 }
 
@@ -520,6 +528,7 @@ fn test_with_wrong_pubkey() {
 
     // deploy contract
     let mut context = Context::default();
+    let genesis = helper::insert_genesis_header(&mut context);
     let contract_bin: Bytes = Loader::default().load_binary("nervos-dao-extended-ownership-script");
     let out_point = context.deploy_cell(contract_bin);
 
@@ -578,7 +587,7 @@ fn test_with_wrong_pubkey() {
     // prepare nervos-dao cell
     // here we set wrong type script on popurse
     let dao_ownership_id: [u8; 32] = puppet_type_script.clone().calc_script_hash().unpack();
-    let dao_ownership_lock_args: Bytes = dao_ownership_id.to_vec().into();
+    let dao_ownership_lock_args: Bytes = LockArgs::new(dao_ownership_id).to_vec().into();
     let dao_ownership_lock_script = context
         .build_script(&out_point, dao_ownership_lock_args)
         .expect("script");
@@ -632,10 +641,12 @@ fn test_with_wrong_pubkey() {
         .cell_dep(secp256k1_dep)
         .cell_dep(secp256k1_data_dep)
         .witnesses(witnesses.pack())
+        .header_dep(genesis.hash())
         .build();
         
     let signed_tx = context.complete_tx(tx);
-    let signed_tx = helper::prepare_witnesses(signed_tx, 0, &privkey.clone());
+    let domain = helper::SigningDomain::new(&genesis.hash(), &dao_ownership_lock_script, &puppet_type_script);
+    let signed_tx = helper::prepare_witnesses(signed_tx, 0, &privkey.clone(), &domain);
     
     // run
     let err = context
@@ -645,7 +656,7 @@ fn test_with_wrong_pubkey() {
     let script_cell_index = 0;
     assert_error_eq!(
         err,
-        ScriptError::ValidationFailure(ERROR_WRONG_PUBKEY).input_lock_script(script_cell_index)
+        ScriptError::ValidationFailure(ERROR_WRONG_PUBLIC_KEY).input_lock_script(script_cell_index)
     );
 }
 
@@ -657,6 +668,7 @@ fn test_with_correct_tx() {
 
     // deploy contract
     let mut context = Context::default();
+    let genesis = helper::insert_genesis_header(&mut context);
     let contract_bin: Bytes = Loader::default().load_binary("nervos-dao-extended-ownership-script");
     let out_point = context.deploy_cell(contract_bin);
 
@@ -713,7 +725,7 @@ fn test_with_correct_tx() {
 
     // prepare nervos-dao cell
     let dao_ownership_id: [u8; 32] = puppet_type_script.clone().calc_script_hash().unpack();
-    let dao_ownership_lock_args: Bytes = dao_ownership_id.to_vec().into();
+    let dao_ownership_lock_args: Bytes = LockArgs::new(dao_ownership_id).to_vec().into();
     let dao_ownership_lock_script = context
         .build_script(&out_point, dao_ownership_lock_args)
         .expect("script");
@@ -768,10 +780,12 @@ fn test_with_correct_tx() {
         .cell_dep(secp256k1_dep)
         .cell_dep(secp256k1_data_dep)
         .witnesses(witnesses.pack())
+        .header_dep(genesis.hash())
         .build();
         
     let signed_tx = context.complete_tx(tx);
-    let signed_tx = helper::prepare_witnesses(signed_tx, 0, &privkey.clone());
+    let domain = helper::SigningDomain::new(&genesis.hash(), &dao_ownership_lock_script, &puppet_type_script);
+    let signed_tx = helper::prepare_witnesses(signed_tx, 0, &privkey.clone(), &domain);
     
     // run
     let cycles = context
@@ -780,3 +794,2575 @@ fn test_with_correct_tx() {
     // println!("consume cycles: {}", cycles);   
 }
 
+// an owner whose puppet names one operator, returns the owner hash and the operator's key and hash
+fn operator_keys() -> ([u8; 20], Privkey, [u8; 20]) {
+    let (_, owner_pubkey) = helper::generate_key_pair();
+    let (operator_privkey, operator_pubkey) = helper::generate_key_pair();
+    (
+        helper::blake160(&owner_pubkey.serialize()),
+        operator_privkey,
+        helper::blake160(&operator_pubkey.serialize()),
+    )
+}
+
+// spend a DAO cell holding `dao_data` next to the puppet, signed by the operator
+fn operator_tx(
+    env: &mut helper::DaoEnv,
+    operator: &Privkey,
+    dao_data: Bytes,
+    puppet_data: Bytes,
+    outputs: Vec<(CellOutput, Bytes)>,
+) -> TransactionView {
+    let dao_input = env.create_dao_cell(dao_data);
+    let puppet_input = env.create_puppet(puppet_data);
+    env.build_tx(vec![dao_input, puppet_input], outputs, 0, operator)
+}
+
+#[test]
+fn test_operator_phase1_within_scope() {
+    let (owner_hash, operator_privkey, operator_hash) = operator_keys();
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = helper::puppet_data(&owner_hash, &[(operator_hash, helper::SCOPE_PHASE1)]);
+
+    // deposit cell becomes a withdrawing cell, puppet stays untouched
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), puppet_data.clone()),
+    ];
+    let tx = operator_tx(&mut env, &operator_privkey, helper::dao_data(0), puppet_data, outputs);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_operator_phase1_out_of_scope() {
+    let (owner_hash, operator_privkey, operator_hash) = operator_keys();
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = helper::puppet_data(&owner_hash, &[(operator_hash, helper::SCOPE_PHASE2)]);
+
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), puppet_data.clone()),
+    ];
+    let tx = operator_tx(&mut env, &operator_privkey, helper::dao_data(0), puppet_data, outputs);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_OPERATOR_PHASE1_DENIED).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_operator_phase2_within_scope() {
+    let (owner_hash, operator_privkey, operator_hash) = operator_keys();
+    let mut env = helper::DaoEnv::new();
+    let scope = helper::SCOPE_PHASE1 | helper::SCOPE_PHASE2;
+    let puppet_data = helper::puppet_data(&owner_hash, &[(operator_hash, scope)]);
+
+    // withdrawing cell is paid out to the owner
+    let outputs = vec![
+        (env.owner_output(&owner_hash), Bytes::new()),
+        (env.puppet_output(), puppet_data.clone()),
+    ];
+    let tx = operator_tx(&mut env, &operator_privkey, helper::dao_data(100), puppet_data, outputs);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_operator_redirects_payout() {
+    let (owner_hash, operator_privkey, operator_hash) = operator_keys();
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = helper::puppet_data(&owner_hash, &[(operator_hash, helper::SCOPE_PHASE2)]);
+
+    // the operator pays the withdrawal to itself
+    let outputs = vec![
+        (env.owner_output(&operator_hash), Bytes::new()),
+        (env.puppet_output(), puppet_data.clone()),
+    ];
+    let tx = operator_tx(&mut env, &operator_privkey, helper::dao_data(100), puppet_data, outputs);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_PAYOUT_LOCK_MISMATCH).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_operator_payout_lock() {
+    let (owner_hash, operator_privkey, operator_hash) = operator_keys();
+    let mut env = helper::DaoEnv::new();
+    let payout = env.plain_output();
+    let payout_lock_hash: [u8; 32] = payout.lock().calc_script_hash().unpack();
+    let puppet_data = helper::push_payout_lock(
+        helper::puppet_data(&owner_hash, &[(operator_hash, helper::SCOPE_PHASE2)]),
+        &payout_lock_hash,
+    );
+
+    let outputs = vec![
+        (payout, Bytes::new()),
+        (env.puppet_output(), puppet_data.clone()),
+    ];
+    let tx = operator_tx(&mut env, &operator_privkey, helper::dao_data(100), puppet_data, outputs);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_operator_without_scope() {
+    let (owner_hash, operator_privkey, operator_hash) = operator_keys();
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = helper::puppet_data(&owner_hash, &[(operator_hash, helper::SCOPE_NONE)]);
+
+    let outputs = vec![
+        (env.plain_output(), Bytes::new()),
+        (env.puppet_output(), puppet_data.clone()),
+    ];
+    let tx = operator_tx(&mut env, &operator_privkey, helper::dao_data(100), puppet_data, outputs);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_OPERATOR_PHASE2_DENIED).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_operator_cannot_transfer_ownership() {
+    let (owner_hash, operator_privkey, operator_hash) = operator_keys();
+    let mut env = helper::DaoEnv::new();
+    let scope = helper::SCOPE_PHASE1 | helper::SCOPE_PHASE2;
+    let puppet_data = helper::puppet_data(&owner_hash, &[(operator_hash, scope)]);
+
+    // the operator tries to hand the puppet over to itself
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(0)),
+        (env.puppet_output(), helper::puppet_data(&operator_hash, &[])),
+    ];
+    let tx = operator_tx(&mut env, &operator_privkey, helper::dao_data(0), puppet_data, outputs);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_OPERATOR_TRANSFER_DENIED).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_with_truncated_puppet_record() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let mut puppet_data = helper::puppet_data(&pubkey_hash, &[(pubkey_hash, helper::SCOPE_PHASE1)]).to_vec();
+    puppet_data.pop();
+    let dao_input = env.create_dao_cell(Bytes::new());
+    let puppet_input = env.create_puppet(puppet_data.into());
+
+    let outputs = vec![(env.plain_output(), Bytes::new())];
+    let tx = env.build_tx(vec![dao_input, puppet_input], outputs, 0, &privkey);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_INVALID_PUPPET_DATA).input_lock_script(0)
+    );
+}
+
+// puppet handed from `previous` to `owner` in epoch 100 with a 10 epochs cooldown
+fn pending_transfer_data(owner: &[u8; 20], previous: &[u8; 20]) -> Bytes {
+    helper::push_cooldown(helper::puppet_data(owner, &[]), 10, 100, previous)
+}
+
+#[test]
+fn test_cooldown_blocks_new_owner() {
+    let (_, previous_pubkey) = helper::generate_key_pair();
+    let previous_hash = helper::blake160(&previous_pubkey.serialize());
+    let (owner_privkey, owner_pubkey) = helper::generate_key_pair();
+    let owner_hash = helper::blake160(&owner_pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = pending_transfer_data(&owner_hash, &previous_hash);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env
+        .create_puppet(puppet_data.clone())
+        .as_builder()
+        .since(helper::since_relative_epoch(5).pack())
+        .build();
+
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), puppet_data),
+    ];
+    let tx = env.build_tx(vec![dao_input, puppet_input], outputs, 0, &owner_privkey);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_COOLDOWN_ACTIVE).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_cooldown_elapsed() {
+    let (_, previous_pubkey) = helper::generate_key_pair();
+    let previous_hash = helper::blake160(&previous_pubkey.serialize());
+    let (owner_privkey, owner_pubkey) = helper::generate_key_pair();
+    let owner_hash = helper::blake160(&owner_pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = pending_transfer_data(&owner_hash, &previous_hash);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env
+        .create_puppet(puppet_data)
+        .as_builder()
+        .since(helper::since_relative_epoch(10).pack())
+        .build();
+
+    // settle the pending transfer while starting a withdrawal
+    let settled = helper::push_cooldown(helper::puppet_data(&owner_hash, &[]), 10, 0, &[0u8; 20]);
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), settled),
+    ];
+    let tx = env.build_tx(vec![dao_input, puppet_input], outputs, 0, &owner_privkey);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_cooldown_previous_owner_cancels() {
+    let (previous_privkey, previous_pubkey) = helper::generate_key_pair();
+    let previous_hash = helper::blake160(&previous_pubkey.serialize());
+    let (_, owner_pubkey) = helper::generate_key_pair();
+    let owner_hash = helper::blake160(&owner_pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env.create_puppet(pending_transfer_data(&owner_hash, &previous_hash));
+
+    let restored = helper::push_cooldown(helper::puppet_data(&previous_hash, &[]), 10, 0, &[0u8; 20]);
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(0)),
+        (env.puppet_output(), restored),
+    ];
+    let tx = env.build_tx(vec![dao_input, puppet_input], outputs, 0, &previous_privkey);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_cooldown_previous_owner_cannot_withdraw() {
+    let (previous_privkey, previous_pubkey) = helper::generate_key_pair();
+    let previous_hash = helper::blake160(&previous_pubkey.serialize());
+    let (_, owner_pubkey) = helper::generate_key_pair();
+    let owner_hash = helper::blake160(&owner_pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = pending_transfer_data(&owner_hash, &previous_hash);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env.create_puppet(puppet_data.clone());
+
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), puppet_data),
+    ];
+    let tx = env.build_tx(vec![dao_input, puppet_input], outputs, 0, &previous_privkey);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_COOLDOWN_CANCEL_ONLY).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_transfer_opens_cooldown() {
+    let (owner_privkey, owner_pubkey) = helper::generate_key_pair();
+    let owner_hash = helper::blake160(&owner_pubkey.serialize());
+    let (_, buyer_pubkey) = helper::generate_key_pair();
+    let buyer_hash = helper::blake160(&buyer_pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = helper::push_cooldown(helper::puppet_data(&owner_hash, &[]), 10, 0, &[0u8; 20]);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env
+        .create_puppet_in_epoch(puppet_data, 150)
+        .as_builder()
+        .since(helper::since_absolute_epoch(200).pack())
+        .build();
+
+    let transferred = helper::push_cooldown(helper::puppet_data(&buyer_hash, &[]), 10, 200, &owner_hash);
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(0)),
+        (env.puppet_output(), transferred),
+    ];
+    let tx = env.build_tx(vec![dao_input, puppet_input], outputs, 0, &owner_privkey);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_transfer_stamp_predates_puppet() {
+    let (owner_privkey, owner_pubkey) = helper::generate_key_pair();
+    let owner_hash = helper::blake160(&owner_pubkey.serialize());
+    let (_, buyer_pubkey) = helper::generate_key_pair();
+    let buyer_hash = helper::blake160(&buyer_pubkey.serialize());
+
+    // stamped epoch 200, but the puppet was last written in epoch 250
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = helper::push_cooldown(helper::puppet_data(&owner_hash, &[]), 10, 0, &[0u8; 20]);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env
+        .create_puppet_in_epoch(puppet_data, 250)
+        .as_builder()
+        .since(helper::since_absolute_epoch(200).pack())
+        .build();
+
+    let transferred = helper::push_cooldown(helper::puppet_data(&buyer_hash, &[]), 10, 200, &owner_hash);
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(0)),
+        (env.puppet_output(), transferred),
+    ];
+    let tx = env.build_tx(vec![dao_input, puppet_input], outputs, 0, &owner_privkey);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_COOLDOWN_RECORD_MISMATCH).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_transfer_cannot_drop_cooldown() {
+    let (owner_privkey, owner_pubkey) = helper::generate_key_pair();
+    let owner_hash = helper::blake160(&owner_pubkey.serialize());
+    let (_, buyer_pubkey) = helper::generate_key_pair();
+    let buyer_hash = helper::blake160(&buyer_pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = helper::push_cooldown(helper::puppet_data(&owner_hash, &[]), 10, 0, &[0u8; 20]);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env
+        .create_puppet(puppet_data)
+        .as_builder()
+        .since(helper::since_absolute_epoch(200).pack())
+        .build();
+
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(0)),
+        (env.puppet_output(), helper::puppet_data(&buyer_hash, &[])),
+    ];
+    let tx = env.build_tx(vec![dao_input, puppet_input], outputs, 0, &owner_privkey);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_COOLDOWN_RECORD_MISMATCH).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_cooldown_cancel_keeps_dao_cells() {
+    let (previous_privkey, previous_pubkey) = helper::generate_key_pair();
+    let previous_hash = helper::blake160(&previous_pubkey.serialize());
+    let (_, owner_pubkey) = helper::generate_key_pair();
+    let owner_hash = helper::blake160(&owner_pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env.create_puppet(pending_transfer_data(&owner_hash, &previous_hash));
+
+    // cancelling, the previous owner also takes the deposit
+    let restored = helper::push_cooldown(helper::puppet_data(&previous_hash, &[]), 10, 0, &[0u8; 20]);
+    let outputs = vec![
+        (env.plain_output(), Bytes::new()),
+        (env.puppet_output(), restored),
+    ];
+    let tx = env.build_tx(vec![dao_input, puppet_input], outputs, 0, &previous_privkey);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_COOLDOWN_CANCEL_ONLY).input_lock_script(0)
+    );
+}
+
+// the puppet, created in epoch 100, moved alone under its real type script
+// to the guard lock, returns the tx
+fn puppet_alone_tx(env: &mut helper::DaoEnv, puppet_data: Bytes, since: u64, output: Bytes) -> TransactionView {
+    env.use_guarded_puppet_type(&[0x42; 32]);
+    let puppet_input = env
+        .create_puppet_in_epoch(puppet_data, 100)
+        .as_builder()
+        .since(since.pack())
+        .build();
+    let guard_lock = env.guard_lock_script();
+    let outputs = vec![(env.puppet_output().as_builder().lock(guard_lock).build(), output)];
+    env.build_unsigned_tx(vec![puppet_input], outputs)
+}
+
+#[test]
+fn test_puppet_type_refuses_retransfer() {
+    let previous_hash = [0x11; 20];
+    let owner_hash = [0x22; 20];
+    let buyer_hash = [0x33; 20];
+
+    // the new owner hands the puppet on during the cooldown, without the DAO cells
+    let mut env = helper::DaoEnv::new();
+    let rehanded = helper::push_cooldown(helper::puppet_data(&buyer_hash, &[]), 10, 105, &owner_hash);
+    let tx = puppet_alone_tx(
+        &mut env,
+        pending_transfer_data(&owner_hash, &previous_hash),
+        helper::since_absolute_epoch(105),
+        rehanded,
+    );
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_COOLDOWN_ACTIVE).input_type_script(0)
+    );
+}
+
+#[test]
+fn test_puppet_type_refuses_dropped_cooldown() {
+    let owner_hash = [0x22; 20];
+    let buyer_hash = [0x33; 20];
+
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = helper::push_cooldown(helper::puppet_data(&owner_hash, &[]), 10, 0, &[0u8; 20]);
+    let tx = puppet_alone_tx(
+        &mut env,
+        puppet_data,
+        helper::since_absolute_epoch(200),
+        helper::puppet_data(&buyer_hash, &[]),
+    );
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_COOLDOWN_RECORD_MISMATCH).input_type_script(0)
+    );
+}
+
+#[test]
+fn test_puppet_type_stamps_transfer() {
+    let owner_hash = [0x22; 20];
+    let buyer_hash = [0x33; 20];
+
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = helper::push_cooldown(helper::puppet_data(&owner_hash, &[]), 10, 0, &[0u8; 20]);
+    let transferred = helper::push_cooldown(helper::puppet_data(&buyer_hash, &[]), 10, 200, &owner_hash);
+    let tx = puppet_alone_tx(&mut env, puppet_data, helper::since_absolute_epoch(200), transferred);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_puppet_type_refuses_unguarded_transfer() {
+    let (_, owner_pubkey) = helper::generate_key_pair();
+    let owner_hash = helper::blake160(&owner_pubkey.serialize());
+    let (_, buyer_pubkey) = helper::generate_key_pair();
+    let buyer_hash = helper::blake160(&buyer_pubkey.serialize());
+    let puppet_data = helper::push_cooldown(helper::puppet_data(&owner_hash, &[]), 10, 0, &[0u8; 20]);
+    let transferred = helper::push_cooldown(helper::puppet_data(&buyer_hash, &[]), 10, 200, &owner_hash);
+
+    // handed to the buyer under its own lock, the previous owner couldn't cancel
+    let mut env = helper::DaoEnv::new();
+    let tx = puppet_alone_tx(&mut env, puppet_data.clone(), helper::since_absolute_epoch(200), transferred.clone());
+    let buyer_lock = env.secp256k1_lock_script(&buyer_hash);
+    let outputs = tx.outputs().into_iter().map(|output| output.as_builder().lock(buyer_lock.clone()).build());
+    let tx = tx.as_advanced_builder().set_outputs(outputs.collect()).build();
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_COOLDOWN_GUARD_MISMATCH).input_type_script(0)
+    );
+
+    // a puppet type without a guard can't open a pending transfer at all
+    let mut env = helper::DaoEnv::new();
+    env.use_puppet_type(&[0x42; 32]);
+    let puppet_input = env
+        .create_puppet_in_epoch(puppet_data, 100)
+        .as_builder()
+        .since(helper::since_absolute_epoch(200).pack())
+        .build();
+    let outputs = vec![(env.puppet_output(), transferred)];
+    let tx = env.build_unsigned_tx(vec![puppet_input], outputs);
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_COOLDOWN_GUARD_MISMATCH).input_type_script(0)
+    );
+}
+
+#[test]
+fn test_cooldown_cancel_secp256k1_puppet() {
+    let (previous_privkey, previous_pubkey) = helper::generate_key_pair();
+    let previous_hash = helper::blake160(&previous_pubkey.serialize());
+    let (_, owner_pubkey) = helper::generate_key_pair();
+    let owner_hash = helper::blake160(&owner_pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    env.use_guarded_puppet_type(&[0x42; 32]);
+    let previous_lock = env.secp256k1_lock_script(&previous_hash);
+    let guard_lock = env.guard_lock_script();
+
+    // the previous owner hands its secp256k1 locked puppet over, the
+    // pending transfer goes to the guard lock
+    let puppet_data = helper::push_cooldown(helper::puppet_data(&previous_hash, &[]), 10, 0, &[0u8; 20]);
+    let puppet_input = env
+        .create_puppet_with_lock_in_epoch(previous_lock.clone(), puppet_data, 90)
+        .as_builder()
+        .since(helper::since_absolute_epoch(100).pack())
+        .build();
+    let pending = pending_transfer_data(&owner_hash, &previous_hash);
+    let outputs = vec![(env.puppet_output().as_builder().lock(guard_lock.clone()).build(), pending.clone())];
+    let tx = env.build_unsigned_tx(vec![puppet_input], outputs);
+    let tx = helper::sign_sighash_all(tx, &[0], &previous_privkey);
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+
+    // and, signing for the guard lock, takes it back to its secp256k1 lock
+    let puppet_input = env.create_puppet_with_lock(guard_lock, pending);
+    let restored = helper::push_cooldown(helper::puppet_data(&previous_hash, &[]), 10, 0, &[0u8; 20]);
+    let outputs = vec![(env.puppet_output().as_builder().lock(previous_lock).build(), restored)];
+    let tx = env.build_tx(vec![puppet_input], outputs, 0, &previous_privkey);
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_puppet_type_settles_after_cooldown() {
+    let previous_hash = [0x11; 20];
+    let owner_hash = [0x22; 20];
+
+    let mut env = helper::DaoEnv::new();
+    let settled = helper::push_cooldown(helper::puppet_data(&owner_hash, &[]), 10, 0, &[0u8; 20]);
+    let tx = puppet_alone_tx(
+        &mut env,
+        pending_transfer_data(&owner_hash, &previous_hash),
+        helper::since_relative_epoch(9),
+        settled.clone(),
+    );
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_COOLDOWN_ACTIVE).input_type_script(0)
+    );
+
+    let mut env = helper::DaoEnv::new();
+    let tx = puppet_alone_tx(
+        &mut env,
+        pending_transfer_data(&owner_hash, &previous_hash),
+        helper::since_relative_epoch(10),
+        settled,
+    );
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_puppet_type_id() {
+    let mut env = helper::DaoEnv::new();
+    let funding = env.context.create_cell(env.plain_output(), Bytes::new());
+    let input = CellInput::new_builder().previous_output(funding).build();
+    let type_id = {
+        let mut hasher = ckb_tool::ckb_hash::new_blake2b();
+        hasher.update(input.as_slice());
+        hasher.update(&0u64.to_le_bytes());
+        let mut type_id = [0u8; 32];
+        hasher.finalize(&mut type_id);
+        type_id
+    };
+
+    for (args, expected) in vec![(type_id, None), ([0x42; 32], Some(ERROR_PUPPET_TYPE_ID_MISMATCH))] {
+        let puppet_type = env.puppet_type_script_with(&args);
+        let output = env.puppet_output().as_builder().type_(Some(puppet_type).pack()).build();
+        let tx = env.build_unsigned_tx(vec![input.clone()], vec![(output, helper::puppet_data(&[0x11; 20], &[]))]);
+        match expected {
+            None => {
+                env.context
+                    .verify_tx(&tx, MAX_CYCLES)
+                    .expect("pass verification");
+            },
+            Some(code) => {
+                let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+                assert_error_eq!(err, ScriptError::ValidationFailure(code).output_type_script(0));
+            },
+        }
+    }
+}
+
+// a DAO position rented out until epoch 100, the lessee may start withdrawals
+fn leased_data(lessor: &[u8; 20], lessee: &[u8; 20]) -> Bytes {
+    helper::push_lease(helper::puppet_data(lessor, &[]), lessee, 100, helper::SCOPE_PHASE1)
+}
+
+#[test]
+fn test_lessee_before_expiry() {
+    let (_, lessor_pubkey) = helper::generate_key_pair();
+    let lessor_hash = helper::blake160(&lessor_pubkey.serialize());
+    let (lessee_privkey, lessee_pubkey) = helper::generate_key_pair();
+    let lessee_hash = helper::blake160(&lessee_pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = leased_data(&lessor_hash, &lessee_hash);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env
+        .create_puppet_in_epoch(puppet_data.clone(), 40)
+        .as_builder()
+        .since(helper::since_absolute_epoch(50).pack())
+        .build();
+
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), puppet_data),
+    ];
+    let tx = env.build_tx(vec![dao_input, puppet_input], outputs, 0, &lessee_privkey);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_lessee_after_expiry() {
+    let (_, lessor_pubkey) = helper::generate_key_pair();
+    let lessor_hash = helper::blake160(&lessor_pubkey.serialize());
+    let (lessee_privkey, lessee_pubkey) = helper::generate_key_pair();
+    let lessee_hash = helper::blake160(&lessee_pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = leased_data(&lessor_hash, &lessee_hash);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env
+        .create_puppet_in_epoch(puppet_data.clone(), 40)
+        .as_builder()
+        .since(helper::since_absolute_epoch(100).pack())
+        .build();
+
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), puppet_data),
+    ];
+    let tx = env.build_tx(vec![dao_input, puppet_input], outputs, 0, &lessee_privkey);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_LEASE_EXPIRED).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_lessee_without_since_after_expiry() {
+    let (_, lessor_pubkey) = helper::generate_key_pair();
+    let lessor_hash = helper::blake160(&lessor_pubkey.serialize());
+    let (lessee_privkey, lessee_pubkey) = helper::generate_key_pair();
+    let lessee_hash = helper::blake160(&lessee_pubkey.serialize());
+
+    // the lease is over, the lessee sends a transaction declaring no since
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = leased_data(&lessor_hash, &lessee_hash);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env.create_puppet_in_epoch(puppet_data.clone(), 120);
+
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), puppet_data),
+    ];
+    let tx = env.build_tx(vec![dao_input, puppet_input], outputs, 0, &lessee_privkey);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_LEASE_EXPIRED).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_lessee_since_older_than_puppet() {
+    let (_, lessor_pubkey) = helper::generate_key_pair();
+    let lessor_hash = helper::blake160(&lessor_pubkey.serialize());
+    let (lessee_privkey, lessee_pubkey) = helper::generate_key_pair();
+    let lessee_hash = helper::blake160(&lessee_pubkey.serialize());
+
+    // the puppet was recreated after the expiry, an old since can't hide it
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = leased_data(&lessor_hash, &lessee_hash);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env
+        .create_puppet_in_epoch(puppet_data.clone(), 120)
+        .as_builder()
+        .since(helper::since_absolute_epoch(50).pack())
+        .build();
+
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), puppet_data),
+    ];
+    let tx = env.build_tx(vec![dao_input, puppet_input], outputs, 0, &lessee_privkey);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_LEASE_EXPIRED).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_idle_lessee_after_expiry() {
+    let (_, lessor_pubkey) = helper::generate_key_pair();
+    let lessor_hash = helper::blake160(&lessor_pubkey.serialize());
+    let (lessee_privkey, lessee_pubkey) = helper::generate_key_pair();
+    let lessee_hash = helper::blake160(&lessee_pubkey.serialize());
+
+    // the puppet is untouched since before the expiry, so its since passes,
+    // but a header of epoch 120 shows the transaction comes after it
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = leased_data(&lessor_hash, &lessee_hash);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env
+        .create_puppet_in_epoch(puppet_data.clone(), 40)
+        .as_builder()
+        .since(helper::since_absolute_epoch(40).pack())
+        .build();
+    env.push_header_dep(120_000, 120);
+
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), puppet_data),
+    ];
+    let tx = env.build_tx(vec![dao_input, puppet_input], outputs, 0, &lessee_privkey);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_LEASE_EXPIRED).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_lessor_during_lease() {
+    let (lessor_privkey, lessor_pubkey) = helper::generate_key_pair();
+    let lessor_hash = helper::blake160(&lessor_pubkey.serialize());
+    let (_, lessee_pubkey) = helper::generate_key_pair();
+    let lessee_hash = helper::blake160(&lessee_pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env
+        .create_puppet(leased_data(&lessor_hash, &lessee_hash))
+        .as_builder()
+        .since(helper::since_absolute_epoch(99).pack())
+        .build();
+
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(0)),
+        (env.puppet_output(), helper::puppet_data(&lessor_hash, &[])),
+    ];
+    let tx = env.build_tx(vec![dao_input, puppet_input], outputs, 0, &lessor_privkey);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_LEASE_ACTIVE).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_lessor_reclaims_after_expiry() {
+    let (lessor_privkey, lessor_pubkey) = helper::generate_key_pair();
+    let lessor_hash = helper::blake160(&lessor_pubkey.serialize());
+    let (_, lessee_pubkey) = helper::generate_key_pair();
+    let lessee_hash = helper::blake160(&lessee_pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env
+        .create_puppet(leased_data(&lessor_hash, &lessee_hash))
+        .as_builder()
+        .since(helper::since_absolute_epoch(100).pack())
+        .build();
+
+    // drop the lease record, no signature from the lessee is needed
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(0)),
+        (env.puppet_output(), helper::puppet_data(&lessor_hash, &[])),
+    ];
+    let tx = env.build_tx(vec![dao_input, puppet_input], outputs, 0, &lessor_privkey);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+// four members share the position, the plain owner hash is left empty
+fn allowlist() -> (Vec<Privkey>, Vec<[u8; 20]>) {
+    (0..4)
+        .map(|_| {
+            let (privkey, pubkey) = helper::generate_key_pair();
+            (privkey, helper::blake160(&pubkey.serialize()))
+        })
+        .unzip()
+}
+
+fn allowlist_tx(
+    env: &mut helper::DaoEnv,
+    root: &[u8; 32],
+    key: &Privkey,
+    auth: &[u8],
+) -> TransactionView {
+    let puppet_data = helper::push_merkle_root(helper::puppet_data(&[0u8; 20], &[]), root);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env.create_puppet(puppet_data.clone());
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), puppet_data),
+    ];
+    env.build_tx_with_auth(vec![dao_input, puppet_input], outputs, 0, key, auth)
+}
+
+#[test]
+fn test_merkle_member() {
+    let (privkeys, members) = allowlist();
+    let (root, siblings) = helper::merkle_proof(&members, 2);
+
+    let mut env = helper::DaoEnv::new();
+    let auth = helper::merkle_auth(&members[2], 2, &siblings);
+    let tx = allowlist_tx(&mut env, &root, &privkeys[2], &auth);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_merkle_proof_of_another_leaf() {
+    let (privkeys, members) = allowlist();
+    let (root, siblings) = helper::merkle_proof(&members, 1);
+
+    // member 2 signs but presents the path of member 1
+    let mut env = helper::DaoEnv::new();
+    let auth = helper::merkle_auth(&members[2], 1, &siblings);
+    let tx = allowlist_tx(&mut env, &root, &privkeys[2], &auth);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_MERKLE_PROOF_INVALID).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_merkle_leaf_not_signer() {
+    let (_, members) = allowlist();
+    let (root, siblings) = helper::merkle_proof(&members, 0);
+    let (outsider_privkey, _) = helper::generate_key_pair();
+
+    // a valid proof for member 0, signed by someone else
+    let mut env = helper::DaoEnv::new();
+    let auth = helper::merkle_auth(&members[0], 0, &siblings);
+    let tx = allowlist_tx(&mut env, &root, &outsider_privkey, &auth);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_MERKLE_PROOF_INVALID).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_merkle_forged_sibling() {
+    let (mut privkeys, mut members) = allowlist();
+    let (root, _) = helper::merkle_proof(&members, 0);
+
+    // an outsider crafts a path from its own leaf using made up siblings
+    let (outsider_privkey, outsider_pubkey) = helper::generate_key_pair();
+    let outsider_hash = helper::blake160(&outsider_pubkey.serialize());
+    members[3] = outsider_hash;
+    privkeys[3] = outsider_privkey;
+    let (_, forged_siblings) = helper::merkle_proof(&members, 3);
+
+    let mut env = helper::DaoEnv::new();
+    let auth = helper::merkle_auth(&outsider_hash, 3, &forged_siblings);
+    let tx = allowlist_tx(&mut env, &root, &privkeys[3], &auth);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_MERKLE_PROOF_INVALID).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_merkle_duplicate_index() {
+    // three members, the last one is the odd leaf out
+    let (privkeys, members) = allowlist();
+    let members = &members[..3];
+    let (root, siblings) = helper::merkle_proof(members, 2);
+
+    // the same path under the index of the padding
+    let mut env = helper::DaoEnv::new();
+    let auth = helper::merkle_auth(&members[2], 3, &siblings);
+    let tx = allowlist_tx(&mut env, &root, &privkeys[2], &auth);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_MERKLE_PROOF_INVALID).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_merkle_inner_node_as_leaf() {
+    let (privkeys, members) = allowlist();
+    let (root, siblings) = helper::merkle_proof(&members, 0);
+
+    // a path one level short that starts from an inner node must not verify
+    let mut env = helper::DaoEnv::new();
+    let auth = helper::merkle_auth(&members[0], 0, &siblings[1..]);
+    let tx = allowlist_tx(&mut env, &root, &privkeys[0], &auth);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_MERKLE_PROOF_INVALID).input_lock_script(0)
+    );
+}
+
+fn stealth_tx(env: &mut helper::DaoEnv, puppet_data: Bytes, key: &Privkey, auth: &[u8]) -> TransactionView {
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env.create_puppet(puppet_data.clone());
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), puppet_data),
+    ];
+    env.build_tx_with_auth(vec![dao_input, puppet_input], outputs, 0, key, auth)
+}
+
+#[test]
+fn test_stealth_owner() {
+    let (privkey, salt, commitment) = helper::generate_one_time_owner();
+    let pubkey_hash = helper::blake160(&privkey.pubkey().expect("pubkey").serialize());
+    let puppet_data = helper::push_owner_commitment(helper::puppet_data(&[0u8; 20], &[]), &commitment);
+    // the puppet can't be linked to the owner's other cells
+    assert!(puppet_data.windows(20).all(|window| window != &pubkey_hash[..]));
+
+    let mut env = helper::DaoEnv::new();
+    let tx = stealth_tx(&mut env, puppet_data, &privkey, &helper::commitment_auth(&salt));
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_stealth_owner_wrong_salt() {
+    let (privkey, _, commitment) = helper::generate_one_time_owner();
+    let (_, other_salt, _) = helper::generate_one_time_owner();
+    let puppet_data = helper::push_owner_commitment(helper::puppet_data(&[0u8; 20], &[]), &commitment);
+
+    let mut env = helper::DaoEnv::new();
+    let tx = stealth_tx(&mut env, puppet_data, &privkey, &helper::commitment_auth(&other_salt));
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_COMMITMENT_MISMATCH).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_stealth_owner_wrong_key() {
+    let (_, salt, commitment) = helper::generate_one_time_owner();
+    let (other_privkey, _) = helper::generate_key_pair();
+    let puppet_data = helper::push_owner_commitment(helper::puppet_data(&[0u8; 20], &[]), &commitment);
+
+    // knowing the salt is not enough without the committed key
+    let mut env = helper::DaoEnv::new();
+    let tx = stealth_tx(&mut env, puppet_data, &other_privkey, &helper::commitment_auth(&salt));
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_COMMITMENT_MISMATCH).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_shared_signature_in_puppet_input_type() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = helper::puppet_data(&pubkey_hash, &[]);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env.create_puppet(puppet_data.clone());
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), puppet_data),
+    ];
+    let tx = env.build_unsigned_tx(vec![dao_input, puppet_input], outputs);
+    // the dao cell's own witness stays empty
+    let domain = env.domain();
+    let tx = helper::sign_puppet_input_type(tx, 1, &privkey, &domain, &[]);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_shared_signature_of_puppet_lock() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = helper::puppet_data(&pubkey_hash, &[]);
+    let puppet_lock = env.secp256k1_lock_script(&pubkey_hash);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env.create_puppet_with_lock(puppet_lock.clone(), puppet_data.clone());
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output().as_builder().lock(puppet_lock).build(), puppet_data),
+    ];
+    let tx = env.build_unsigned_tx(vec![dao_input, puppet_input], outputs);
+    // one sighash-all signature unlocks the puppet and the dao cell
+    let tx = helper::sign_sighash_all(tx, &[1], &privkey);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_shared_signature_wrong_key() {
+    let (_, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+    let (wrong_privkey, _) = helper::generate_key_pair();
+
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = helper::puppet_data(&pubkey_hash, &[]);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env.create_puppet(puppet_data.clone());
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), puppet_data),
+    ];
+    let tx = env.build_unsigned_tx(vec![dao_input, puppet_input], outputs);
+    let domain = env.domain();
+    let tx = helper::sign_puppet_input_type(tx, 1, &wrong_privkey, &domain, &[]);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_WRONG_PUBLIC_KEY).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_shared_signature_missing() {
+    let (_, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = helper::puppet_data(&pubkey_hash, &[]);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env.create_puppet(puppet_data.clone());
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), puppet_data),
+    ];
+    // neither the dao cell nor the puppet carries a signature
+    let tx = env.build_unsigned_tx(vec![dao_input, puppet_input], outputs);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_WITNESS_ARGS_ENCODING).input_lock_script(0)
+    );
+}
+
+fn withdraw_tx(env: &mut helper::DaoEnv, owner_hash: &[u8; 20]) -> TransactionView {
+    let puppet_data = helper::puppet_data(owner_hash, &[]);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env.create_puppet(puppet_data.clone());
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), puppet_data),
+    ];
+    env.build_unsigned_tx(vec![dao_input, puppet_input], outputs)
+}
+
+#[test]
+fn test_signature_over_raw_tx_hash() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_tx(&mut env, &pubkey_hash);
+
+    // the old signing message is no longer accepted
+    let message: [u8; 32] = tx.hash().unpack();
+    let sig = privkey.sign_recoverable(&message.into()).expect("sign");
+    let witness = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(sig.serialize())).pack())
+        .build();
+    let tx = tx
+        .as_advanced_builder()
+        .set_witnesses(vec![witness.as_bytes().pack(), Bytes::new().pack()])
+        .build();
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_WRONG_PUBLIC_KEY).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_signature_from_another_chain() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_tx(&mut env, &pubkey_hash);
+
+    // signed for a fork with another genesis block
+    let mut domain = env.domain();
+    domain.genesis_hash = [1u8; 32];
+    let tx = helper::prepare_witnesses(tx, 0, &privkey, &domain);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_WRONG_PUBLIC_KEY).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_bare_signature() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_tx(&mut env, &pubkey_hash);
+    let domain = env.domain();
+    let tx = helper::sign_bare(tx, &[0], &privkey, &domain);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_bare_signature_from_another_chain() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_tx(&mut env, &pubkey_hash);
+
+    let mut domain = env.domain();
+    domain.genesis_hash = [1u8; 32];
+    let tx = helper::sign_bare(tx, &[0], &privkey, &domain);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_WRONG_PUBLIC_KEY).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_bare_signature_without_domain() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_tx(&mut env, &pubkey_hash);
+    // what a plain secp256k1_blake2b_sighash_all signer would produce
+    let tx = helper::sign_sighash_all(tx, &[0], &privkey);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_WRONG_PUBLIC_KEY).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_large_transaction_fits_heap() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = helper::puppet_data(&pubkey_hash, &[]);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env.create_puppet(puppet_data.clone());
+    // more data than the lock's 128K heap could hold
+    let data = Bytes::from(vec![0x42u8; 160 * 1024]);
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), puppet_data),
+        (env.plain_output(), data),
+    ];
+    let tx = env.build_unsigned_tx(vec![dao_input, puppet_input], outputs);
+    let domain = env.domain();
+    let tx = helper::prepare_witnesses(tx, 0, &privkey, &domain);
+
+    // the lock reads the genesis header, not the whole transaction
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_signature_for_another_puppet() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_tx(&mut env, &pubkey_hash);
+
+    let mut domain = env.domain();
+    domain.puppet_type_hash = [2u8; 32];
+    let tx = helper::prepare_witnesses(tx, 0, &privkey, &domain);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_WRONG_PUBLIC_KEY).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_without_genesis_header() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_tx(&mut env, &pubkey_hash)
+        .as_advanced_builder()
+        .set_header_deps(vec![])
+        .build();
+    let domain = env.domain();
+    let tx = helper::prepare_witnesses(tx, 0, &privkey, &domain);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_GENESIS_HEADER_MISSING).input_lock_script(0)
+    );
+}
+
+// The seller lists a withdrawing DAO cell, asking 1000 shannons at output 0.
+fn seller_listing(env: &mut helper::DaoEnv, sighash: u8) -> TransactionView {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+    let puppet_data = helper::puppet_data(&pubkey_hash, &[]);
+    let dao_input = env.create_dao_cell(helper::dao_data(100));
+    let puppet_input = env.create_puppet(puppet_data.clone());
+    let outputs = vec![
+        (env.plain_output().as_builder().capacity(1000u64.pack()).build(), Bytes::new()),
+        (env.puppet_output(), puppet_data),
+    ];
+    let tx = env.build_unsigned_tx(vec![dao_input, puppet_input], outputs);
+    let domain = env.domain();
+    helper::sign_with_sighash(tx, 0, &privkey, &domain, sighash, &[0], 1)
+}
+
+// the buyer brings its own cell and takes the change
+fn buyer_completes(env: &mut helper::DaoEnv, tx: TransactionView) -> TransactionView {
+    let buyer_out_point = env.context.create_cell(
+        env.plain_output().as_builder().capacity(5000u64.pack()).build(),
+        Bytes::new(),
+    );
+    tx.as_advanced_builder()
+        .input(CellInput::new_builder().previous_output(buyer_out_point).build())
+        .witness(Bytes::new().pack())
+        .output(env.plain_output().as_builder().capacity(3000u64.pack()).build())
+        .output_data(Bytes::new().pack())
+        .build()
+}
+
+#[test]
+fn test_sighash_single_anyone_can_pay() {
+    let mut env = helper::DaoEnv::new();
+    let listing = seller_listing(&mut env, helper::SIGHASH_SINGLE | helper::SIGHASH_ANYONECANPAY);
+    let tx = buyer_completes(&mut env, listing);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_sighash_single_signed_output_altered() {
+    let mut env = helper::DaoEnv::new();
+    let listing = seller_listing(&mut env, helper::SIGHASH_SINGLE | helper::SIGHASH_ANYONECANPAY);
+    let tx = buyer_completes(&mut env, listing);
+
+    // the buyer tries to pay the seller less
+    let mut outputs: Vec<CellOutput> = tx.outputs().into_iter().collect();
+    outputs[0] = outputs[0].clone().as_builder().capacity(1u64.pack()).build();
+    let tx = tx.as_advanced_builder().set_outputs(outputs).build();
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_WRONG_PUBLIC_KEY).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_sighash_anyone_can_pay_covers_all_outputs() {
+    let mut env = helper::DaoEnv::new();
+    // inputs may be appended, but every output is signed
+    let listing = seller_listing(&mut env, helper::SIGHASH_ANYONECANPAY);
+    let tx = buyer_completes(&mut env, listing);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_WRONG_PUBLIC_KEY).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_sighash_single_covers_all_inputs() {
+    let mut env = helper::DaoEnv::new();
+    // outputs may be appended, but every input is signed
+    let listing = seller_listing(&mut env, helper::SIGHASH_SINGLE);
+    let tx = buyer_completes(&mut env, listing);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_WRONG_PUBLIC_KEY).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_unknown_sighash_mode() {
+    let mut env = helper::DaoEnv::new();
+    let listing = seller_listing(&mut env, helper::SIGHASH_OTX | helper::SIGHASH_SINGLE);
+
+    let err = env.context.verify_tx(&listing, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_UNKNOWN_SIGHASH_MODE).input_lock_script(0)
+    );
+}
+
+// The seller's open transaction: the DAO position goes to the buyer for
+// 1000 shannons paid at output 2, the signature covers `ranges`.
+fn seller_otx(env: &mut helper::DaoEnv, ranges: Option<helper::OtxRanges>) -> TransactionView {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+    let (_, buyer_pubkey) = helper::generate_key_pair();
+    let buyer_pubkey_hash = helper::blake160(&buyer_pubkey.serialize());
+
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env.create_puppet(helper::puppet_data(&pubkey_hash, &[]));
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(0)),
+        (env.puppet_output(), helper::puppet_data(&buyer_pubkey_hash, &[])),
+        (env.plain_output().as_builder().capacity(1000u64.pack()).build(), Bytes::new()),
+    ];
+    let tx = env.build_unsigned_tx(vec![dao_input, puppet_input], outputs);
+    let ranges = ranges.unwrap_or_else(|| helper::OtxRanges::whole(&tx));
+    let domain = env.domain();
+    helper::sign_otx(tx, 0, &privkey, &domain, &ranges)
+}
+
+// the buyer's open transaction pays with its own cell and takes the change
+fn buyer_otx(env: &mut helper::DaoEnv) -> TransactionView {
+    let buyer_input = {
+        let out_point = env.context.create_cell(
+            env.plain_output().as_builder().capacity(5000u64.pack()).build(),
+            Bytes::new(),
+        );
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let change = env.plain_output().as_builder().capacity(3000u64.pack()).build();
+    env.build_unsigned_tx(vec![buyer_input], vec![(change, Bytes::new())])
+}
+
+#[test]
+fn test_otx_merged_after_counterparty() {
+    let mut env = helper::DaoEnv::new();
+    let seller = seller_otx(&mut env, None);
+    let buyer = buyer_otx(&mut env);
+    let tx = helper::merge_otxs(&seller, &buyer);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_otx_merged_before_counterparty() {
+    let mut env = helper::DaoEnv::new();
+    let seller = seller_otx(&mut env, None);
+    let buyer = buyer_otx(&mut env);
+    // the seller's inputs and outputs move, its declared ranges follow
+    let tx = helper::merge_otxs(&buyer, &seller);
+    let ranges = helper::otx_ranges(&tx, 1).expect("otx ranges");
+    assert_eq!(ranges.inputs, 1..3);
+    assert_eq!(ranges.outputs, 1..4);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_otx_covered_output_altered() {
+    let mut env = helper::DaoEnv::new();
+    let seller = seller_otx(&mut env, None);
+    let buyer = buyer_otx(&mut env);
+    let tx = helper::merge_otxs(&seller, &buyer);
+
+    // the payment to the seller is cut
+    let mut outputs: Vec<CellOutput> = tx.outputs().into_iter().collect();
+    outputs[2] = outputs[2].clone().as_builder().capacity(1u64.pack()).build();
+    let tx = tx.as_advanced_builder().set_outputs(outputs).build();
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_WRONG_PUBLIC_KEY).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_otx_range_out_of_bound() {
+    let mut env = helper::DaoEnv::new();
+    let seller = seller_otx(&mut env, None);
+    let buyer = buyer_otx(&mut env);
+    let tx = helper::merge_otxs(&seller, &buyer);
+
+    let mut ranges = helper::otx_ranges(&tx, 0).expect("otx ranges");
+    ranges.outputs = 2..5;
+    let tx = helper::set_otx_ranges(tx, 0, &ranges);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_OTX_RANGE_OUT_OF_BOUND).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_otx_group_not_covered() {
+    let mut env = helper::DaoEnv::new();
+    // the signature covers the puppet but not the DAO cell at input 0
+    let ranges = helper::OtxRanges {
+        inputs: 1..2,
+        outputs: 0..3,
+        cell_deps: 0..4,
+        header_deps: 0..1,
+    };
+    let seller = seller_otx(&mut env, Some(ranges));
+    let buyer = buyer_otx(&mut env);
+    let tx = helper::merge_otxs(&seller, &buyer);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_OTX_GROUP_NOT_COVERED).input_lock_script(0)
+    );
+}
+
+// Withdraw `count` DAO cells at once, the puppet comes last. Returns the
+// cycles consumed, with or without a puppet index hint in the witness.
+fn batch_withdraw_cycles(count: usize, hinted: bool) -> u64 {
+    let mut env = helper::DaoEnv::new();
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut inputs: Vec<CellInput> = (0..count)
+        .map(|_| env.create_dao_cell(helper::dao_data(100)))
+        .collect();
+    inputs.push(env.create_puppet(helper::puppet_data(&pubkey_hash, &[])));
+    let outputs = vec![
+        (env.plain_output().as_builder().capacity((500 * count as u64).pack()).build(), Bytes::new()),
+        (env.puppet_output(), helper::puppet_data(&pubkey_hash, &[])),
+    ];
+    let auth = if hinted {
+        helper::puppet_index_auth(count as u32)
+    } else {
+        Vec::new()
+    };
+    let tx = env.build_tx_with_auth(inputs, outputs, 0, &privkey, &auth);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification")
+}
+
+#[test]
+fn test_puppet_index_hint() {
+    let mut env = helper::DaoEnv::new();
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+    let puppet_data = helper::puppet_data(&pubkey_hash, &[]);
+
+    let dao_input = env.create_dao_cell(helper::dao_data(100));
+    let puppet_input = env.create_puppet(puppet_data.clone());
+    let outputs = vec![
+        (env.plain_output(), Bytes::new()),
+        (env.puppet_output(), puppet_data),
+    ];
+    let auth = helper::puppet_index_auth(1);
+    let tx = env.build_tx_with_auth(vec![dao_input, puppet_input], outputs, 0, &privkey, &auth);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_puppet_index_hint_mismatch() {
+    let mut env = helper::DaoEnv::new();
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+    let puppet_data = helper::puppet_data(&pubkey_hash, &[]);
+
+    let dao_input = env.create_dao_cell(helper::dao_data(100));
+    let puppet_input = env.create_puppet(puppet_data.clone());
+    let outputs = vec![
+        (env.plain_output(), Bytes::new()),
+        (env.puppet_output(), puppet_data),
+    ];
+    // the hint points at the DAO cell
+    let auth = helper::puppet_index_auth(0);
+    let tx = env.build_tx_with_auth(vec![dao_input, puppet_input], outputs, 0, &privkey, &auth);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_PUPPET_INDEX_MISMATCH).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_puppet_index_hint_cycles() {
+    // every withdrawn cell is in the lock's group and is classified either
+    // way, the scan only adds a type hash load per input on top of that
+    const MAX_CYCLES_PER_INPUT: u64 = 45_000;
+
+    let counts = [1u64, 50, 200];
+    let cycles: Vec<(u64, u64)> = counts
+        .iter()
+        .map(|count| (batch_withdraw_cycles(*count as usize, false), batch_withdraw_cycles(*count as usize, true)))
+        .collect();
+    for (count, (scanned, hinted)) in counts.iter().zip(cycles.iter()) {
+        assert!(hinted < scanned, "{} inputs: hinted {} cycles, scanned {}", count, hinted, scanned);
+    }
+
+    let (scanned_one, hinted_one) = cycles[0];
+    for (count, (scanned, hinted)) in counts.iter().zip(cycles.iter()).skip(1) {
+        let per_input = (hinted - hinted_one) / (count - 1);
+        assert!(
+            per_input <= MAX_CYCLES_PER_INPUT,
+            "{} inputs: {} cycles per hinted input",
+            count,
+            per_input
+        );
+        // and the scan costs more per input than the hint
+        assert!(
+            (scanned - scanned_one) > (hinted - hinted_one),
+            "{} inputs: scanned {} cycles, hinted {}",
+            count,
+            scanned,
+            hinted
+        );
+    }
+
+    // the saving grows with the batch
+    let saved: Vec<u64> = cycles.iter().map(|(scanned, hinted)| scanned - hinted).collect();
+    assert!(saved[0] < saved[1] && saved[1] < saved[2], "saved cycles {:?}", saved);
+}
+
+// A withdrawal whose puppet is held by a secp256k1 lock, returns the cycles.
+// With `recovered` the ownership lock also gets its own signature in the
+// puppet's input type and recovers it, which is what it did before it learned
+// to trust the puppet lock's signature.
+fn secp256k1_puppet_withdraw_cycles(recovered: bool) -> u64 {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let puppet_data = helper::puppet_data(&pubkey_hash, &[]);
+    let puppet_lock = env.secp256k1_lock_script(&pubkey_hash);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env.create_puppet_with_lock(puppet_lock.clone(), puppet_data.clone());
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output().as_builder().lock(puppet_lock).build(), puppet_data),
+    ];
+    let mut tx = env.build_unsigned_tx(vec![dao_input, puppet_input], outputs);
+    if recovered {
+        let domain = env.domain();
+        tx = helper::sign_puppet_input_type(tx, 1, &privkey, &domain, &[]);
+    }
+    let tx = helper::sign_sighash_all(tx, &[1], &privkey);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification")
+}
+
+#[test]
+fn test_secp256k1_puppet_skips_recovery() {
+    let before = secp256k1_puppet_withdraw_cycles(true);
+    let after = secp256k1_puppet_withdraw_cycles(false);
+
+    // recovery, the library and its 1 MB table dominate the ownership lock,
+    // without them the transaction costs little more than the puppet lock
+    assert!(
+        after < before && (before - after) * 3 > before,
+        "secp256k1 puppet withdrawal: {} cycles recovered, {} cycles delegated",
+        before,
+        after
+    );
+}
+
+// The owner's withdrawal, signed with a bare signature or with the auth kind
+// byte, returns the cycles. Only the second copies the 1 MB table.
+fn owner_withdraw_cycles(bare: bool) -> u64 {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_tx(&mut env, &pubkey_hash);
+    let domain = env.domain();
+    let tx = if bare {
+        helper::sign_bare(tx, &[0], &privkey, &domain)
+    } else {
+        helper::prepare_witnesses(tx, 0, &privkey, &domain)
+    };
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification")
+}
+
+#[test]
+fn test_bare_signature_skips_table_copy() {
+    // copying the 1 MB table takes at least a load and a store per 8 bytes,
+    // 262,144 cycles, with some room for the rest of the two paths
+    const MIN_SAVED_CYCLES: u64 = 200_000;
+
+    let before = owner_withdraw_cycles(false);
+    let after = owner_withdraw_cycles(true);
+
+    // both recover one signature, the bare one without copying the table
+    assert!(
+        after + MIN_SAVED_CYCLES <= before,
+        "owner withdrawal: {} cycles with the table copy, {} cycles bare",
+        before,
+        after
+    );
+}
+
+#[test]
+fn test_secp256k1_library_missing() {
+    let mut env = helper::DaoEnv::new();
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let tx = withdraw_tx(&mut env, &helper::blake160(&pubkey.serialize()));
+    let tx = helper::prepare_witnesses(tx, 0, &privkey, &env.domain());
+
+    // drop the cell dep of the secp256k1 library
+    let secp256k1_dep = CellDep::new_builder()
+        .out_point(env.secp256k1_out_point.clone())
+        .build();
+    let cell_deps: Vec<CellDep> = tx
+        .cell_deps()
+        .into_iter()
+        .filter(|dep| dep.as_slice() != secp256k1_dep.as_slice())
+        .collect();
+    let tx = tx.as_advanced_builder().set_cell_deps(cell_deps).build();
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_LIBRARY_NOT_FOUND).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_secp256k1_unparsable_signature() {
+    let mut env = helper::DaoEnv::new();
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let tx = withdraw_tx(&mut env, &helper::blake160(&pubkey.serialize()));
+    let tx = helper::prepare_witnesses(tx, 0, &privkey, &env.domain());
+
+    // r overflows the curve order
+    let witness = WitnessArgs::from_slice(&tx.witnesses().get(0).unwrap().raw_data()).unwrap();
+    let mut lock: Vec<u8> = witness.lock().to_opt().unwrap().raw_data().to_vec();
+    lock[..32].copy_from_slice(&[0xffu8; 32]);
+    let witness = witness
+        .as_builder()
+        .lock(Some(Bytes::from(lock)).pack())
+        .build();
+    let tx = tx
+        .as_advanced_builder()
+        .set_witnesses(vec![witness.as_bytes().pack(), Bytes::new().pack()])
+        .build();
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_SECP_PARSE_SIGNATURE).input_lock_script(0)
+    );
+}
+
+// the lock args after the puppet type hash which point at the library
+fn locator_args(library: LibraryLocator) -> Vec<u8> {
+    let args = LockArgs { library, ..LockArgs::new([0u8; 32]) };
+    args.to_vec().split_off(PUPPET_TYPE_HASH_LEN)
+}
+
+fn withdraw_with_locator(env: &mut helper::DaoEnv, locator: Vec<u8>) -> TransactionView {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    env.lock_args_suffix = Bytes::from(locator);
+    let tx = withdraw_tx(env, &helper::blake160(&pubkey.serialize()));
+    let domain = env.domain();
+    helper::prepare_witnesses(tx, 0, &privkey, &domain)
+}
+
+#[test]
+fn test_secp256k1_by_type_id() {
+    let mut env = helper::DaoEnv::new();
+    let type_hash = env.use_secp256k1_type_id();
+    let tx = withdraw_with_locator(&mut env, locator_args(LibraryLocator::TypeHash(type_hash)));
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_secp256k1_by_cell_dep_index() {
+    let mut env = helper::DaoEnv::new();
+    let type_hash = env.use_secp256k1_type_id();
+    // the library is the third cell dep
+    let tx = withdraw_with_locator(&mut env, locator_args(LibraryLocator::CellDep { type_hash, index: 2 }));
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_secp256k1_wrong_cell_dep_index() {
+    let mut env = helper::DaoEnv::new();
+    let type_hash = env.use_secp256k1_type_id();
+    // the lock script itself is the first cell dep
+    let tx = withdraw_with_locator(&mut env, locator_args(LibraryLocator::CellDep { type_hash, index: 0 }));
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_LIBRARY_NOT_FOUND).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_secp256k1_type_id_not_deployed() {
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_with_locator(&mut env, locator_args(LibraryLocator::TypeHash([0x42u8; 32])));
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_LIBRARY_NOT_FOUND).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_invalid_library_locator() {
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_with_locator(&mut env, vec![0x03]);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_NO_VALID_ARGS).input_lock_script(0)
+    );
+}
+
+fn withdraw_with_pubkey(env: &mut helper::DaoEnv, uncompressed: bool) -> TransactionView {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let tx = withdraw_tx(env, &helper::blake160(&pubkey.serialize()));
+    let pubkey = if uncompressed {
+        helper::uncompressed_pubkey(&pubkey)
+    } else {
+        pubkey.serialize()
+    };
+    let domain = env.domain();
+    helper::prepare_witnesses_with_pubkey(tx, 0, &privkey, &domain, &pubkey)
+}
+
+fn replace_lock(tx: TransactionView, lock: Vec<u8>) -> TransactionView {
+    let witness = WitnessArgs::from_slice(&tx.witnesses().get(0).unwrap().raw_data()).unwrap_or_default();
+    let witness = witness
+        .as_builder()
+        .lock(Some(Bytes::from(lock)).pack())
+        .build();
+    tx.as_advanced_builder()
+        .set_witnesses(vec![witness.as_bytes().pack(), Bytes::new().pack()])
+        .build()
+}
+
+fn lock_of(tx: &TransactionView) -> Vec<u8> {
+    let witness = WitnessArgs::from_slice(&tx.witnesses().get(0).unwrap().raw_data()).unwrap();
+    witness.lock().to_opt().unwrap().raw_data().to_vec()
+}
+
+#[test]
+fn test_compact_signature_compressed_pubkey() {
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_with_pubkey(&mut env, false);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_compact_signature_uncompressed_pubkey() {
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_with_pubkey(&mut env, true);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_compact_signature_other_pubkey() {
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_with_pubkey(&mut env, false);
+
+    // the owner's signature, with somebody else's key
+    let (_, other) = helper::generate_key_pair();
+    let mut lock = lock_of(&tx);
+    lock.truncate(66);
+    lock.extend_from_slice(&other.serialize());
+    let tx = replace_lock(tx, lock);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_SECP_VERIFICATION).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_compact_signature_malformed() {
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_with_pubkey(&mut env, true);
+    let lock = lock_of(&tx);
+
+    // the padding byte must be zero
+    let mut padded = lock.clone();
+    padded[64] = 1;
+    // an unknown pubkey prefix
+    let mut prefixed = lock.clone();
+    prefixed[66] = 0x05;
+    // an uncompressed pubkey cut short
+    let mut truncated = lock;
+    truncated.truncate(66 + 33);
+
+    for lock in vec![padded, prefixed, truncated] {
+        let tx = replace_lock(tx.clone(), lock);
+        let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+        assert_error_eq!(
+            err,
+            ScriptError::ValidationFailure(ERROR_INVALID_WITNESS_PUBKEY).input_lock_script(0)
+        );
+    }
+}
+
+// The Ethereum and RSA tests need a lock built with the `eth` and `rsa`
+// features, which stay off by default until ckb-lib-eth and ckb-lib-rsa pin
+// their libraries. Run them with `cargo test -- --ignored` against such a build.
+
+#[test]
+#[ignore]
+fn test_eth_library_not_deployed() {
+    let mut env = helper::DaoEnv::new();
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let tx = withdraw_tx(&mut env, &helper::eth_address(&pubkey));
+    let domain = env.domain();
+    let tx = helper::prepare_witnesses_with_auth(tx, 0, &privkey, &domain, &[helper::AUTH_ETH]);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_LIBRARY_NOT_FOUND).input_lock_script(0)
+    );
+}
+
+#[test]
+#[ignore]
+fn test_eth_signature() {
+    let mut env = helper::DaoEnv::new();
+    env.deploy_library(helper::ETH_LIBRARY);
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let tx = withdraw_tx(&mut env, &helper::eth_address(&pubkey));
+    let domain = env.domain();
+    let tx = helper::prepare_witnesses_with_auth(tx, 0, &privkey, &domain, &[helper::AUTH_ETH]);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+#[ignore]
+fn test_eth_by_type_id() {
+    let mut env = helper::DaoEnv::new();
+    let type_hash = env.deploy_library_with_type_id(helper::ETH_LIBRARY, 0x43);
+    // the locator names the Ethereum library, which the lock requires
+    let args = LockArgs {
+        library: LibraryLocator::TypeHash(type_hash),
+        auth_kind: Some(helper::AUTH_ETH),
+        ..LockArgs::new([0u8; 32])
+    };
+    env.lock_args_suffix = Bytes::from(args.to_vec().split_off(PUPPET_TYPE_HASH_LEN));
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let tx = withdraw_tx(&mut env, &helper::eth_address(&pubkey));
+    let domain = env.domain();
+    let tx = helper::prepare_witnesses_with_auth(tx, 0, &privkey, &domain, &[helper::AUTH_ETH]);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+/// an RSA witness: zero signature field, the auth kind and the RSA signature
+fn rsa_lock(rsa_signature: &[u8]) -> Vec<u8> {
+    let mut lock_witness = LockWitness::new([0u8; helper::SIGNATURE_LEN]);
+    lock_witness.auth = Auth::Rsa(rsa_signature.to_vec());
+    lock_witness.to_vec().expect("lock witness")
+}
+
+#[test]
+#[ignore]
+fn test_rsa_library_not_deployed() {
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_tx(&mut env, &[0x11; 20]);
+    let tx = replace_lock(tx, rsa_lock(&[0x42; 520]));
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_LIBRARY_NOT_FOUND).input_lock_script(0)
+    );
+}
+
+/// a withdrawal for the RSA key `owner`, signed by `signer`
+fn rsa_withdraw_tx(env: &mut helper::DaoEnv, owner: &[u8], signer: &PKey<Private>) -> TransactionView {
+    let tx = withdraw_tx(env, &helper::blake160(owner));
+    let message = env.domain().signing_message(&tx);
+    replace_lock(tx, rsa_lock(&helper::rsa_sign(signer, message.as_bytes())))
+}
+
+#[test]
+#[ignore]
+fn test_rsa_signature() {
+    let mut env = helper::DaoEnv::new();
+    env.deploy_library(helper::RSA_LIBRARY);
+    let key = helper::generate_rsa_key();
+    let tx = rsa_withdraw_tx(&mut env, &helper::rsa_pubkey(&key), &key);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+#[ignore]
+fn test_rsa_signature_of_another_key() {
+    let mut env = helper::DaoEnv::new();
+    env.deploy_library(helper::RSA_LIBRARY);
+    let owner = helper::generate_rsa_key();
+    let tx = rsa_withdraw_tx(&mut env, &helper::rsa_pubkey(&owner), &helper::generate_rsa_key());
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_WRONG_PUBLIC_KEY).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_rsa_and_eth_malformed() {
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_tx(&mut env, &[0x11; 20]);
+
+    // a signature field next to the RSA signature
+    let mut signed = rsa_lock(&[0x42; 520]);
+    signed[0] = 1;
+    // no RSA signature
+    let empty = rsa_lock(&[]);
+    // an Ethereum signature with a payload
+    let eth_payload = [&[0x42u8; helper::SIGNATURE_LEN][..], &[helper::AUTH_ETH, 0]].concat();
+
+    for lock in vec![signed, empty, eth_payload] {
+        let tx = replace_lock(tx.clone(), lock);
+        let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+        assert_error_eq!(
+            err,
+            ScriptError::ValidationFailure(ERROR_INVALID_AUTH_PAYLOAD).input_lock_script(0)
+        );
+    }
+
+    let tx = replace_lock(tx, [&[0x42u8; helper::SIGNATURE_LEN][..], &[0x07]].concat());
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_UNKNOWN_AUTH_KIND).input_lock_script(0)
+    );
+}
+
+// A transaction whose output 1 carries the test type script, which asks the
+// ownership library whether the puppet's owner signed. The puppet is input 0,
+// with `puppet_lock` or always success.
+fn puppet_owner_tx(env: &mut helper::DaoEnv, owner_hash: &[u8; 20], puppet_lock: Option<Script>) -> (TransactionView, Script) {
+    let owner_type_script = env.puppet_owner_type_script();
+    let puppet_data = helper::puppet_data(owner_hash, &[]);
+    let (puppet_input, puppet_output) = match puppet_lock {
+        Some(lock) => (
+            env.create_puppet_with_lock(lock.clone(), puppet_data.clone()),
+            env.puppet_output().as_builder().lock(lock).build(),
+        ),
+        None => (env.create_puppet(puppet_data.clone()), env.puppet_output()),
+    };
+    let typed_output = env
+        .plain_output()
+        .as_builder()
+        .type_(Some(owner_type_script.clone()).pack())
+        .build();
+    let outputs = vec![(puppet_output, puppet_data), (typed_output, Bytes::new())];
+    (env.build_unsigned_tx(vec![puppet_input], outputs), owner_type_script)
+}
+
+#[test]
+fn test_library_verifies_puppet_owner() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let (tx, owner_type_script) = puppet_owner_tx(&mut env, &pubkey_hash, None);
+    // the owner signs for the calling script, not the ownership lock
+    let domain = helper::SigningDomain::new(&env.genesis.hash(), &owner_type_script, &env.puppet_type_script);
+    let tx = helper::sign_puppet_input_type(tx, 0, &privkey, &domain, &[]);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_library_puppet_owner_by_puppet_lock() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let puppet_lock = env.secp256k1_lock_script(&pubkey_hash);
+    let (tx, _) = puppet_owner_tx(&mut env, &pubkey_hash, Some(puppet_lock));
+    let tx = helper::sign_sighash_all(tx, &[0], &privkey);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_library_rejects_stranger() {
+    let (_, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+    let (wrong_privkey, _) = helper::generate_key_pair();
+
+    let mut env = helper::DaoEnv::new();
+    let (tx, owner_type_script) = puppet_owner_tx(&mut env, &pubkey_hash, None);
+    let domain = helper::SigningDomain::new(&env.genesis.hash(), &owner_type_script, &env.puppet_type_script);
+    let tx = helper::sign_puppet_input_type(tx, 0, &wrong_privkey, &domain, &[]);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_WRONG_PUBLIC_KEY).output_type_script(1)
+    );
+}
+
+#[test]
+fn test_library_signature_bound_to_caller() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let (tx, _) = puppet_owner_tx(&mut env, &pubkey_hash, None);
+    // a signature for the ownership lock doesn't authorize another script
+    let domain = env.domain();
+    let tx = helper::sign_puppet_input_type(tx, 0, &privkey, &domain, &[]);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_WRONG_PUBLIC_KEY).output_type_script(1)
+    );
+}
+
+/// lock args with everything but the puppet type hash taken from `args`
+fn versioned_args(env: &mut helper::DaoEnv, args: LockArgs) {
+    env.lock_args_suffix = Bytes::from(args.to_vec().split_off(PUPPET_TYPE_HASH_LEN));
+}
+
+#[test]
+fn test_args_required_auth_kind() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    for &(auth_kind, expected) in &[(AUTH_PUBKEY_HASH, None), (helper::AUTH_ETH, Some(ERROR_AUTH_KIND_NOT_ALLOWED))] {
+        let mut env = helper::DaoEnv::new();
+        versioned_args(&mut env, LockArgs { auth_kind: Some(auth_kind), ..LockArgs::new([0u8; 32]) });
+        let tx = withdraw_tx(&mut env, &pubkey_hash);
+        let domain = env.domain();
+        let tx = helper::prepare_witnesses(tx, 0, &privkey, &domain);
+
+        let result = env.context.verify_tx(&tx, MAX_CYCLES);
+        match expected {
+            None => {
+                result.expect("pass verification");
+            },
+            Some(code) => assert_error_eq!(
+                result.unwrap_err(),
+                ScriptError::ValidationFailure(code).input_lock_script(0)
+            ),
+        }
+    }
+}
+
+fn lock_period_tx(env: &mut helper::DaoEnv, key: &Privkey, owner_hash: &[u8; 20], since: u64) -> TransactionView {
+    versioned_args(env, LockArgs { lock_period: Some(180), ..LockArgs::new([0u8; 32]) });
+    let puppet_data = helper::puppet_data(owner_hash, &[]);
+    let dao_input = env
+        .create_dao_cell(helper::dao_data(0))
+        .as_builder()
+        .since(since.pack())
+        .build();
+    let puppet_input = env.create_puppet(puppet_data.clone());
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output(), puppet_data),
+    ];
+    env.build_tx(vec![dao_input, puppet_input], outputs, 0, key)
+}
+
+#[test]
+fn test_lock_period_active() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let tx = lock_period_tx(&mut env, &privkey, &pubkey_hash, helper::since_relative_epoch(179));
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_LOCK_PERIOD_ACTIVE).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_lock_period_elapsed() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let tx = lock_period_tx(&mut env, &privkey, &pubkey_hash, helper::since_relative_epoch(180));
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+/// a phase-1 withdrawal proving ownership with the puppet as a cell dep
+fn cell_dep_proof_tx(env: &mut helper::DaoEnv, owner_hash: &[u8; 20]) -> TransactionView {
+    let puppet_dep = env.puppet_cell_dep(helper::puppet_data(owner_hash, &[]));
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let outputs = vec![(env.dao_output(), helper::dao_data(100))];
+    let tx = env.build_unsigned_tx(vec![dao_input], outputs);
+    tx.as_advanced_builder().cell_dep(puppet_dep).build()
+}
+
+#[test]
+fn test_cell_dep_proof() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    versioned_args(&mut env, LockArgs { cell_dep_proof: true, ..LockArgs::new([0u8; 32]) });
+    let tx = cell_dep_proof_tx(&mut env, &pubkey_hash);
+    let domain = env.domain();
+    let tx = helper::prepare_witnesses(tx, 0, &privkey, &domain);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+#[test]
+fn test_cell_dep_proof_not_allowed() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    // legacy args only look at the inputs
+    let mut env = helper::DaoEnv::new();
+    let tx = cell_dep_proof_tx(&mut env, &pubkey_hash);
+    let domain = env.domain();
+    let tx = helper::prepare_witnesses(tx, 0, &privkey, &domain);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_NO_PUPPET_CELL_FOUND).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_cell_dep_proof_partial_signature() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    versioned_args(&mut env, LockArgs { cell_dep_proof: true, ..LockArgs::new([0u8; 32]) });
+    let tx = cell_dep_proof_tx(&mut env, &pubkey_hash);
+    let domain = env.domain();
+    // the puppet index means nothing without a puppet input
+    let tx = helper::sign_with_sighash(tx, 0, &privkey, &domain, helper::SIGHASH_SINGLE, &[0], 0);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_UNKNOWN_SIGHASH_MODE).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_molecule_witness() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_tx(&mut env, &pubkey_hash);
+    let domain = env.domain();
+    let tx = helper::prepare_molecule_witnesses(tx, 0, &privkey, &domain, |builder| builder);
+
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+}
+
+/// a withdrawal signed with a molecule witness `build` breaks fails with `code`
+fn assert_molecule_witness_refused<F>(build: F, code: i8)
+where
+    F: FnOnce(helper::MoleculeWitnessBuilder) -> helper::MoleculeWitnessBuilder,
+{
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_tx(&mut env, &pubkey_hash);
+    let domain = env.domain();
+    let tx = helper::prepare_molecule_witnesses(tx, 0, &privkey, &domain, build);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(code).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_molecule_witness_malformed() {
+    // a merkle auth without its proof
+    assert_molecule_witness_refused(|builder| builder.flags(AUTH_MERKLE), ERROR_INVALID_AUTH_PAYLOAD);
+    // a pubkey hash auth with a salt
+    let salt = mol::Byte32::new_unchecked(Bytes::from(vec![0x42; 32]));
+    assert_molecule_witness_refused(
+        |builder| builder.proof(Some(mol::AuthProofUnion::Byte32(salt))),
+        ERROR_INVALID_AUTH_PAYLOAD,
+    );
+    // a pubkey that isn't one
+    assert_molecule_witness_refused(
+        |builder| builder.pubkey(Some(&[0x05; 33][..])),
+        ERROR_INVALID_WITNESS_PUBKEY,
+    );
+    // the options already say whether there's a pubkey
+    assert_molecule_witness_refused(|builder| builder.flags(FLAG_PUBKEY), ERROR_WITNESS_MOLECULE_ENCODING);
+}
+
+#[test]
+fn test_molecule_witness_broken_table() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_tx(&mut env, &pubkey_hash);
+    let domain = env.domain();
+    let tx = helper::prepare_molecule_witnesses(tx, 0, &privkey, &domain, |builder| builder);
+
+    // the first offset of the table says there's a single field
+    let mut lock = lock_of(&tx);
+    lock[helper::SIGNATURE_LEN + 1 + 4] = 8;
+    let tx = replace_lock(tx, lock);
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_WITNESS_MOLECULE_ENCODING).input_lock_script(0)
+    );
+}
+
+#[test]
+fn test_lock_witness_too_short() {
+    let mut env = helper::DaoEnv::new();
+    let tx = withdraw_tx(&mut env, &[0x11; 20]);
+    let tx = replace_lock(tx, vec![0x42; helper::SIGNATURE_LEN - 1]);
+
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_WITNESS_TOO_SHORT).input_lock_script(0)
+    );
+}
+
+fn trace_names(env: &helper::DaoEnv) -> Vec<String> {
+    helper::trace_events(&env.context)
+        .into_iter()
+        .map(|event| event.name)
+        .collect()
+}
+
+#[test]
+fn test_trace_withdraw() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    env.use_trace_lock();
+    let tx = withdraw_tx(&mut env, &pubkey_hash);
+    let domain = env.domain();
+    let tx = helper::prepare_witnesses(tx, 0, &privkey, &domain);
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+
+    assert_eq!(trace_names(&env), vec!["witness", "scan", "puppet", "signer", "decision"]);
+    let events = helper::trace_events(&env.context);
+    assert_eq!(events[0].get("kind"), Some("0"));
+    assert_eq!(events[1].get("puppets"), Some("1"));
+    assert_eq!(events[2].get("index"), Some("1"));
+    assert_eq!(events[2].get("source"), Some("input"));
+    let hash = helper::hex(&pubkey_hash);
+    assert_eq!(events[3].get("hash"), Some(hash.as_str()));
+    assert_eq!(events[3].get("delegated"), Some("false"));
+    assert_eq!(events[4].get("allow"), Some("true"));
+    assert_eq!(events[4].get("code"), Some("0"));
+}
+
+#[test]
+fn test_trace_refused() {
+    let (_, pubkey) = helper::generate_key_pair();
+    let (wrong_privkey, wrong_pubkey) = helper::generate_key_pair();
+
+    let mut env = helper::DaoEnv::new();
+    env.use_trace_lock();
+    let tx = withdraw_tx(&mut env, &helper::blake160(&pubkey.serialize()));
+    let domain = env.domain();
+    let tx = helper::prepare_witnesses(tx, 0, &wrong_privkey, &domain);
+    let err = env.context.verify_tx(&tx, MAX_CYCLES).unwrap_err();
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(ERROR_WRONG_PUBLIC_KEY).input_lock_script(0)
+    );
+
+    let events = helper::trace_events(&env.context);
+    let signer = events.iter().find(|event| event.name == "signer").expect("signer");
+    let hash = helper::hex(&helper::blake160(&wrong_pubkey.serialize()));
+    assert_eq!(signer.get("hash"), Some(hash.as_str()));
+    let decision = events.last().expect("decision");
+    assert_eq!(decision.name, "decision");
+    assert_eq!(decision.get("allow"), Some("false"));
+    let code = ERROR_WRONG_PUBLIC_KEY.to_string();
+    assert_eq!(decision.get("code"), Some(code.as_str()));
+}
+
+#[test]
+fn test_trace_delegated_signature() {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+
+    let mut env = helper::DaoEnv::new();
+    env.use_trace_lock();
+    let puppet_data = helper::puppet_data(&pubkey_hash, &[]);
+    let puppet_lock = env.secp256k1_lock_script(&pubkey_hash);
+    let dao_input = env.create_dao_cell(helper::dao_data(0));
+    let puppet_input = env.create_puppet_with_lock(puppet_lock.clone(), puppet_data.clone());
+    let outputs = vec![
+        (env.dao_output(), helper::dao_data(100)),
+        (env.puppet_output().as_builder().lock(puppet_lock).build(), puppet_data),
+    ];
+    let tx = env.build_unsigned_tx(vec![dao_input, puppet_input], outputs);
+    let tx = helper::sign_sighash_all(tx, &[1], &privkey);
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+
+    // no lock witness of its own, the puppet lock's signer is taken
+    assert_eq!(trace_names(&env), vec!["scan", "puppet", "signer", "decision"]);
+    let signer = &helper::trace_events(&env.context)[2];
+    let hash = helper::hex(&pubkey_hash);
+    assert_eq!(signer.get("hash"), Some(hash.as_str()));
+    assert_eq!(signer.get("delegated"), Some("true"));
+}
+
+#[test]
+fn test_default_lock_without_trace() {
+    let contains_prefix = |bin: &Bytes| {
+        bin.windows(helper::TRACE_PREFIX.len())
+            .any(|window| window == helper::TRACE_PREFIX.as_bytes())
+    };
+    // checks the release binary under CAPSULE_TEST_ENV=release
+    let loader = Loader::default();
+    assert!(!contains_prefix(&loader.load_binary("nervos-dao-extended-ownership-script")));
+    assert!(contains_prefix(&loader.load_binary(helper::TRACE_LOCK)));
+
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let mut env = helper::DaoEnv::new();
+    env.context.set_capture_debug(true);
+    let tx = withdraw_tx(&mut env, &helper::blake160(&pubkey.serialize()));
+    let domain = env.domain();
+    let tx = helper::prepare_witnesses(tx, 0, &privkey, &domain);
+    env.context
+        .verify_tx(&tx, MAX_CYCLES)
+        .expect("pass verification");
+    assert!(helper::trace_events(&env.context).is_empty());
+}
+
+/// the auth a probe transaction is signed with, see `auth_probe`
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum AuthProbe {
+    Secp256k1,
+    Eth,
+    Rsa,
+    OwnerLock,
+}
+
+const AUTH_PROBES: [AuthProbe; 4] = [AuthProbe::Secp256k1, AuthProbe::Eth, AuthProbe::Rsa, AuthProbe::OwnerLock];
+
+/// A withdrawal signed with `probe`, with the library verifying it deployed,
+/// and the error of a lock which verifies that auth.
+fn auth_probe(env: &mut helper::DaoEnv, probe: AuthProbe) -> (TransactionView, Option<i8>) {
+    let (privkey, pubkey) = helper::generate_key_pair();
+    let pubkey_hash = helper::blake160(&pubkey.serialize());
+    match probe {
+        AuthProbe::Secp256k1 => {
+            let tx = withdraw_tx(env, &pubkey_hash);
+            let domain = env.domain();
+            (helper::prepare_witnesses(tx, 0, &privkey, &domain), None)
+        },
+        AuthProbe::Eth => {
+            env.deploy_library(helper::ETH_LIBRARY);
+            let tx = withdraw_tx(env, &helper::eth_address(&pubkey));
+            let domain = env.domain();
+            let tx = helper::prepare_witnesses_with_auth(tx, 0, &privkey, &domain, &[helper::AUTH_ETH]);
+            (tx, None)
+        },
+        AuthProbe::Rsa => {
+            env.deploy_library(helper::RSA_LIBRARY);
+            let key = helper::generate_rsa_key();
+            (rsa_withdraw_tx(env, &helper::rsa_pubkey(&key), &key), None)
+        },
+        AuthProbe::OwnerLock => {
+            let puppet_data = helper::puppet_data(&pubkey_hash, &[]);
+            let puppet_lock = env.secp256k1_lock_script(&pubkey_hash);
+            let dao_input = env.create_dao_cell(helper::dao_data(0));
+            let puppet_input = env.create_puppet_with_lock(puppet_lock.clone(), puppet_data.clone());
+            let outputs = vec![
+                (env.dao_output(), helper::dao_data(100)),
+                (env.puppet_output().as_builder().lock(puppet_lock).build(), puppet_data),
+            ];
+            let tx = env.build_unsigned_tx(vec![dao_input, puppet_input], outputs);
+            (helper::sign_sighash_all(tx, &[1], &privkey), None)
+        },
+    }
+}
+
+/// Every probe against the lock build `name`, those it doesn't support
+/// have to fail with `UnsupportedAuth`.
+fn check_lock_variant(name: &str, supported: &[AuthProbe]) {
+    for &probe in AUTH_PROBES.iter() {
+        let mut env = helper::DaoEnv::new();
+        env.use_lock_binary(name);
+        let (tx, error) = auth_probe(&mut env, probe);
+        let error = if supported.contains(&probe) {
+            error
+        } else {
+            Some(ERROR_UNSUPPORTED_AUTH)
+        };
+
+        let result = env.context.verify_tx(&tx, MAX_CYCLES);
+        match error {
+            None => {
+                if let Err(err) = result {
+                    panic!("{} with {:?} should pass: {}", name, probe, err);
+                }
+            },
+            Some(code) => {
+                let expected: Error = ScriptError::ValidationFailure(code).input_lock_script(0).into();
+                let err = result.expect_err(&format!("{} with {:?} should fail", name, probe));
+                assert_eq!(err.to_string(), expected.to_string(), "{} with {:?}", name, probe);
+            },
+        }
+    }
+}
+
+#[test]
+fn test_variant_default() {
+    check_lock_variant("nervos-dao-extended-ownership-script", &[AuthProbe::Secp256k1, AuthProbe::OwnerLock]);
+}
+
+#[test]
+fn test_variant_secp256k1() {
+    check_lock_variant("nervos-dao-extended-ownership-secp256k1", &[AuthProbe::Secp256k1]);
+}